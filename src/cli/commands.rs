@@ -24,6 +24,15 @@ pub enum Action {
 
     /// Generate configuration file template
     Config(ConfigOptions),
+
+    /// Run a Stratum proxy sharing one pool connection between many miners
+    Proxy(ProxyOptions),
+
+    /// Replay a recorded pool session offline and verify its shares
+    Replay(ReplayOptions),
+
+    /// Run a local pool simulator issuing synthetic jobs
+    PoolSim(PoolSimOptions),
 }
 
 /// Options for starting the mining operation
@@ -56,6 +65,10 @@ pub struct BenchmarkOptions {
     /// Number of threads to use
     #[arg(short, long, default_value_t = num_cpus::get())]
     pub threads: usize,
+
+    /// File the measured hashrate is recorded in (advertised to pools on login)
+    #[arg(short, long, default_value = "benchmark.json")]
+    pub output: PathBuf,
 }
 
 /// Options for generating configuration files
@@ -73,3 +86,58 @@ pub struct ConfigOptions {
     #[arg(short, long)]
     pub node: bool,
 }
+
+/// Options for running the Stratum proxy
+#[derive(Parser, Debug)]
+pub struct ProxyOptions {
+    /// Path to configuration file (the `[mode.pool]` section is used upstream)
+    #[arg(short, long, default_value = "config.toml")]
+    pub config: PathBuf,
+
+    /// Address to listen on for downstream miners
+    #[arg(short, long, default_value = "0.0.0.0:3333")]
+    pub bind: String,
+}
+
+/// Options for replaying a recorded pool session
+#[derive(Parser, Debug)]
+pub struct ReplayOptions {
+    /// Recording written with the pool `record_file` option
+    pub file: PathBuf,
+
+    /// Seconds to mine each recorded job (0 only re-verifies recorded shares)
+    #[arg(short, long, default_value_t = 10)]
+    pub job_time: u64,
+
+    /// Number of threads to use
+    #[arg(short, long, default_value_t = num_cpus::get())]
+    pub threads: usize,
+}
+
+/// Options for running the pool simulator
+#[derive(Parser, Debug)]
+pub struct PoolSimOptions {
+    /// Address to listen on for miners
+    #[arg(short, long, default_value = "127.0.0.1:3333")]
+    pub bind: String,
+
+    /// Algorithm of the synthetic jobs
+    #[arg(short, long, default_value = "randomx")]
+    pub algorithm: AlgorithmType,
+
+    /// Share difficulty handed to miners
+    #[arg(short, long, default_value_t = 1000)]
+    pub difficulty: u64,
+
+    /// Seconds between new jobs
+    #[arg(short, long, default_value_t = 30)]
+    pub job_interval: u64,
+
+    /// Close every connection after this many seconds
+    #[arg(long)]
+    pub disconnect_after: Option<u64>,
+
+    /// Answer every Nth valid share with an injected pool error
+    #[arg(long)]
+    pub error_every: Option<u64>,
+}
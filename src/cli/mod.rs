@@ -1,6 +1,9 @@
 // src/cli/mod.rs
 //! Command-line interface definitions
 
-pub use commands::{Action, BenchmarkOptions, Commands, ConfigOptions, StartOptions};
+pub use commands::{
+    Action, BenchmarkOptions, Commands, ConfigOptions, PoolSimOptions, ProxyOptions, ReplayOptions,
+    StartOptions,
+};
 
 mod commands;
@@ -1,5 +1,9 @@
 // src/config/config.rs
 use crate::{
+    miner::{
+        CpuAffinity, features::RandomXOptions, foreign_load::ForeignLoadConfig, priority::Priority,
+        thermal::ThermalConfig,
+    },
     network::{node::NodeConfig, pool::PoolConfig},
     utils::error::MinerError,
 };
@@ -18,7 +22,7 @@ pub struct Config {
     pub algorithm: String,
 
     /// Number of worker threads to use for mining
-    /// (default: number of CPU cores)
+    /// (default: 0 = sized to the CPU caches for the selected algorithm)
     #[serde(default = "default_worker_threads")]
     pub worker_threads: usize,
 
@@ -27,6 +31,45 @@ pub struct Config {
     #[serde(default = "default_batch_size")]
     pub batch_size: u64,
 
+    /// Nonce byte reserved for this rig when several rigs share one pool
+    /// login; fixed below any pool-assigned prefix (default: none)
+    #[serde(default)]
+    pub instance_id: Option<u8>,
+
+    /// Recompute every share with an independent verifier before submitting
+    /// it; mismatches are counted as hardware errors (default: true)
+    #[serde(default = "default_verify_shares")]
+    pub verify_shares: bool,
+
+    /// Logical CPUs to pin mining threads to, or "auto" for one thread per
+    /// physical core first (default: unpinned)
+    #[serde(default)]
+    pub cpu_affinity: Option<CpuAffinity>,
+
+    /// Scheduling priority of mining and dataset initialization threads:
+    /// "normal", "low" (nice 19) or "idle" (SCHED_IDLE) (default: normal)
+    #[serde(default)]
+    pub priority: Priority,
+
+    /// Optimized RandomX code paths to allow; disabled paths use the
+    /// software fallback (default: all the CPU supports)
+    #[serde(default)]
+    pub randomx: RandomXOptions,
+
+    /// Throttle mining when the CPU runs hot (default: disabled)
+    #[serde(default)]
+    pub thermal: Option<ThermalConfig>,
+
+    /// Maximum share of the host's total CPU time the miner may use, in
+    /// percent; enforced by duty-cycling (default: uncapped)
+    #[serde(default)]
+    pub max_cpu_percent: Option<f32>,
+
+    /// Pause or shrink mining while other processes load the CPU
+    /// (default: disabled)
+    #[serde(default)]
+    pub foreign_load: Option<ForeignLoadConfig>,
+
     /// Mining mode configuration (pool or node)
     pub mode: MiningMode,
 }
@@ -50,13 +93,17 @@ fn default_algorithm() -> String {
 }
 
 fn default_worker_threads() -> usize {
-    num_cpus::get()
+    0
 }
 
 fn default_batch_size() -> u64 {
     1000
 }
 
+fn default_verify_shares() -> bool {
+    true
+}
+
 impl Config {
     /// Loads configuration from a file
     ///
@@ -65,7 +112,7 @@ impl Config {
     ///
     /// # Returns
     /// * `Ok(Config)` - Successfully loaded configuration
-    /// * `Err(MinerError)` - If file couldn't be read or parsed
+    /// * `Err(MinerError)` - If file couldn't be read, parsed or validated
     pub fn load(path: impl Into<PathBuf>) -> Result<Self, MinerError> {
         let path = path.into();
         let config_str = std::fs::read_to_string(&path).map_err(|e| {
@@ -76,8 +123,42 @@ impl Config {
             ))
         })?;
 
-        toml::from_str(&config_str)
-            .map_err(|e| MinerError::ConfigError(format!("Invalid config format: {}", e)))
+        let config: Config = toml::from_str(&config_str)
+            .map_err(|e| MinerError::ConfigError(format!("Invalid config format: {}", e)))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks settings that parse but cannot work together
+    ///
+    /// # Errors
+    /// Returns `MinerError::ConfigError` if:
+    /// - `thermal.low_temperature` is not below `thermal.high_temperature`
+    /// - `max_cpu_percent` is not above 0 and at most 100
+    /// - `foreign_load.low_load` is not below `foreign_load.high_load`
+    pub fn validate(&self) -> Result<(), MinerError> {
+        if let Some(thermal) = &self.thermal
+            && thermal.low_temperature >= thermal.high_temperature
+        {
+            return Err(MinerError::ConfigError(
+                "thermal.low_temperature must be below thermal.high_temperature".to_string(),
+            ));
+        }
+        if let Some(max_percent) = self.max_cpu_percent
+            && !(max_percent > 0.0 && max_percent <= 100.0)
+        {
+            return Err(MinerError::ConfigError(
+                "max_cpu_percent must be above 0 and at most 100".to_string(),
+            ));
+        }
+        if let Some(foreign_load) = &self.foreign_load
+            && foreign_load.low_load >= foreign_load.high_load
+        {
+            return Err(MinerError::ConfigError(
+                "foreign_load.low_load must be below foreign_load.high_load".to_string(),
+            ));
+        }
+        Ok(())
     }
 
     /// Generates a configuration template string
@@ -94,10 +175,41 @@ impl Config {
         template.push_str("[general]\n");
         template.push_str("# Supported algorithms: randomx, cryptonight-v7, cryptonight-r\n");
         template.push_str("algorithm = \"randomx\"\n");
-        template.push_str("# Number of worker threads (0 = auto-detect)\n");
+        template.push_str("# Number of worker threads (0 = sized to the CPU caches)\n");
         template.push_str("worker_threads = 0\n");
         template.push_str("# Nonce batch size per worker\n");
-        template.push_str("batch_size = 1000\n\n");
+        template.push_str("batch_size = 1000\n");
+        template.push_str("# Unique per rig when several rigs share one pool login (0-255)\n");
+        template.push_str("# instance_id = 0\n");
+        template.push_str("# Re-verify shares with a light RandomX VM before submitting\n");
+        template.push_str("verify_shares = true\n");
+        template.push_str("# Pin mining threads: \"auto\" or a list of logical CPUs (optional)\n");
+        template.push_str("# cpu_affinity = \"auto\"\n");
+        template.push_str("# Mining thread priority: \"normal\", \"low\" (nice 19) or \"idle\"\n");
+        template.push_str("priority = \"normal\"\n\n");
+        template.push_str("# Force RandomX software fallbacks (optional)\n");
+        template.push_str("# [randomx]\n");
+        template.push_str("# jit = true                # false runs the interpreter\n");
+        template.push_str("# hard_aes = true           # false uses software AES\n");
+        template
+            .push_str("# argon2 = \"auto\"           # \"avx2\", \"ssse3\" or \"reference\"\n\n");
+        template.push_str("# Throttle mining when the CPU runs hot (optional)\n");
+        template.push_str("# [thermal]\n");
+        template.push_str("# high_temperature = 85.0\n");
+        template.push_str("# low_temperature = 75.0\n");
+        template.push_str("# mode = \"threads\"          # or \"duty_cycle\"\n");
+        template.push_str("# check_interval = 5\n");
+        template.push_str("# min_threads = 1\n\n");
+        template.push_str("# Cap the miner's share of total CPU time in percent (optional)\n");
+        template.push_str("# max_cpu_percent = 50.0\n\n");
+        template.push_str("# Yield to other processes' CPU load (optional)\n");
+        template.push_str("# [foreign_load]\n");
+        template.push_str("# high_load = 50.0\n");
+        template.push_str("# low_load = 20.0\n");
+        template.push_str("# sustain = 30\n");
+        template.push_str("# mode = \"pause\"            # or \"shrink\"\n");
+        template.push_str("# check_interval = 5\n");
+        template.push_str("# min_threads = 1\n\n");
 
         if pool {
             template.push_str("# Pool mining configuration\n");
@@ -106,6 +218,22 @@ impl Config {
             template.push_str("user = \"your_wallet_address\"\n");
             template.push_str("password = \"x\"\n");
             template.push_str("worker_id = \"worker01\"\n");
+            template.push_str("# Benchmark results advertised to the pool (see `benchmark`)\n");
+            template.push_str("benchmark_file = \"benchmark.json\"\n");
+            template.push_str(
+                "# Request a fixed difficulty aiming at N shares per minute (optional)\n",
+            );
+            template.push_str("# target_shares_per_minute = 2.0\n");
+            template.push_str("# POST a JSON notification when a share could solve a block\n");
+            template.push_str("# block_notify_url = \"https://example.com/hooks/block\"\n");
+            template.push_str("# Record the pool session for offline `replay` (optional)\n");
+            template.push_str("# record_file = \"session.jsonl\"\n");
+            template
+                .push_str("\n# Build templates on your own node (pool must support self-select)\n");
+            template.push_str("# [mode.pool.self_select]\n");
+            template.push_str("# rpc_url = \"http://localhost:18081/json_rpc\"\n");
+            template.push_str("# rpc_user = \"monero\"\n");
+            template.push_str("# rpc_password = \"password\"\n");
         }
 
         if node {
@@ -115,8 +243,85 @@ impl Config {
             template.push_str("rpc_user = \"monero\"\n");
             template.push_str("rpc_password = \"password\"\n");
             template.push_str("wallet_address = \"your_wallet_address\"\n");
+            template.push_str("# Unique per rig when several rigs solo mine the same node\n");
+            template.push_str("rig_id = 0\n");
+            template.push_str("# Backup nodes used when the primary lags or fails\n");
+            template.push_str("backup_rpc_urls = []\n");
+            template.push_str("# Blocks a node may trail the highest node before failing over\n");
+            template.push_str("max_height_lag = 2\n");
+            template.push_str("# Node ZMQ publisher for instant block notifications (optional)\n");
+            template.push_str("# zmq_url = \"tcp://127.0.0.1:18083\"\n");
+            template.push_str("# ZMQ publishers of the backup nodes, in backup_rpc_urls order\n");
+            template.push_str("# backup_zmq_urls = []\n");
+            template.push_str(
+                "\n# Let other miners on the LAN solo mine through this node (optional)\n",
+            );
+            template.push_str("# [mode.node.server]\n");
+            template.push_str("# bind = \"0.0.0.0:3333\"\n");
+            template.push_str("# share_difficulty = 10000\n");
         }
 
         template
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POOL: &str = r#"
+[mode.pool]
+url = "stratum+tcp://pool.example.com:3333"
+user = "wallet"
+password = "x"
+worker_id = "rig"
+"#;
+
+    fn parse(settings: &str) -> Config {
+        toml::from_str(&format!("{}\n{}", settings, POOL)).unwrap()
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(parse("").validate().is_ok());
+    }
+
+    #[test]
+    fn thermal_watermarks_must_be_ordered() {
+        let valid = parse("[thermal]\nhigh_temperature = 85.0\nlow_temperature = 75.0");
+        assert!(valid.validate().is_ok());
+        let inverted = parse("[thermal]\nhigh_temperature = 75.0\nlow_temperature = 85.0");
+        assert!(inverted.validate().is_err());
+        let equal = parse("[thermal]\nhigh_temperature = 80.0\nlow_temperature = 80.0");
+        assert!(equal.validate().is_err());
+    }
+
+    #[test]
+    fn cpu_cap_must_be_a_share_of_the_host() {
+        assert!(parse("max_cpu_percent = 50.0").validate().is_ok());
+        assert!(parse("max_cpu_percent = 100.0").validate().is_ok());
+        assert!(parse("max_cpu_percent = 0.0").validate().is_err());
+        assert!(parse("max_cpu_percent = 150.0").validate().is_err());
+        assert!(parse("max_cpu_percent = nan").validate().is_err());
+    }
+
+    #[test]
+    fn randomx_fallbacks_parse() {
+        let config = parse("[randomx]\nhard_aes = false\nargon2 = \"ssse3\"");
+        assert!(config.randomx.jit);
+        assert!(!config.randomx.hard_aes);
+        assert_eq!(
+            config.randomx.argon2,
+            crate::miner::features::Argon2Impl::Ssse3
+        );
+        assert_eq!(parse("").randomx, RandomXOptions::default());
+    }
+
+    #[test]
+    fn foreign_load_watermarks_must_be_ordered() {
+        let valid = parse("[foreign_load]\nhigh_load = 50.0\nlow_load = 20.0");
+        assert!(valid.validate().is_ok());
+        let inverted = parse("[foreign_load]\nhigh_load = 20.0\nlow_load = 50.0");
+        assert!(inverted.validate().is_err());
+    }
+}
@@ -1,5 +1,4 @@
 // src/main.rs
-use crate::miner::algorithm::{cryptonight::CryptoNightAlgo, randomx::RandomX};
 use crate::types::AlgorithmType;
 use crate::utils::logging::init_bench_logging;
 use clap::Parser;
@@ -9,6 +8,12 @@ use std::time::Duration;
 use tokio::runtime::Runtime;
 use xmr_miner_rs::{self, *};
 
+/// Delay before reconnecting to the pool after the connection drops
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Nonces per worker batch when replaying recorded jobs
+const REPLAY_BATCH_SIZE: u64 = 1000;
+
 /// Main entry point for XMR miner
 ///
 /// # Returns
@@ -26,6 +31,9 @@ fn main() -> Result<(), MinerError> {
         cli::Action::Start(opts) => start_mining(opts),
         cli::Action::Benchmark(opts) => run_benchmark(opts),
         cli::Action::Config(opts) => generate_config(opts),
+        cli::Action::Proxy(opts) => run_proxy(opts),
+        cli::Action::Replay(opts) => run_replay(opts),
+        cli::Action::PoolSim(opts) => run_pool_sim(opts),
     }
 }
 
@@ -54,34 +62,282 @@ fn start_mining(opts: cli::StartOptions) -> Result<(), MinerError> {
 
     // Communication channels
     let (share_sender, share_receiver) = unbounded(); // For submitting shares
-    let (job_sender, _job_receiver) = unbounded(); // For receiving work (receiver unused)
+    let (job_sender, job_receiver) = unbounded::<Option<miner::scheduler::MiningJob>>(); // For receiving work
 
     // Statistics reporting
     let reporter = stats::StatsReporter::new(Duration::from_secs(60));
     reporter.start_reporting();
 
     // Mining setup
-    let scheduler = miner::Scheduler::new(share_sender.clone(), config.batch_size);
-    let algorithm = create_algorithm(&config)?;
-    scheduler.start_mining(algorithm, config.worker_threads);
+    let algorithm_type = parse_algorithm(&config)?;
+    let (_, randomx_flags) = miner::features::report(algorithm_type, &config.randomx);
+    let scheduler = Arc::new(
+        miner::Scheduler::new(
+            share_sender.clone(),
+            config.batch_size,
+            config.instance_id,
+            config.verify_shares,
+        )
+        .with_randomx_flags(randomx_flags)
+        .with_stats(reporter.share_sender())
+        .with_affinity(config.cpu_affinity.clone())
+        .with_priority(config.priority),
+    );
+    if config.worker_threads == 0 {
+        let plan = miner::topology::plan_threads(algorithm_type);
+        log::info!("Using {} mining threads: {}", plan.threads, plan.reason);
+        config.worker_threads = plan.threads;
+    }
+    scheduler.start_mining(algorithm_type, config.worker_threads);
+    if let Some(thermal) = config.thermal.clone() {
+        miner::thermal::ThermalController::new(thermal, scheduler.clone()).start();
+    }
+    if let Some(max_percent) = config.max_cpu_percent {
+        miner::cpu_limit::CpuLimiter::new(max_percent, scheduler.clone()).start();
+    }
+    if let Some(foreign_load) = config.foreign_load.clone() {
+        miner::foreign_load::ForeignLoadController::new(foreign_load, scheduler.clone())
+            .start(reporter.clone());
+    }
+
+    // Forward jobs from the pool/node to the scheduler and any local server
+    let (job_watch, job_updates) = tokio::sync::watch::channel(None);
+    let job_scheduler = scheduler.clone();
+    std::thread::spawn(move || {
+        for job in job_receiver {
+            // Local miners get the job while the scheduler may still be
+            // building the dataset for a new seed
+            job_watch.send_replace(job.clone());
+            match job {
+                Some(job) => job_scheduler.update_job(job),
+                None => job_scheduler.clear_job(),
+            }
+        }
+    });
 
     // Runtime setup
     let rt = Runtime::new()?;
     rt.block_on(async {
+        #[cfg(unix)]
+        tokio::spawn(scale_threads_on_signals(
+            opts.config.clone(),
+            algorithm_type,
+            scheduler.clone(),
+        ));
+
         match config.mode {
             config::MiningMode::Pool(pool_cfg) => {
-                let pool = network::PoolClient::new(pool_cfg, job_sender, share_receiver);
-                pool.connect().await?;
-                pool.run().await
+                let pool = network::PoolClient::new(pool_cfg, job_sender, share_receiver)
+                    .with_stats(reporter.share_sender())
+                    .with_randomx_flags(randomx_flags);
+                loop {
+                    match pool.connect().await {
+                        Ok(()) => match pool.run().await {
+                            Ok(()) => log::warn!("Pool closed the connection"),
+                            Err(e) => log::error!("Pool connection lost: {}", e),
+                        },
+                        Err(e @ MinerError::ConfigError(_)) => return Err(e),
+                        Err(e) => log::error!("Pool connection failed: {}", e),
+                    }
+                    log::info!(
+                        "Reconnecting to pool in {} seconds",
+                        RECONNECT_DELAY.as_secs()
+                    );
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
             }
             config::MiningMode::Node(node_cfg) => {
-                let mut node = network::NodeClient::new(node_cfg);
+                if let Some(server_cfg) = node_cfg.server.clone() {
+                    let server = network::SoloServer::new(server_cfg, job_updates, share_sender)
+                        .with_randomx_flags(randomx_flags);
+                    tokio::spawn(async move {
+                        if let Err(e) = server.run().await {
+                            log::error!("Solo Stratum server stopped: {}", e);
+                        }
+                    });
+                }
+                let mut node = network::NodeClient::new(node_cfg, job_sender, share_receiver);
                 node.monitor_chain().await
             }
         }
     })
 }
 
+/// Adjusts the number of mining threads on Unix signals
+///
+/// * `SIGHUP` - re-reads `worker_threads` from the configuration file
+///   (0 = sized to the CPU caches)
+/// * `SIGUSR1` - starts one more thread
+/// * `SIGUSR2` - retires one thread (down to 0, pausing mining)
+///
+/// Signals change the configured thread count; reductions requested by the
+/// thermal and foreign load controllers still apply on top of it.
+///
+/// # Arguments
+/// * `path` - Configuration file to reload
+/// * `algorithm` - Algorithm being mined, for the automatic thread count
+/// * `scheduler` - Scheduler whose threads are scaled
+#[cfg(unix)]
+async fn scale_threads_on_signals(
+    path: std::path::PathBuf,
+    algorithm: AlgorithmType,
+    scheduler: Arc<miner::Scheduler>,
+) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let (Ok(mut hangup), Ok(mut more), Ok(mut fewer)) = (
+        signal(SignalKind::hangup()),
+        signal(SignalKind::user_defined1()),
+        signal(SignalKind::user_defined2()),
+    ) else {
+        log::warn!("Signal handlers unavailable, thread count is fixed");
+        return;
+    };
+
+    loop {
+        let threads = tokio::select! {
+            _ = hangup.recv() => match config::load(&path) {
+                Ok(config) if config.worker_threads == 0 => {
+                    miner::topology::plan_threads(algorithm).threads
+                }
+                Ok(config) => config.worker_threads,
+                Err(e) => {
+                    log::error!("Configuration reload failed: {}", e);
+                    continue;
+                }
+            },
+            _ = more.recv() => scheduler.base_threads() + 1,
+            _ = fewer.recv() => scheduler.base_threads().saturating_sub(1),
+        };
+        scheduler.set_threads(threads);
+    }
+}
+
+/// Runs the Stratum proxy
+///
+/// # Arguments
+/// * `opts` - Proxy options
+///
+/// # Operations
+/// 1. Initializes logging
+/// 2. Loads the pool configuration used for the upstream session
+/// 3. Accepts downstream miners until the listener fails
+fn run_proxy(opts: cli::ProxyOptions) -> Result<(), MinerError> {
+    utils::init_logging();
+
+    let config = config::load(&opts.config)?;
+    let config::MiningMode::Pool(pool_cfg) = config.mode else {
+        return Err(MinerError::ConfigError(
+            "Proxy mode requires a [mode.pool] section".to_string(),
+        ));
+    };
+
+    let randomx_flags = miner::features::RandomXFlags::select(
+        &miner::features::CpuFeatures::detect(),
+        &config.randomx,
+    );
+    let proxy = network::StratumProxy::new(opts.bind, pool_cfg).with_randomx_flags(randomx_flags);
+    let rt = Runtime::new()?;
+    rt.block_on(proxy.run())
+}
+
+/// Runs the pool simulator
+///
+/// # Arguments
+/// * `opts` - Simulator options
+///
+/// # Operations
+/// 1. Initializes logging
+/// 2. Issues synthetic jobs and validates shares until the listener fails
+fn run_pool_sim(opts: cli::PoolSimOptions) -> Result<(), MinerError> {
+    utils::init_logging();
+
+    let config = network::simulator::SimulatorConfig {
+        bind: opts.bind,
+        algorithm: opts.algorithm,
+        difficulty: opts.difficulty.max(1),
+        job_interval: Duration::from_secs(opts.job_interval.max(1)),
+        disconnect_after: opts.disconnect_after.map(Duration::from_secs),
+        error_every: opts.error_every,
+    };
+
+    let (_, randomx_flags) =
+        miner::features::report(opts.algorithm, &miner::features::RandomXOptions::default());
+    let simulator = network::PoolSimulator::new(config).with_randomx_flags(randomx_flags);
+    let rt = Runtime::new()?;
+    rt.block_on(simulator.run())
+}
+
+/// Replays a recorded pool session offline
+///
+/// # Arguments
+/// * `opts` - Replay options
+///
+/// # Operations
+/// 1. Loads the recording
+/// 2. Re-verifies every recorded share against its recorded job
+/// 3. Mines each recorded job in order for `job_time` seconds and verifies the shares found
+///
+/// # Errors
+/// Returns `MinerError` if the recording cannot be loaded or any share fails verification
+fn run_replay(opts: cli::ReplayOptions) -> Result<(), MinerError> {
+    utils::init_logging();
+
+    let recording = network::recording::Recording::load(&opts.file)?;
+    let jobs = recording.jobs();
+    log::info!(
+        "Loaded {} messages with {} jobs from {}",
+        recording.messages().len(),
+        jobs.len(),
+        opts.file.display()
+    );
+
+    let verifier = miner::ShareVerifier::new();
+    let check = recording.check_shares(&verifier);
+    log::info!(
+        "Recorded shares: {} valid, {} invalid, {} without job, {} disagreeing with the pool",
+        check.valid,
+        check.invalid,
+        check.unknown_job,
+        check.pool_disagrees
+    );
+
+    let mut found = 0;
+    let mut invalid = 0;
+    if opts.job_time > 0 && !jobs.is_empty() {
+        let (share_sender, share_receiver) = unbounded();
+        let scheduler = miner::Scheduler::new(share_sender, REPLAY_BATCH_SIZE, None, false);
+        // Each job creates its algorithm with the recorded seed hash
+        scheduler.start_mining(jobs[0].1.algorithm, opts.threads);
+
+        for (_, job) in &jobs {
+            log::info!("Replaying job {} ({})", job.job_id, job.algorithm);
+            scheduler.update_job(job.clone());
+            let deadline = std::time::Instant::now() + Duration::from_secs(opts.job_time);
+            while let Ok(share) = share_receiver.recv_deadline(deadline) {
+                // Shares still in flight from the previous job
+                if share.job_id != job.job_id {
+                    continue;
+                }
+                found += 1;
+                if !verifier.verify(job, &job.blob, &share) {
+                    invalid += 1;
+                }
+            }
+        }
+        scheduler.stop();
+        log::info!("Replayed jobs: {} shares found, {} invalid", found, invalid);
+    }
+
+    if check.invalid + invalid > 0 {
+        return Err(MinerError::AlgorithmError(format!(
+            "{} recorded and {} replayed shares failed verification",
+            check.invalid, invalid
+        )));
+    }
+    Ok(())
+}
+
 /// Runs mining algorithm benchmarks
 ///
 /// # Arguments
@@ -95,7 +351,9 @@ fn start_mining(opts: cli::StartOptions) -> Result<(), MinerError> {
 fn run_benchmark(opts: cli::BenchmarkOptions) -> Result<(), MinerError> {
     init_bench_logging();
 
-    let algorithm = create_bench_algorithm(opts.algorithm)?;
+    let (features, flags) =
+        miner::features::report(opts.algorithm, &miner::features::RandomXOptions::default());
+    let algorithm = create_bench_algorithm(opts.algorithm, flags)?;
     let reporter = stats::StatsReporter::new(Duration::from_secs(5));
     let hash_sender = reporter.hash_sender();
 
@@ -149,6 +407,14 @@ fn run_benchmark(opts: cli::BenchmarkOptions) -> Result<(), MinerError> {
     log::info!("Average hashrate: {:.2} H/s", stats.avg_hashrate_1m);
     log::logger().flush(); // Ensure final results appear
 
+    // Store the result so pool logins can advertise it
+    let hashrate = stats.hashes_total as f64 / start_time.elapsed().as_secs_f64();
+    let mut results = stats::BenchmarkResults::load(&opts.output)?;
+    results.record(opts.algorithm, hashrate);
+    results.record_host(&features, &flags);
+    results.save(&opts.output)?;
+    log::info!("Saved benchmark result to {}", opts.output.display());
+
     Ok(())
 }
 
@@ -166,52 +432,39 @@ fn generate_config(opts: cli::ConfigOptions) -> Result<(), MinerError> {
     Ok(())
 }
 
-/// Creates algorithm instance based on configuration
+/// Parses the configured algorithm
 ///
 /// # Arguments
 /// * `config` - Mining configuration
 ///
 /// # Returns
-/// - `Ok(Arc<dyn Algorithm>)` on success
+/// - `Ok(AlgorithmType)` on success
 /// - `Err(MinerError)` if algorithm is invalid
-fn create_algorithm(config: &config::Config) -> Result<Arc<dyn Algorithm>, MinerError> {
-    // Parse string to AlgorithmType
-    let algo_type = config
+fn parse_algorithm(config: &config::Config) -> Result<AlgorithmType, MinerError> {
+    config
         .algorithm
         .parse()
-        .map_err(|_| MinerError::ConfigError(format!("Invalid algorithm: {}", config.algorithm)))?;
-
-    match algo_type {
-        AlgorithmType::RandomX => {
-            let temp_key = [0u8; 32]; // Placeholder until first job
-            Ok(Arc::new(RandomX::new(
-                true, // Use fast mode for mining
-                &temp_key,
-            )))
-        }
-        AlgorithmType::CryptoNightV7 => Ok(Arc::new(CryptoNightAlgo::new(1))),
-        AlgorithmType::CryptoNightR => Ok(Arc::new(CryptoNightAlgo::new(4))),
-    }
+        .map_err(|_| MinerError::ConfigError(format!("Invalid algorithm: {}", config.algorithm)))
 }
 
 /// Creates algorithm instance for benchmarking
 ///
+/// Benchmarks have no job, so RandomX is keyed with the zero placeholder key.
+///
 /// # Arguments
 /// * `algo` - Algorithm type to benchmark
+/// * `randomx_flags` - RandomX flags selected for this host
 ///
 /// # Returns
 /// - `Ok(Arc<dyn Algorithm>)` on success
 /// - `Err(MinerError)` if algorithm is invalid
-fn create_bench_algorithm(algo: AlgorithmType) -> Result<Arc<dyn Algorithm>, MinerError> {
-    match algo {
-        AlgorithmType::RandomX => {
-            let temp_key = [0u8; 32];
-            Ok(Arc::new(RandomX::new(
-                true, // Use fast mode for mining
-                &temp_key,
-            )))
-        }
-        AlgorithmType::CryptoNightV7 => Ok(Arc::new(CryptoNightAlgo::new(1))),
-        AlgorithmType::CryptoNightR => Ok(Arc::new(CryptoNightAlgo::new(4))),
-    }
+fn create_bench_algorithm(
+    algo: AlgorithmType,
+    randomx_flags: miner::features::RandomXFlags,
+) -> Result<Arc<dyn Algorithm>, MinerError> {
+    Ok(miner::algorithm::create(
+        algo,
+        &[0u8; miner::algorithm::KEY_SIZE],
+        randomx_flags,
+    ))
 }
@@ -0,0 +1,198 @@
+// src/miner/affinity.rs
+//! CPU affinity for mining threads
+//!
+//! Pinning each mining thread to one logical CPU stops the scheduler from
+//! migrating threads between cores, which keeps their RandomX scratchpads warm
+//! in the local L2/L3 cache. CPUs are either listed explicitly or picked
+//! automatically, one thread per physical core before any SMT sibling is used.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Sysfs directory describing the logical CPUs (Linux)
+const SYSFS_CPU_DIR: &str = "/sys/devices/system/cpu";
+
+/// Which logical CPUs mining threads are pinned to
+///
+/// Deserializes from either the string `"auto"` or a list of CPU indices.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CpuAffinity {
+    /// Pin threads to these logical CPUs in order
+    Cpus(Vec<usize>),
+    /// Choose CPUs from the host topology
+    Mode(AffinityMode),
+}
+
+/// Automatic CPU selection strategies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AffinityMode {
+    /// One thread per physical core first, then the SMT siblings
+    Auto,
+}
+
+impl CpuAffinity {
+    /// Assigns a logical CPU to each of `threads` mining threads
+    ///
+    /// Threads beyond the number of listed (or available) CPUs wrap around and
+    /// share CPUs with earlier threads.
+    ///
+    /// # Returns
+    /// The CPU for each thread, or an empty list if no CPU is usable
+    pub fn assign(&self, threads: usize) -> Vec<usize> {
+        let cpus = match self {
+            CpuAffinity::Cpus(cpus) => cpus.clone(),
+            CpuAffinity::Mode(AffinityMode::Auto) => physical_first(),
+        };
+        if cpus.is_empty() {
+            return Vec::new();
+        }
+        cpus.iter().copied().cycle().take(threads).collect()
+    }
+}
+
+/// Pins the calling thread to a logical CPU
+///
+/// # Returns
+/// `true` if the operating system accepted the affinity
+pub fn pin_current_thread(cpu: usize) -> bool {
+    core_affinity::set_for_current(core_affinity::CoreId { id: cpu })
+}
+
+/// Lists the logical CPUs ordered one per physical core first
+///
+/// The first logical CPU of every core (across all sockets) comes first,
+/// followed by the remaining SMT siblings. Falls back to plain CPU order when
+/// the topology cannot be read.
+pub fn physical_first() -> Vec<usize> {
+    let cpus: Vec<usize> = core_affinity::get_core_ids()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|core| core.id)
+        .collect();
+    physical_first_in(&cpus, Path::new(SYSFS_CPU_DIR))
+}
+
+/// Orders `cpus` one per physical core first using the topology under `sysfs`
+///
+/// # Arguments
+/// * `cpus` - Logical CPUs available to this process
+/// * `sysfs` - Directory holding the `cpuN/topology` entries
+pub fn physical_first_in(cpus: &[usize], sysfs: &Path) -> Vec<usize> {
+    // Logical CPUs grouped by (socket, core)
+    let mut cores: BTreeMap<(u32, u32), Vec<usize>> = BTreeMap::new();
+    for &cpu in cpus {
+        let Some(core) = core_of(sysfs, cpu) else {
+            return cpus.to_vec();
+        };
+        cores.entry(core).or_default().push(cpu);
+    }
+
+    let siblings = cores.values().map(Vec::len).max().unwrap_or(0);
+    (0..siblings)
+        .flat_map(|rank| {
+            cores
+                .values()
+                .filter_map(move |group| group.get(rank).copied())
+        })
+        .collect()
+}
+
+/// Reads the socket and core a logical CPU belongs to
+fn core_of(sysfs: &Path, cpu: usize) -> Option<(u32, u32)> {
+    let topology = sysfs.join(format!("cpu{}/topology", cpu));
+    let read = |name: &str| -> Option<u32> {
+        std::fs::read_to_string(topology.join(name))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+    Some((read("physical_package_id")?, read("core_id")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Writes a fake sysfs CPU tree with the (socket, core) of each CPU
+    fn sysfs(name: &str, cores: &[(u32, u32)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "xmr_miner-affinity-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        for (cpu, (socket, core)) in cores.iter().enumerate() {
+            let topology = root.join(format!("cpu{}/topology", cpu));
+            std::fs::create_dir_all(&topology).unwrap();
+            std::fs::write(
+                topology.join("physical_package_id"),
+                format!("{}\n", socket),
+            )
+            .unwrap();
+            std::fs::write(topology.join("core_id"), format!("{}\n", core)).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn listed_cpus_wrap_around() {
+        let affinity = CpuAffinity::Cpus(vec![0, 2]);
+        assert_eq!(affinity.assign(5), vec![0, 2, 0, 2, 0]);
+        assert_eq!(affinity.assign(1), vec![0]);
+        assert!(affinity.assign(0).is_empty());
+    }
+
+    #[test]
+    fn empty_list_pins_nothing() {
+        assert!(CpuAffinity::Cpus(Vec::new()).assign(4).is_empty());
+    }
+
+    #[test]
+    fn deserializes_auto_or_cpu_list() {
+        let auto: CpuAffinity = serde_json::from_str(r#""auto""#).unwrap();
+        assert_eq!(auto, CpuAffinity::Mode(AffinityMode::Auto));
+        let cpus: CpuAffinity = serde_json::from_str("[0, 2]").unwrap();
+        assert_eq!(cpus, CpuAffinity::Cpus(vec![0, 2]));
+
+        assert_eq!(serde_json::to_string(&auto).unwrap(), r#""auto""#);
+        assert_eq!(serde_json::to_string(&cpus).unwrap(), "[0,2]");
+        assert!(serde_json::from_str::<CpuAffinity>(r#""fast""#).is_err());
+    }
+
+    #[test]
+    fn physical_cores_come_before_smt_siblings() {
+        // Two sockets with two cores each, siblings numbered next to each other
+        let root = sysfs(
+            "smt",
+            &[
+                (0, 0),
+                (0, 0),
+                (0, 1),
+                (0, 1),
+                (1, 0),
+                (1, 0),
+                (1, 1),
+                (1, 1),
+            ],
+        );
+        let cpus: Vec<usize> = (0..8).collect();
+        let ordered = physical_first_in(&cpus, &root);
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(ordered, vec![0, 2, 4, 6, 1, 3, 5, 7]);
+    }
+
+    #[test]
+    fn unreadable_topology_keeps_cpu_order() {
+        let root = sysfs("partial", &[(0, 0), (0, 0)]);
+        // CPU 2 has no topology entry
+        let ordered = physical_first_in(&[0, 1, 2], &root);
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(ordered, vec![0, 1, 2]);
+        assert!(physical_first_in(&[], &root).is_empty());
+    }
+}
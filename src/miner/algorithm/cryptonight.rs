@@ -8,8 +8,9 @@
 //! - Hashing operations
 //! - Solution verification
 
-use crate::miner::algorithm::Algorithm;
+use crate::miner::algorithm::{Algorithm, with_nonce};
 use crate::types::AlgorithmType;
+use crate::utils::difficulty::meets_target;
 use crate::utils::error::MinerError;
 use cryptonight::cryptonight;
 
@@ -54,7 +55,7 @@ impl Algorithm for CryptoNightAlgo {
     /// Computes a CryptoNight hash for the given input and nonce
     ///
     /// # Arguments
-    /// * `input` - The block header template (without nonce)
+    /// * `input` - The hashing blob
     /// * `nonce` - The nonce value to try
     ///
     /// # Returns
@@ -62,12 +63,11 @@ impl Algorithm for CryptoNightAlgo {
     /// - `Err(MinerError)` - If hashing fails (unlikely as cryptonight rarely errors)
     ///
     /// # Implementation Details
-    /// 1. Appends the nonce to the input data (little-endian bytes)
+    /// 1. Writes the nonce into the header nonce field (see [`with_nonce`])
     /// 2. Computes the CryptoNight hash using the configured variant
     /// 3. Returns the fixed-length hash result
     fn hash(&self, input: &[u8], nonce: u64) -> Result<[u8; 32], MinerError> {
-        let mut data = input.to_vec();
-        data.extend_from_slice(&nonce.to_le_bytes());
+        let data = with_nonce(input, nonce);
 
         let hash = cryptonight(&data, data.len(), self.variant);
         Ok(hash.try_into().expect("Always returns 32 bytes"))
@@ -76,17 +76,17 @@ impl Algorithm for CryptoNightAlgo {
     /// Verifies if a hash meets the target difficulty
     ///
     /// # Arguments
-    /// * `input` - The block header template (without nonce)
+    /// * `input` - The hashing blob
     /// * `nonce` - The nonce value to verify
     /// * `target` - The target difficulty threshold
     ///
     /// # Returns
-    /// - `Ok(true)` if hash is less than target (valid solution)
+    /// - `Ok(true)` if the hash meets the target (little-endian, valid solution)
     /// - `Ok(false)` if hash doesn't meet target
     /// - `Err(MinerError)` if hashing fails
     fn verify(&self, input: &[u8], nonce: u64, target: &[u8]) -> Result<bool, MinerError> {
         let hash = self.hash(input, nonce)?;
-        Ok(hash.as_ref() < target)
+        Ok(meets_target(&hash, target))
     }
 
     /// Returns the algorithm type enum variant
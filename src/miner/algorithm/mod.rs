@@ -19,8 +19,19 @@ pub mod randomx;
 /// - CryptoNightR (Monero's 2019 variant)
 pub mod cryptonight;
 
+use crate::miner::features::RandomXFlags;
 use crate::types::AlgorithmType;
 use crate::utils::error::MinerError;
+use std::sync::Arc;
+
+/// Offset of the 4-byte nonce in a Monero hashing blob
+pub const NONCE_OFFSET: usize = 39;
+
+/// Size of the header nonce in bytes
+const NONCE_SIZE: usize = 4;
+
+/// Size of the key an algorithm instance is created with (a RandomX seed hash)
+pub const KEY_SIZE: usize = 32;
 
 /// Common interface for all mining algorithms
 ///
@@ -30,7 +41,7 @@ pub trait Algorithm: Send + Sync {
     /// Compute the hash for given input data and nonce
     ///
     /// # Arguments
-    /// * `input` - The block header or other data to be hashed
+    /// * `input` - The hashing blob (see [`with_nonce`] for nonce placement)
     /// * `nonce` - The nonce value to use in the hash computation
     ///
     /// # Returns
@@ -45,7 +56,8 @@ pub trait Algorithm: Send + Sync {
     /// * `target` - The target difficulty to compare against
     ///
     /// # Returns
-    /// `true` if hash is valid (less than target), `false` otherwise
+    /// `true` if the hash meets the target (see
+    /// [`meets_target`](crate::utils::difficulty::meets_target)), `false` otherwise
     fn verify(&self, input: &[u8], nonce: u64, target: &[u8]) -> Result<bool, MinerError>;
 
     /// Get the algorithm type
@@ -54,6 +66,94 @@ pub trait Algorithm: Send + Sync {
     /// The specific algorithm variant being used
     fn algorithm_type(&self) -> AlgorithmType;
 }
+
+/// Builds the hashing input for a nonce
+///
+/// The lower 32 bits of `nonce` are written little-endian into the header
+/// nonce field at [`NONCE_OFFSET`], exactly where they end up in the
+/// submitted block. Inputs too short to hold a header (benchmarks, tests) get
+/// the nonce appended instead.
+///
+/// # Arguments
+/// * `input` - Hashing blob
+/// * `nonce` - Nonce to place
+pub fn with_nonce(input: &[u8], nonce: u64) -> Vec<u8> {
+    let nonce = (nonce as u32).to_le_bytes();
+    let mut data = input.to_vec();
+    match data.get_mut(NONCE_OFFSET..NONCE_OFFSET + NONCE_SIZE) {
+        Some(field) => field.copy_from_slice(&nonce),
+        None => data.extend_from_slice(&nonce),
+    }
+    data
+}
+
+/// Returns the key an algorithm instance must be created with for a job
+///
+/// RandomX instances are keyed by the seed hash sent with the job. Jobs
+/// without a seed hash, and all other algorithms, use a zero placeholder key.
+///
+/// # Arguments
+/// * `algorithm` - Algorithm of the job
+/// * `seed_hash` - Seed hash (hex) sent with the job
+///
+/// # Errors
+/// Returns `MinerError` if the seed hash is not 32 bytes of hex
+pub fn job_key(
+    algorithm: AlgorithmType,
+    seed_hash: Option<&str>,
+) -> Result<[u8; KEY_SIZE], MinerError> {
+    match seed_hash {
+        Some(seed) if algorithm == AlgorithmType::RandomX && !seed.is_empty() => hex::decode(seed)?
+            .try_into()
+            .map_err(|_| MinerError::ProtocolError(format!("Invalid seed hash {}", seed))),
+        _ => Ok([0u8; KEY_SIZE]),
+    }
+}
+
+/// Creates an algorithm instance for mining
+///
+/// RandomX runs in fast mode keyed with `key`, with one dataset per NUMA node
+/// on multi-socket hosts.
+///
+/// # Arguments
+/// * `algorithm` - Algorithm type to create
+/// * `key` - Key from [`job_key`] (ignored by CryptoNight)
+/// * `flags` - RandomX flags selected for this host (ignored by CryptoNight)
+pub fn create(
+    algorithm: AlgorithmType,
+    key: &[u8; KEY_SIZE],
+    flags: RandomXFlags,
+) -> Arc<dyn Algorithm> {
+    match algorithm {
+        AlgorithmType::RandomX => Arc::new(randomx::RandomX::new_numa(
+            key,
+            &crate::miner::numa::nodes(),
+            flags,
+        )),
+        AlgorithmType::CryptoNightV7 => Arc::new(cryptonight::CryptoNightAlgo::new(1)),
+        AlgorithmType::CryptoNightR => Arc::new(cryptonight::CryptoNightAlgo::new(4)),
+    }
+}
+
+/// Creates a separate algorithm instance for verifying shares
+///
+/// RandomX uses a light-mode VM (no dataset, ~256MB), so verification is
+/// independent of the fast-mode dataset.
+///
+/// # Arguments
+/// * `algorithm` - Algorithm type to create
+/// * `key` - Key from [`job_key`] for the job whose shares are verified
+/// * `flags` - RandomX flags selected for this host (ignored by CryptoNight)
+pub fn create_verifier(
+    algorithm: AlgorithmType,
+    key: &[u8; KEY_SIZE],
+    flags: RandomXFlags,
+) -> Arc<dyn Algorithm> {
+    match algorithm {
+        AlgorithmType::RandomX => Arc::new(randomx::RandomX::new(false, key, flags)),
+        other => create(other, key, flags),
+    }
+}
 /*
 Recommended Optimizations:
 
@@ -7,30 +7,100 @@
 //! - Hashing operations
 //! - Verification of solutions
 
-use crate::miner::algorithm::Algorithm;
+use crate::miner::algorithm::{Algorithm, with_nonce};
+use crate::miner::features::RandomXFlags;
+use crate::miner::numa::{self, NumaNode};
 use crate::types::AlgorithmType;
+use crate::utils::difficulty::meets_target;
 use crate::utils::error::MinerError;
-use rust_randomx::{Context, Hasher};
+use randomx4r::{RandomxCache, RandomxDataset, RandomxFlags, RandomxVm};
 use std::sync::Arc;
+use std::thread;
+
+/// A keyed RandomX cache (light mode) or dataset (fast mode)
+struct Context {
+    /// Flags the cache, dataset and VMs are created with
+    flags: RandomXFlags,
+    /// Memory the VMs hash against
+    memory: Memory,
+}
+
+/// Cache or dataset of a [`Context`]
+enum Memory {
+    Light(RandomxCache),
+    Fast(RandomxDataset),
+}
+
+impl Context {
+    /// Allocates and initializes a context for `key`
+    ///
+    /// In fast mode the dataset is filled from one thread per available CPU,
+    /// which inherit the calling thread's priority and NUMA binding.
+    ///
+    /// # Arguments
+    /// * `key` - The key/seed (typically the block seed hash)
+    /// * `fast` - Build the full dataset instead of keeping the cache
+    /// * `flags` - Flags to create the cache, dataset and VMs with
+    ///
+    /// # Panics
+    /// If the cache or dataset cannot be allocated (out of memory, or flags
+    /// the platform does not support)
+    fn new(key: &[u8], fast: bool, flags: RandomXFlags) -> Self {
+        let memory = if fast {
+            let threads = thread::available_parallelism().map_or(1, |n| n.get());
+            let threads = threads.min(u8::MAX as usize) as u8;
+            let dataset =
+                RandomxDataset::new(binding_flags(flags) | RandomxFlags::FULLMEM, key, threads)
+                    .unwrap_or_else(|e| panic!("RandomX dataset allocation failed: {}", e));
+            Memory::Fast(dataset)
+        } else {
+            let cache = RandomxCache::new(binding_flags(flags), key).unwrap_or_else(|e| {
+                panic!("RandomX cache allocation failed (flags: {}): {}", flags, e)
+            });
+            Memory::Light(cache)
+        };
+        Context { flags, memory }
+    }
+
+    /// Computes the RandomX hash of `input` on a VM created for this call
+    ///
+    /// # Errors
+    /// Returns `MinerError` if the VM cannot be allocated
+    fn hash(&self, input: &[u8]) -> Result<[u8; 32], MinerError> {
+        let flags = binding_flags(self.flags);
+        let hash = match &self.memory {
+            Memory::Light(cache) => RandomxVm::new(flags, cache).map(|vm| vm.hash(input)),
+            Memory::Fast(dataset) => {
+                RandomxVm::new_fast(flags | RandomxFlags::FULLMEM, dataset).map(|vm| vm.hash(input))
+            }
+        };
+        hash.map_err(|e| {
+            MinerError::AlgorithmError(format!(
+                "RandomX VM creation failed (flags: {}): {}",
+                self.flags, e
+            ))
+        })
+    }
+}
+
+/// Converts the selected flags to the bindings' flag type
+fn binding_flags(flags: RandomXFlags) -> RandomxFlags {
+    RandomxFlags::from_bits_truncate(flags.bits())
+}
 
 /// RandomX algorithm implementation
 ///
 /// Manages the RandomX context (dataset) and provides thread-safe hashing operations.
 /// The implementation uses reference-counted pointers to share the heavy dataset
-/// between threads while allowing each thread to maintain its own lightweight hasher.
+/// between threads while every hash runs on its own lightweight VM.
+/// On multi-socket hosts one dataset is kept per NUMA node.
 #[derive(Clone)]
 pub struct RandomX {
-    /// Shared RandomX context containing the dataset
+    /// Shared RandomX contexts containing the dataset, one per NUMA node
     ///
     /// This is the memory-intensive component that's shared across all threads.
-    /// Wrapped in Arc for thread-safe reference counting.
-    context: Arc<Context>,
-
-    /// Thread-safe hasher instance
-    ///
-    /// Each RandomX instance maintains its own hasher to avoid contention.
-    /// Wrapped in Arc to support cloning across threads.
-    hasher: Arc<Hasher>,
+    /// Indexed like [`numa::nodes`]; a single context on single-node hosts.
+    contexts: Vec<Arc<Context>>,
 }
 
 impl RandomX {
@@ -39,31 +109,78 @@ impl RandomX {
     /// # Arguments
     /// * `fast` - Enables fast mode when true (uses more memory but better performance)
     /// * `key` - The key/seed used to initialize the dataset (typically block seed)
+    /// * `flags` - JIT, AES and Argon2 flags selected for this host
     ///
     /// # Panics
     /// May panic if:
-    /// - Key length is invalid (not 32 bytes)
-    /// - Memory allocation for dataset fails
+    /// - Memory allocation for the cache or dataset fails
     ///
     /// # Performance Notes
     /// - Initialization is expensive (dataset generation takes several seconds)
     /// - Fast mode requires ~2080MB RAM vs ~256MB in light mode
-    pub fn new(fast: bool, key: &[u8]) -> Self {
-        // Create Arc-wrapped Context first
-        let context = Arc::new(Context::new(key, fast));
+    pub fn new(fast: bool, key: &[u8], flags: RandomXFlags) -> Self {
+        Self {
+            contexts: vec![Arc::new(Context::new(key, fast, flags))],
+        }
+    }
+
+    /// Creates a fast-mode instance with one dataset per NUMA node
+    ///
+    /// Each dataset is initialized from a thread bound to its node's CPUs, so
+    /// the initialization threads inherit the binding and the kernel allocates
+    /// the dataset from that node's memory. Falls back to a single dataset on
+    /// single-node hosts.
+    ///
+    /// # Arguments
+    /// * `key` - The key/seed used to initialize the datasets
+    /// * `nodes` - NUMA nodes as returned by [`numa::nodes`]
+    /// * `flags` - JIT, AES and Argon2 flags selected for this host
+    ///
+    /// # Performance Notes
+    /// Requires ~2080MB RAM per node.
+    pub fn new_numa(key: &[u8], nodes: &[NumaNode], flags: RandomXFlags) -> Self {
+        if nodes.len() < 2 {
+            return Self::new(true, key, flags);
+        }
+        log::info!(
+            "Allocating {} RandomX datasets, one per NUMA node",
+            nodes.len()
+        );
 
-        // Hasher needs Arc<Context>
-        let hasher = Arc::new(Hasher::new(Arc::clone(&context)));
+        let contexts = std::thread::scope(|scope| {
+            let handles: Vec<_> = nodes
+                .iter()
+                .map(|node| {
+                    scope.spawn(move || {
+                        if !numa::bind_current_thread(&node.cpus) {
+                            log::warn!(
+                                "Failed to bind to NUMA node {}, dataset may be remote",
+                                node.id
+                            );
+                        }
+                        Arc::new(Context::new(key, true, flags))
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("dataset initialization panicked"))
+                .collect()
+        });
+        Self { contexts }
+    }
 
-        Self { context, hasher }
+    /// Flags the instance's datasets and VMs are created with
+    pub fn flags(&self) -> RandomXFlags {
+        self.contexts[0].flags
     }
 
-    /// Creates a new thread-local hasher instance
+    /// Returns the context hashes on the calling thread use
     ///
-    /// Used internally to provide thread-safe hashing operations without
-    /// requiring mutex locks on the hasher.
-    fn create_hasher(&self) -> Hasher {
-        Hasher::new(Arc::clone(&self.context))
+    /// Uses the dataset of the NUMA node the calling thread is bound to.
+    fn context(&self) -> &Context {
+        let node = numa::current_node().min(self.contexts.len() - 1);
+        &self.contexts[node]
     }
 }
 
@@ -71,7 +188,7 @@ impl Algorithm for RandomX {
     /// Computes a RandomX hash for the given input and nonce
     ///
     /// # Arguments
-    /// * `input` - The block header template (without nonce)
+    /// * `input` - The hashing blob
     /// * `nonce` - The nonce value to try
     ///
     /// # Returns
@@ -79,35 +196,27 @@ impl Algorithm for RandomX {
     /// - `Err(MinerError)` - If hashing fails
     ///
     /// # Implementation Details
-    /// 1. Appends nonce to input (little-endian bytes)
+    /// 1. Writes the nonce into the header nonce field (see [`with_nonce`])
     /// 2. Computes RandomX hash
     /// 3. Converts output to fixed-size array
     fn hash(&self, input: &[u8], nonce: u64) -> Result<[u8; 32], MinerError> {
-        let hasher = self.create_hasher();
-        let mut full_input = input.to_vec();
-        full_input.extend_from_slice(&nonce.to_le_bytes());
-
-        // Correct hash usage - returns Output struct
-        let output = hasher.hash(&full_input);
-
-        // Convert Output to byte array using AsRef<[u8]> implementation
-        Ok(output.as_ref().try_into().unwrap())
+        self.context().hash(&with_nonce(input, nonce))
     }
 
     /// Verifies if a hash meets the target difficulty
     ///
     /// # Arguments
-    /// * `input` - The block header template (without nonce)
+    /// * `input` - The hashing blob
     /// * `nonce` - The nonce value to verify
     /// * `target` - The target difficulty threshold
     ///
     /// # Returns
-    /// - `Ok(true)` if hash < target
+    /// - `Ok(true)` if the hash meets the target (little-endian)
     /// - `Ok(false)` otherwise
     /// - `Err(MinerError)` if hashing fails
     fn verify(&self, input: &[u8], nonce: u64, target: &[u8]) -> Result<bool, MinerError> {
         let hash = self.hash(input, nonce)?;
-        Ok(hash.as_ref() < target)
+        Ok(meets_target(&hash, target))
     }
 
     /// Returns the algorithm type (RandomX)
@@ -116,6 +225,46 @@ impl Algorithm for RandomX {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::miner::features::{Argon2Impl, CpuFeatures, RandomXOptions};
+
+    /// Key, input and hash of the RandomX API example
+    const KEY: &[u8] = b"RandomX example key\x00";
+    const INPUT: &[u8] = b"RandomX example input\x00";
+    const EXPECTED: [u8; 32] = [
+        138, 72, 229, 249, 219, 69, 171, 121, 217, 8, 5, 116, 196, 216, 25, 84, 254, 106, 198, 56,
+        66, 33, 74, 255, 115, 194, 68, 178, 99, 48, 183, 201,
+    ];
+
+    #[test]
+    fn selected_flags_reproduce_reference_hash() {
+        let context = Context::new(KEY, false, RandomXFlags::detect());
+        assert_eq!(context.flags, RandomXFlags::detect());
+        assert_eq!(context.hash(INPUT).unwrap(), EXPECTED);
+    }
+
+    #[test]
+    fn forced_fallbacks_change_flags_in_use() {
+        let options = RandomXOptions {
+            jit: false,
+            hard_aes: false,
+            argon2: Argon2Impl::Reference,
+        };
+        let forced = RandomXFlags::select(&CpuFeatures::detect(), &options);
+        let context = Context::new(KEY, false, forced);
+
+        assert_eq!(context.flags, forced);
+        assert_eq!(binding_flags(context.flags), RandomxFlags::DEFAULT);
+        if RandomXFlags::detect().bits() != 0 {
+            assert_ne!(context.flags, RandomXFlags::detect());
+        }
+        // The interpreter, software AES and reference Argon2 agree with the optimized paths
+        assert_eq!(context.hash(INPUT).unwrap(), EXPECTED);
+    }
+}
+
 /*
 // src/miner/algorithm/randomx(test).rs
 #[cfg(test)]
@@ -0,0 +1,187 @@
+// src/miner/cpu_limit.rs
+//! CPU usage cap
+//!
+//! Keeps the miner's share of the host's total CPU time under a configured
+//! cap by duty-cycling the mining threads. The process's CPU usage is sampled
+//! with `sysinfo`; from the usage measured at the current idle share the
+//! controller estimates what full speed would cost and requests just enough
+//! idle time from the scheduler to stay below the cap. Until a sample is
+//! available (at start and while mining is paused) the idle share is estimated
+//! from the number of mining threads, so the cap holds from the first batch.
+
+use crate::miner::Scheduler;
+use crate::miner::scheduler::MAX_IDLE_PERCENT;
+use std::sync::Arc;
+use std::time::Duration;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+/// Time between CPU usage samples
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Name under which idle time is requested from the scheduler
+const IDLE_SOURCE: &str = "cpu_limit";
+
+/// Duty-cycles mining to keep the miner's CPU usage under a cap
+pub struct CpuLimiter {
+    /// Maximum share of the host's total CPU time, in percent
+    max_percent: f32,
+    /// Scheduler whose workers are duty-cycled
+    scheduler: Arc<Scheduler>,
+}
+
+impl CpuLimiter {
+    /// Creates a limiter for `scheduler`
+    ///
+    /// # Arguments
+    /// * `max_percent` - Maximum share of the host's total CPU time (0-100]
+    /// * `scheduler` - Scheduler whose workers are duty-cycled
+    pub fn new(max_percent: f32, scheduler: Arc<Scheduler>) -> Self {
+        CpuLimiter {
+            max_percent,
+            scheduler,
+        }
+    }
+
+    /// Starts sampling the miner's CPU usage on a background thread
+    ///
+    /// The initial idle share is requested before this returns.
+    pub fn start(self) {
+        self.estimate(num_cpus::get().max(1));
+        std::thread::spawn(move || {
+            let pid = match sysinfo::get_current_pid() {
+                Ok(pid) => pid,
+                Err(e) => {
+                    log::warn!("Cannot measure miner CPU usage ({}), CPU cap inactive", e);
+                    return;
+                }
+            };
+            let mut system = System::new();
+            let cpus = num_cpus::get().max(1);
+            log::info!("Capping miner CPU usage at {:.0}%", self.max_percent);
+
+            // The first refresh only establishes the baseline for cpu_usage()
+            refresh_process(&mut system, pid);
+            loop {
+                let paused = self.scheduler.is_paused();
+                std::thread::sleep(SAMPLE_INTERVAL);
+                refresh_process(&mut system, pid);
+                // A sample overlapping a pause understates full-speed usage
+                if paused || self.scheduler.is_paused() {
+                    self.estimate(cpus);
+                    continue;
+                }
+                let Some(process) = system.process(pid) else {
+                    continue;
+                };
+                // sysinfo reports 100% per fully used logical CPU
+                self.update(process.cpu_usage() / cpus as f32);
+            }
+        });
+    }
+
+    /// Requests the idle share estimated from the number of mining threads
+    ///
+    /// # Arguments
+    /// * `cpus` - Number of logical CPUs of the host
+    fn estimate(&self, cpus: usize) {
+        let target = estimated_idle(self.scheduler.threads(), cpus, self.max_percent);
+        self.scheduler.request_idle(IDLE_SOURCE, target);
+    }
+
+    /// Applies one control step for a CPU usage sample
+    ///
+    /// # Arguments
+    /// * `usage` - Miner CPU usage as a share of the host's total, in percent
+    pub fn update(&self, usage: f32) {
+        let target = idle_for(usage, self.scheduler.idle_percent(), self.max_percent);
+        self.scheduler.request_idle(IDLE_SOURCE, target);
+    }
+}
+
+/// Computes the idle share that keeps CPU usage under `max_percent`
+///
+/// # Arguments
+/// * `usage` - CPU usage measured while workers idled `idle` percent
+/// * `idle` - Idle share in effect during the measurement
+/// * `max_percent` - CPU usage cap
+///
+/// # Returns
+/// The idle share to request, at most [`MAX_IDLE_PERCENT`]
+fn idle_for(usage: f32, idle: u32, max_percent: f32) -> u32 {
+    // Usage the miner would reach without any idle time
+    let busy = (100 - idle.min(MAX_IDLE_PERCENT)) as f32 / 100.0;
+    let full_speed = usage / busy;
+    if full_speed <= max_percent {
+        return 0;
+    }
+    let idle = 100.0 - 100.0 * max_percent / full_speed;
+    (idle.ceil() as u32).min(MAX_IDLE_PERCENT)
+}
+
+/// Estimates the idle share that keeps `threads` busy threads under `max_percent`
+///
+/// Every mining thread is assumed to keep one logical CPU fully busy.
+///
+/// # Arguments
+/// * `threads` - Number of mining threads
+/// * `cpus` - Number of logical CPUs of the host
+/// * `max_percent` - CPU usage cap
+fn estimated_idle(threads: usize, cpus: usize, max_percent: f32) -> u32 {
+    let full_speed = 100.0 * threads.min(cpus) as f32 / cpus.max(1) as f32;
+    idle_for(full_speed, 0, max_percent)
+}
+
+/// Refreshes the CPU usage of a single process
+fn refresh_process(system: &mut System, pid: Pid) {
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing().with_cpu(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_idle_below_cap() {
+        assert_eq!(idle_for(20.0, 0, 50.0), 0);
+        assert_eq!(idle_for(50.0, 0, 50.0), 0);
+        // 30% at 50% idle would be 60% at full speed
+        assert_eq!(idle_for(30.0, 50, 60.0), 0);
+    }
+
+    #[test]
+    fn idle_scales_full_speed_usage_to_cap() {
+        assert_eq!(idle_for(100.0, 0, 50.0), 50);
+        assert_eq!(idle_for(100.0, 0, 25.0), 75);
+        // Rounded up so the cap is not exceeded
+        assert_eq!(idle_for(100.0, 0, 33.0), 67);
+    }
+
+    #[test]
+    fn idle_accounts_for_current_duty_cycle() {
+        // 40% measured at 50% idle: 80% at full speed, so 50% cap needs 37.5% idle
+        assert_eq!(idle_for(40.0, 50, 50.0), 38);
+        // Converged: measured exactly at the cap with the right idle share
+        assert_eq!(idle_for(50.0, 50, 50.0), 50);
+    }
+
+    #[test]
+    fn idle_is_capped() {
+        assert_eq!(idle_for(100.0, 0, 1.0), MAX_IDLE_PERCENT);
+        assert_eq!(idle_for(100.0, 100, 1.0), MAX_IDLE_PERCENT);
+    }
+
+    #[test]
+    fn estimate_assumes_one_busy_cpu_per_thread() {
+        // 8 of 16 CPUs is 50% of the host
+        assert_eq!(estimated_idle(8, 16, 50.0), 0);
+        assert_eq!(estimated_idle(16, 16, 50.0), 50);
+        assert_eq!(estimated_idle(8, 16, 25.0), 50);
+        // More threads than CPUs cannot use more than the whole host
+        assert_eq!(estimated_idle(32, 16, 50.0), 50);
+        assert_eq!(estimated_idle(0, 16, 50.0), 0);
+    }
+}
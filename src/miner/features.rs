@@ -0,0 +1,331 @@
+// src/miner/features.rs
+//! CPU feature detection and RandomX flag selection
+//!
+//! RandomX speed depends heavily on the instruction set extensions the CPU
+//! offers: AES-NI for the hardware AES rounds, SSSE3/AVX2 for the Argon2
+//! cache fill, and a supported architecture for the JIT compiler. The flags
+//! RandomX runs with are chosen from the detected features, minus any
+//! software fallback forced in the `[randomx]` configuration. Features and
+//! flags are reported at startup and stored with benchmark results so
+//! hashrates from different hosts can be compared.
+
+use crate::types::AlgorithmType;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// RandomX flag bits (see `randomx.h`)
+const FLAG_HARD_AES: u32 = 2;
+const FLAG_JIT: u32 = 8;
+const FLAG_ARGON2_SSSE3: u32 = 32;
+const FLAG_ARGON2_AVX2: u32 = 64;
+
+/// Instruction set extensions relevant to mining
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuFeatures {
+    /// Hardware AES (AES-NI on x86, the AES extension on ARM)
+    pub aes: bool,
+    /// SSSE3
+    pub ssse3: bool,
+    /// AVX2
+    pub avx2: bool,
+    /// AVX-512 Foundation
+    pub avx512f: bool,
+}
+
+impl CpuFeatures {
+    /// Detects the features of the running CPU
+    #[cfg(target_arch = "x86_64")]
+    pub fn detect() -> Self {
+        CpuFeatures {
+            aes: std::arch::is_x86_feature_detected!("aes"),
+            ssse3: std::arch::is_x86_feature_detected!("ssse3"),
+            avx2: std::arch::is_x86_feature_detected!("avx2"),
+            avx512f: std::arch::is_x86_feature_detected!("avx512f"),
+        }
+    }
+
+    /// Detects the features of the running CPU
+    #[cfg(target_arch = "aarch64")]
+    pub fn detect() -> Self {
+        CpuFeatures {
+            aes: std::arch::is_aarch64_feature_detected!("aes"),
+            ..Default::default()
+        }
+    }
+
+    /// Detects the features of the running CPU (none on other architectures)
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    pub fn detect() -> Self {
+        CpuFeatures::default()
+    }
+
+    /// Names of the available features (e.g. `["aes", "ssse3", "avx2"]`)
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.aes, "aes"),
+            (self.ssse3, "ssse3"),
+            (self.avx2, "avx2"),
+            (self.avx512f, "avx512f"),
+        ]
+        .into_iter()
+        .filter_map(|(present, name)| present.then_some(name))
+        .collect()
+    }
+}
+
+impl fmt::Display for CpuFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = self.names();
+        if names.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", names.join(" "))
+        }
+    }
+}
+
+/// Argon2 implementation used to fill the RandomX cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Argon2Impl {
+    /// Fastest implementation the CPU supports (AVX2, then SSSE3)
+    #[default]
+    Auto,
+    /// AVX2 implementation
+    Avx2,
+    /// SSSE3 implementation
+    Ssse3,
+    /// Portable reference implementation
+    Reference,
+}
+
+/// Optimized RandomX code paths the configuration allows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RandomXOptions {
+    /// Compile programs to native code; `false` runs the interpreter
+    /// (default: true)
+    #[serde(default = "default_enabled")]
+    pub jit: bool,
+    /// Use AES instructions; `false` forces software AES (default: true)
+    #[serde(default = "default_enabled")]
+    pub hard_aes: bool,
+    /// Argon2 implementation for the cache fill (default: auto)
+    #[serde(default)]
+    pub argon2: Argon2Impl,
+}
+
+impl Default for RandomXOptions {
+    fn default() -> Self {
+        RandomXOptions {
+            jit: true,
+            hard_aes: true,
+            argon2: Argon2Impl::Auto,
+        }
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// RandomX flags caches, datasets and VMs are created with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RandomXFlags {
+    /// JIT compiler enabled
+    pub jit: bool,
+    /// Hardware AES enabled
+    pub hard_aes: bool,
+    /// SSSE3 Argon2 implementation used for the cache fill
+    pub argon2_ssse3: bool,
+    /// AVX2 Argon2 implementation used for the cache fill
+    pub argon2_avx2: bool,
+}
+
+impl RandomXFlags {
+    /// Chooses the fastest flags the CPU supports, minus forced fallbacks
+    ///
+    /// JIT is available on x86-64 and ARM64, hard AES needs AES-NI. At most
+    /// one Argon2 implementation is selected; one the CPU lacks falls back
+    /// to the reference implementation.
+    ///
+    /// # Arguments
+    /// * `features` - Detected CPU features
+    /// * `options` - Optimized paths the configuration allows
+    pub fn select(features: &CpuFeatures, options: &RandomXOptions) -> Self {
+        let (argon2_ssse3, argon2_avx2) = match options.argon2 {
+            Argon2Impl::Auto => (features.ssse3 && !features.avx2, features.avx2),
+            Argon2Impl::Avx2 => (false, features.avx2),
+            Argon2Impl::Ssse3 => (features.ssse3, false),
+            Argon2Impl::Reference => (false, false),
+        };
+        RandomXFlags {
+            jit: options.jit && cfg!(any(target_arch = "x86_64", target_arch = "aarch64")),
+            hard_aes: options.hard_aes && features.aes,
+            argon2_ssse3,
+            argon2_avx2,
+        }
+    }
+
+    /// Fastest flags for the running CPU, without forced fallbacks
+    pub fn detect() -> Self {
+        Self::select(&CpuFeatures::detect(), &RandomXOptions::default())
+    }
+
+    /// Raw `randomx_flags` bits
+    pub fn bits(&self) -> u32 {
+        [
+            (self.jit, FLAG_JIT),
+            (self.hard_aes, FLAG_HARD_AES),
+            (self.argon2_ssse3, FLAG_ARGON2_SSSE3),
+            (self.argon2_avx2, FLAG_ARGON2_AVX2),
+        ]
+        .into_iter()
+        .filter_map(|(set, bit)| set.then_some(bit))
+        .fold(0, |bits, bit| bits | bit)
+    }
+
+    /// Names of the enabled flags (e.g. `["jit", "hard_aes", "argon2_avx2"]`)
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.jit, "jit"),
+            (self.hard_aes, "hard_aes"),
+            (self.argon2_ssse3, "argon2_ssse3"),
+            (self.argon2_avx2, "argon2_avx2"),
+        ]
+        .into_iter()
+        .filter_map(|(set, name)| set.then_some(name))
+        .collect()
+    }
+}
+
+impl fmt::Display for RandomXFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = self.names();
+        if names.is_empty() {
+            write!(f, "none (interpreter, software AES)")
+        } else {
+            write!(f, "{}", names.join(" "))
+        }
+    }
+}
+
+/// Detects the CPU features, selects the RandomX flags and logs both
+///
+/// # Arguments
+/// * `algorithm` - Algorithm about to be mined or benchmarked
+/// * `options` - Optimized RandomX paths the configuration allows
+///
+/// # Returns
+/// The detected features and the RandomX flags to create instances with
+pub fn report(algorithm: AlgorithmType, options: &RandomXOptions) -> (CpuFeatures, RandomXFlags) {
+    let features = CpuFeatures::detect();
+    let flags = RandomXFlags::select(&features, options);
+    log::info!("CPU features: {}", features);
+    if algorithm != AlgorithmType::RandomX {
+        return (features, flags);
+    }
+
+    log::info!("RandomX flags: {} (0x{:02x})", flags, flags.bits());
+    let fastest = RandomXFlags::select(&features, &RandomXOptions::default());
+    if flags != fastest {
+        log::info!(
+            "RandomX software fallbacks forced by configuration (fastest: {})",
+            fastest
+        );
+    }
+    if (options.argon2 == Argon2Impl::Avx2 && !features.avx2)
+        || (options.argon2 == Argon2Impl::Ssse3 && !features.ssse3)
+    {
+        log::warn!(
+            "CPU lacks the configured Argon2 implementation, using the reference implementation"
+        );
+    }
+    (features, flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(aes: bool, ssse3: bool, avx2: bool) -> CpuFeatures {
+        CpuFeatures {
+            aes,
+            ssse3,
+            avx2,
+            avx512f: false,
+        }
+    }
+
+    fn fastest(features: &CpuFeatures) -> RandomXFlags {
+        RandomXFlags::select(features, &RandomXOptions::default())
+    }
+
+    #[test]
+    fn avx2_argon2_replaces_ssse3() {
+        let flags = fastest(&features(true, true, true));
+        assert!(flags.argon2_avx2);
+        assert!(!flags.argon2_ssse3);
+        assert!(flags.hard_aes);
+        assert_eq!(
+            flags.bits() & (FLAG_ARGON2_SSSE3 | FLAG_ARGON2_AVX2),
+            FLAG_ARGON2_AVX2
+        );
+    }
+
+    #[test]
+    fn ssse3_argon2_without_avx2() {
+        let flags = fastest(&features(true, true, false));
+        assert!(flags.argon2_ssse3);
+        assert!(!flags.argon2_avx2);
+    }
+
+    #[test]
+    fn software_fallbacks_without_extensions() {
+        let flags = fastest(&features(false, false, false));
+        assert!(!flags.hard_aes);
+        assert!(!flags.argon2_ssse3 && !flags.argon2_avx2);
+        assert_eq!(flags.bits() & !FLAG_JIT, 0);
+    }
+
+    #[test]
+    fn forced_fallbacks_clear_flags() {
+        let options = RandomXOptions {
+            jit: false,
+            hard_aes: false,
+            argon2: Argon2Impl::Reference,
+        };
+        let flags = RandomXFlags::select(&features(true, true, true), &options);
+        assert_eq!(flags, RandomXFlags::default());
+        assert_eq!(flags.bits(), 0);
+    }
+
+    #[test]
+    fn argon2_choice_needs_cpu_support() {
+        let ssse3 = RandomXOptions {
+            argon2: Argon2Impl::Ssse3,
+            ..Default::default()
+        };
+        let flags = RandomXFlags::select(&features(true, true, true), &ssse3);
+        assert!(flags.argon2_ssse3 && !flags.argon2_avx2);
+
+        let avx2 = RandomXOptions {
+            argon2: Argon2Impl::Avx2,
+            ..Default::default()
+        };
+        let flags = RandomXFlags::select(&features(true, true, false), &avx2);
+        assert!(!flags.argon2_ssse3 && !flags.argon2_avx2);
+    }
+
+    #[test]
+    fn options_parse_from_config() {
+        let options: RandomXOptions =
+            toml::from_str("jit = false\nargon2 = \"reference\"").unwrap();
+        assert!(!options.jit);
+        assert!(options.hard_aes);
+        assert_eq!(options.argon2, Argon2Impl::Reference);
+        assert_eq!(
+            toml::from_str::<RandomXOptions>("").unwrap(),
+            RandomXOptions::default()
+        );
+    }
+}
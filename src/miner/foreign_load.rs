@@ -0,0 +1,299 @@
+// src/miner/foreign_load.rs
+//! Yielding to other workloads
+//!
+//! Watches how much CPU the host's other processes use and backs mining off
+//! while that load stays above a high watermark, either by pausing it or by
+//! retiring threads one at a time. Once the foreign load has stayed below the
+//! low watermark for as long, mining picks up again. Requiring the load to
+//! persist keeps short spikes (a shell command, a cron job) from toggling
+//! mining.
+
+use crate::miner::Scheduler;
+use crate::stats::StatsReporter;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Name under which thread reductions are requested from the scheduler
+const SOURCE: &str = "foreign_load";
+
+/// How mining yields to foreign load
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum YieldMode {
+    /// Stop hashing entirely
+    #[default]
+    Pause,
+    /// Retire one mining thread per check
+    Shrink,
+}
+
+/// Configuration for yielding to other workloads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForeignLoadConfig {
+    /// Foreign CPU load (% of the host) above which mining yields
+    pub high_load: f32,
+    /// Foreign CPU load (% of the host) below which mining resumes
+    pub low_load: f32,
+    /// Seconds a watermark must stay crossed before acting (default: 30)
+    #[serde(default = "default_sustain")]
+    pub sustain: u64,
+    /// How mining yields (default: pause)
+    #[serde(default)]
+    pub mode: YieldMode,
+    /// Seconds between load checks (default: 5)
+    #[serde(default = "default_check_interval")]
+    pub check_interval: u64,
+    /// Threads kept running in shrink mode (default: 1)
+    #[serde(default = "default_min_threads")]
+    pub min_threads: usize,
+}
+
+fn default_sustain() -> u64 {
+    30
+}
+
+fn default_check_interval() -> u64 {
+    5
+}
+
+fn default_min_threads() -> usize {
+    1
+}
+
+/// Which watermark the foreign load is currently past
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Load {
+    High,
+    Low,
+}
+
+/// Pauses or shrinks mining while other processes need the CPU
+pub struct ForeignLoadController {
+    /// Watermarks and yield mode
+    config: ForeignLoadConfig,
+    /// Scheduler whose workers yield
+    scheduler: Arc<Scheduler>,
+    /// Watermark crossed by the recent readings, and since when
+    crossed: Option<(Load, Instant)>,
+    /// Threads retired by this controller and not yet restored
+    retired: usize,
+}
+
+impl ForeignLoadController {
+    /// Creates a controller for `scheduler`
+    ///
+    /// # Arguments
+    /// * `config` - Watermarks and yield mode
+    /// * `scheduler` - Scheduler whose workers yield
+    pub fn new(config: ForeignLoadConfig, scheduler: Arc<Scheduler>) -> Self {
+        ForeignLoadController {
+            config,
+            scheduler,
+            crossed: None,
+            retired: 0,
+        }
+    }
+
+    /// Starts checking the foreign load on a background thread
+    ///
+    /// # Arguments
+    /// * `reporter` - Statistics reporter whose system information is sampled
+    pub fn start(mut self, mut reporter: StatsReporter) {
+        std::thread::spawn(move || {
+            let interval = Duration::from_secs(self.config.check_interval.max(1));
+            // The first sample only establishes the baseline for CPU usage
+            reporter.get_hardware_stats();
+            loop {
+                std::thread::sleep(interval);
+                let load = reporter.get_hardware_stats().foreign_cpu_usage;
+                self.update(load, Instant::now());
+            }
+        });
+    }
+
+    /// Applies one control step for a foreign load reading
+    ///
+    /// Once the load has stayed above the high watermark for `sustain`
+    /// seconds mining yields by one step (pausing is a single step); once it
+    /// has stayed below the low watermark as long, one step is undone. In
+    /// between the sustain timer restarts.
+    ///
+    /// # Arguments
+    /// * `load` - CPU usage of other processes, in percent of the host
+    /// * `now` - Time of the reading
+    pub fn update(&mut self, load: f32, now: Instant) {
+        let current = if load > self.config.high_load {
+            Load::High
+        } else if load < self.config.low_load {
+            Load::Low
+        } else {
+            self.crossed = None;
+            return;
+        };
+
+        let since = match self.crossed {
+            Some((crossed, since)) if crossed == current => since,
+            _ => {
+                self.crossed = Some((current, now));
+                now
+            }
+        };
+        if now.duration_since(since) < Duration::from_secs(self.config.sustain) {
+            return;
+        }
+        match current {
+            Load::High => self.yield_step(load),
+            Load::Low => self.resume_step(load),
+        }
+    }
+
+    /// Reduces mining by one step
+    fn yield_step(&mut self, load: f32) {
+        match self.config.mode {
+            YieldMode::Pause => {
+                if self.scheduler.is_paused() {
+                    return;
+                }
+                log::warn!(
+                    "Other processes using {:.1}% CPU (limit {:.1}%), pausing mining",
+                    load,
+                    self.config.high_load
+                );
+                self.scheduler.set_paused(true);
+            }
+            YieldMode::Shrink => {
+                let threads = self.scheduler.base_threads().saturating_sub(self.retired);
+                if threads <= self.config.min_threads {
+                    return;
+                }
+                log::warn!(
+                    "Other processes using {:.1}% CPU (limit {:.1}%), reducing mining threads to {}",
+                    load,
+                    self.config.high_load,
+                    threads - 1
+                );
+                self.retired += 1;
+                self.scheduler.request_fewer_threads(SOURCE, self.retired);
+            }
+        }
+    }
+
+    /// Undoes one step of yielding
+    fn resume_step(&mut self, load: f32) {
+        match self.config.mode {
+            YieldMode::Pause => {
+                if !self.scheduler.is_paused() {
+                    return;
+                }
+                log::info!("Other processes down to {:.1}% CPU, resuming mining", load);
+                self.scheduler.set_paused(false);
+            }
+            YieldMode::Shrink => {
+                if self.retired == 0 {
+                    return;
+                }
+                self.retired -= 1;
+                log::info!(
+                    "Other processes down to {:.1}% CPU, restoring mining threads to {}",
+                    load,
+                    self.scheduler.base_threads().saturating_sub(self.retired)
+                );
+                self.scheduler.request_fewer_threads(SOURCE, self.retired);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUSTAIN: Duration = Duration::from_secs(30);
+
+    fn controller(mode: YieldMode, threads: usize) -> ForeignLoadController {
+        let (shares, _) = crossbeam_channel::unbounded();
+        let scheduler = Arc::new(Scheduler::new(shares, 1000, None, false));
+        scheduler.set_threads(threads);
+        let config = ForeignLoadConfig {
+            high_load: 50.0,
+            low_load: 20.0,
+            sustain: SUSTAIN.as_secs(),
+            mode,
+            check_interval: 5,
+            min_threads: 2,
+        };
+        ForeignLoadController::new(config, scheduler)
+    }
+
+    #[test]
+    fn sustained_high_load_pauses_mining() {
+        let mut yielding = controller(YieldMode::Pause, 4);
+        let start = Instant::now();
+        yielding.update(80.0, start);
+        yielding.update(80.0, start + SUSTAIN / 2);
+        assert!(!yielding.scheduler.is_paused());
+
+        yielding.update(80.0, start + SUSTAIN);
+        assert!(yielding.scheduler.is_paused());
+
+        // Resumes only once the load has stayed low as long
+        yielding.update(10.0, start + SUSTAIN * 2);
+        assert!(yielding.scheduler.is_paused());
+        yielding.update(10.0, start + SUSTAIN * 3);
+        assert!(!yielding.scheduler.is_paused());
+    }
+
+    #[test]
+    fn dip_between_watermarks_restarts_sustain() {
+        let mut yielding = controller(YieldMode::Pause, 4);
+        let start = Instant::now();
+        yielding.update(80.0, start);
+        yielding.update(30.0, start + SUSTAIN / 2);
+        yielding.update(80.0, start + SUSTAIN);
+        assert!(!yielding.scheduler.is_paused());
+
+        yielding.update(80.0, start + SUSTAIN * 2);
+        assert!(yielding.scheduler.is_paused());
+
+        // The same applies while waiting to resume
+        yielding.update(10.0, start + SUSTAIN * 3);
+        yielding.update(30.0, start + SUSTAIN * 3 + SUSTAIN / 2);
+        yielding.update(10.0, start + SUSTAIN * 4);
+        assert!(yielding.scheduler.is_paused());
+    }
+
+    #[test]
+    fn shrink_stops_at_min_threads() {
+        let mut yielding = controller(YieldMode::Shrink, 4);
+        let start = Instant::now();
+        yielding.update(80.0, start);
+        for step in 1..=4 {
+            yielding.update(80.0, start + SUSTAIN * step);
+        }
+        // 2 threads left, the configured minimum
+        assert_eq!(yielding.retired, 2);
+        assert_eq!(yielding.scheduler.base_threads(), 4);
+        assert!(!yielding.scheduler.is_paused());
+    }
+
+    #[test]
+    fn shrink_restores_one_thread_per_step() {
+        let mut yielding = controller(YieldMode::Shrink, 4);
+        let start = Instant::now();
+        yielding.update(80.0, start);
+        yielding.update(80.0, start + SUSTAIN);
+        yielding.update(80.0, start + SUSTAIN * 2);
+        assert_eq!(yielding.retired, 2);
+
+        let low = start + SUSTAIN * 3;
+        yielding.update(10.0, low);
+        assert_eq!(yielding.retired, 2);
+        yielding.update(10.0, low + SUSTAIN);
+        assert_eq!(yielding.retired, 1);
+        yielding.update(10.0, low + SUSTAIN * 2);
+        assert_eq!(yielding.retired, 0);
+        yielding.update(10.0, low + SUSTAIN * 3);
+        assert_eq!(yielding.retired, 0);
+    }
+}
@@ -25,7 +25,63 @@ pub mod scheduler;
 /// Workers receive jobs from the scheduler and submit found shares.
 pub mod worker;
 
+/// Local share re-verification
+///
+/// Recomputes share hashes with an independent algorithm instance and
+/// counts mismatches as hardware errors.
+pub mod verifier;
+
+/// CPU affinity for mining threads
+///
+/// Resolves the configured CPU list (or the automatic physical-core-first
+/// order) and pins worker threads to it.
+pub mod affinity;
+
+/// Cache-aware automatic thread count
+///
+/// Reads the last-level cache layout and sizes the worker pool so every
+/// thread's scratchpad fits in cache.
+pub mod topology;
+
+/// NUMA topology and memory locality
+///
+/// Detects NUMA nodes so RandomX can keep one dataset per node and mining
+/// threads can stay on the node holding their dataset.
+pub mod numa;
+
+/// CPU feature detection and RandomX flag reporting
+///
+/// Detects AES-NI, SSSE3, AVX2 and AVX-512 and derives the RandomX flags
+/// the library runs with from them.
+pub mod features;
+
+/// Thermal throttling
+///
+/// Retires threads or adds idle time when the CPU runs hot and undoes it
+/// once the CPU cools down.
+pub mod thermal;
+
+/// CPU usage cap
+///
+/// Measures the miner's CPU usage and adds idle time to keep it under the
+/// configured share of the host.
+pub mod cpu_limit;
+
+/// Yielding to other workloads
+///
+/// Pauses mining or retires threads while other processes keep the CPU busy
+/// and undoes it once they are done.
+pub mod foreign_load;
+
+/// Scheduling priority for mining threads
+///
+/// Lowers mining and dataset initialization threads to nice 19 or
+/// `SCHED_IDLE` so interactive work always runs first.
+pub mod priority;
+
 // Re-export main components for cleaner imports
+pub use self::affinity::CpuAffinity;
 pub use self::algorithm::Algorithm;
-pub use self::scheduler::{MiningJob, Scheduler, Share};
+pub use self::scheduler::{MiningJob, NonceSpace, Scheduler, Share};
+pub use self::verifier::ShareVerifier;
 pub use self::worker::Worker;
@@ -0,0 +1,196 @@
+// src/miner/numa.rs
+//! NUMA topology and memory locality
+//!
+//! On multi-socket hosts every RandomX dataset lookup that crosses the
+//! socket interconnect costs roughly twice a local one. The miner therefore
+//! builds one dataset per NUMA node and keeps every mining thread on the CPUs
+//! of a single node, hashing against that node's dataset.
+//!
+//! Datasets are placed by the kernel's first-touch policy: a dataset is
+//! initialized from a thread bound to its node's CPUs, so its pages are
+//! allocated from that node's memory.
+
+use crate::miner::topology::parse_cpu_list;
+use std::cell::Cell;
+use std::path::Path;
+
+/// Sysfs directory describing the NUMA nodes (Linux)
+const SYSFS_NODE_DIR: &str = "/sys/devices/system/node";
+
+thread_local! {
+    /// Index (into [`nodes`]) of the NUMA node the current thread is bound to
+    static CURRENT_NODE: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A NUMA node and the logical CPUs attached to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumaNode {
+    /// Kernel node id (not necessarily contiguous)
+    pub id: usize,
+    /// Logical CPUs local to this node
+    pub cpus: Vec<usize>,
+}
+
+/// Lists the NUMA nodes that have CPUs, ordered by node id
+///
+/// # Returns
+/// The nodes, or an empty list on single-node or non-Linux hosts where the
+/// topology is not exposed
+pub fn nodes() -> Vec<NumaNode> {
+    nodes_in(Path::new(SYSFS_NODE_DIR))
+}
+
+/// Lists the NUMA nodes under `sysfs` that have CPUs, ordered by node id
+///
+/// # Arguments
+/// * `sysfs` - Directory holding the `nodeN/cpulist` entries
+pub fn nodes_in(sysfs: &Path) -> Vec<NumaNode> {
+    let Ok(entries) = std::fs::read_dir(sysfs) else {
+        return Vec::new();
+    };
+
+    let mut nodes: Vec<NumaNode> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let id = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("node")?
+                .parse()
+                .ok()?;
+            let cpus = parse_cpu_list(&read_cpulist(&entry.path())?);
+            (!cpus.is_empty()).then_some(NumaNode { id, cpus })
+        })
+        .collect();
+    nodes.sort_by_key(|node| node.id);
+    nodes
+}
+
+/// Finds the node a logical CPU belongs to
+///
+/// # Returns
+/// The index of the node in `nodes`, if any contains `cpu`
+pub fn node_of(nodes: &[NumaNode], cpu: usize) -> Option<usize> {
+    nodes.iter().position(|node| node.cpus.contains(&cpu))
+}
+
+/// Records the node the current thread hashes on
+///
+/// # Arguments
+/// * `index` - Index of the node in the list returned by [`nodes`]
+pub fn set_current_node(index: usize) {
+    CURRENT_NODE.with(|node| node.set(index));
+}
+
+/// Index of the node the current thread hashes on (0 when never set)
+pub fn current_node() -> usize {
+    CURRENT_NODE.with(Cell::get)
+}
+
+/// Restricts the calling thread to a set of logical CPUs
+///
+/// Threads spawned afterwards by the calling thread inherit the restriction.
+///
+/// # Returns
+/// `true` if the operating system accepted the CPU set
+#[cfg(target_os = "linux")]
+pub fn bind_current_thread(cpus: &[usize]) -> bool {
+    use nix::sched::{CpuSet, sched_setaffinity};
+    use nix::unistd::Pid;
+
+    let mut set = CpuSet::new();
+    for &cpu in cpus {
+        if set.set(cpu).is_err() {
+            return false;
+        }
+    }
+    sched_setaffinity(Pid::from_raw(0), &set).is_ok()
+}
+
+/// Restricts the calling thread to a set of logical CPUs
+///
+/// CPU sets are only supported on Linux; elsewhere this does nothing.
+///
+/// # Returns
+/// Always `false`
+#[cfg(not(target_os = "linux"))]
+pub fn bind_current_thread(_cpus: &[usize]) -> bool {
+    false
+}
+
+/// Reads a node's CPU list file
+fn read_cpulist(node_dir: &Path) -> Option<String> {
+    std::fs::read_to_string(node_dir.join("cpulist")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Writes a fake sysfs node tree with the CPU list of each node id
+    fn sysfs(name: &str, nodes: &[(usize, &str)]) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("xmr_miner-numa-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        for (id, cpulist) in nodes {
+            let node = root.join(format!("node{}", id));
+            std::fs::create_dir_all(&node).unwrap();
+            std::fs::write(node.join("cpulist"), format!("{}\n", cpulist)).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn parses_nodes_with_cpu_ranges() {
+        // Memory-only nodes have an empty CPU list
+        let root = sysfs("two", &[(10, "8-11"), (0, "0-3,16-19"), (2, "")]);
+        std::fs::write(root.join("possible"), "0-10\n").unwrap();
+        let nodes = nodes_in(&root);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            nodes,
+            vec![
+                NumaNode {
+                    id: 0,
+                    cpus: vec![0, 1, 2, 3, 16, 17, 18, 19],
+                },
+                NumaNode {
+                    id: 10,
+                    cpus: vec![8, 9, 10, 11],
+                },
+            ]
+        );
+        assert_eq!(node_of(&nodes, 17), Some(0));
+        assert_eq!(node_of(&nodes, 9), Some(1));
+        assert_eq!(node_of(&nodes, 4), None);
+    }
+
+    #[test]
+    fn single_node_and_missing_topology() {
+        let root = sysfs("one", &[(0, "0-7")]);
+        let nodes = nodes_in(&root);
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(node_of(&nodes, 7), Some(0));
+
+        assert!(nodes_in(&root).is_empty());
+    }
+
+    #[test]
+    fn threads_start_on_the_first_node() {
+        std::thread::spawn(|| {
+            assert_eq!(current_node(), 0);
+            set_current_node(1);
+            assert_eq!(current_node(), 1);
+        })
+        .join()
+        .unwrap();
+        // The node is per thread
+        std::thread::spawn(|| assert_eq!(current_node(), 0))
+            .join()
+            .unwrap();
+    }
+}
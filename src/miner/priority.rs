@@ -0,0 +1,148 @@
+// src/miner/priority.rs
+//! Scheduling priority for mining threads
+//!
+//! Mining keeps every CPU it is given busy. On a developer workstation that
+//! is only acceptable if the kernel always prefers interactive work, so the
+//! mining threads and the threads building the RandomX dataset can run at the
+//! lowest nice level, or under Linux's `SCHED_IDLE` policy, which only gets
+//! CPU time no other thread wants. New threads inherit the priority of the
+//! thread that spawns them.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thread_priority::{Error, ThreadPriority};
+
+/// Scheduling priority of mining threads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    /// Leave the priority unchanged
+    #[default]
+    Normal,
+    /// Lowest nice level (19 on Unix, lowest priority on Windows)
+    Low,
+    /// `SCHED_IDLE` on Linux: run only when the CPU is otherwise idle
+    /// (same as `Low` elsewhere)
+    Idle,
+}
+
+impl Priority {
+    /// Applies the priority to the calling thread
+    ///
+    /// Threads spawned afterwards by the calling thread inherit it.
+    ///
+    /// # Returns
+    /// `true` if the priority is in effect (always for `Normal`)
+    pub fn apply(self) -> bool {
+        self == Priority::Normal || set_current(self).is_ok()
+    }
+
+    /// Runs `f` on a thread with this priority
+    ///
+    /// Threads `f` spawns (e.g. RandomX dataset initialization) inherit the
+    /// priority while the calling thread keeps its own.
+    ///
+    /// # Arguments
+    /// * `task` - What `f` does, for the warning if the priority cannot be set
+    /// * `f` - The work to run
+    ///
+    /// # Returns
+    /// The result of `f`
+    pub fn run<T: Send>(self, task: &str, f: impl FnOnce() -> T + Send) -> T {
+        if self == Priority::Normal {
+            return f();
+        }
+        std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    if !self.apply() {
+                        log::warn!("Failed to set {} priority for {}", self, task);
+                    }
+                    f()
+                })
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        })
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Priority::Normal => write!(f, "normal"),
+            Priority::Low => write!(f, "low"),
+            Priority::Idle => write!(f, "idle"),
+        }
+    }
+}
+
+/// Lowers the calling thread to nice 19, or to `SCHED_IDLE` for `Idle`
+#[cfg(target_os = "linux")]
+fn set_current(priority: Priority) -> Result<(), Error> {
+    use thread_priority::{
+        NormalThreadSchedulePolicy, ThreadSchedulePolicy, set_thread_priority_and_policy,
+        thread_native_id,
+    };
+
+    let policy = match priority {
+        Priority::Idle => NormalThreadSchedulePolicy::Idle,
+        Priority::Normal | Priority::Low => NormalThreadSchedulePolicy::Other,
+    };
+    set_thread_priority_and_policy(
+        thread_native_id(),
+        ThreadPriority::Min,
+        ThreadSchedulePolicy::Normal(policy),
+    )
+}
+
+/// Lowers the calling thread to the lowest priority the platform offers
+#[cfg(not(target_os = "linux"))]
+fn set_current(_priority: Priority) -> Result<(), Error> {
+    thread_priority::set_current_thread_priority(ThreadPriority::Min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_names_are_lowercase() {
+        for (priority, name) in [
+            (Priority::Normal, "normal"),
+            (Priority::Low, "low"),
+            (Priority::Idle, "idle"),
+        ] {
+            let json = format!("\"{}\"", name);
+            assert_eq!(serde_json::to_string(&priority).unwrap(), json);
+            assert_eq!(serde_json::from_str::<Priority>(&json).unwrap(), priority);
+            assert_eq!(priority.to_string(), name);
+        }
+        assert!(serde_json::from_str::<Priority>("\"Low\"").is_err());
+        assert_eq!(Priority::default(), Priority::Normal);
+    }
+
+    #[test]
+    fn normal_runs_on_the_calling_thread() {
+        let caller = std::thread::current().id();
+        let ran_on = Priority::Normal.run("test", || std::thread::current().id());
+        assert_eq!(ran_on, caller);
+        assert!(Priority::Normal.apply());
+    }
+
+    #[test]
+    fn lowered_priority_runs_on_its_own_thread() {
+        let caller = std::thread::current().id();
+        for priority in [Priority::Low, Priority::Idle] {
+            let (ran_on, value) = priority.run("test", || (std::thread::current().id(), 42));
+            assert_ne!(ran_on, caller);
+            assert_eq!(value, 42);
+        }
+    }
+
+    #[test]
+    fn panics_reach_the_caller() {
+        let result = std::panic::catch_unwind(|| Priority::Low.run("test", || panic!("boom")));
+        let panic = result.unwrap_err();
+        assert_eq!(panic.downcast_ref::<&str>(), Some(&"boom"));
+    }
+}
@@ -4,14 +4,77 @@
 //! Manages the distribution of mining jobs to workers and collection of shares.
 //! Handles job updates, nonce distribution, and worker coordination.
 
-use crate::miner::algorithm::Algorithm;
+use crate::miner::affinity::{self, CpuAffinity};
+use crate::miner::algorithm::{self, Algorithm, KEY_SIZE, NONCE_OFFSET};
+use crate::miner::features::RandomXFlags;
+use crate::miner::numa;
+use crate::miner::priority::Priority;
+use crate::miner::verifier::{ShareVerifier, VerifierCache};
+use crate::network::template::BlockTemplate;
+use crate::stats::ShareResult;
 use crate::types::AlgorithmType;
+use crate::utils::difficulty::meets_target;
+use crate::utils::error::MinerError;
 use arc_swap::ArcSwap;
 use crossbeam_channel::Sender;
-use rayon::prelude::*;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-//use crate::utils::error::MinerError;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Highest idle share a duty cycle may impose, in percent
+pub const MAX_IDLE_PERCENT: u32 = 95;
+
+/// Slice of the 32-bit header nonce space a job may be mined in
+///
+/// The high-order bits of every nonce are fixed to `prefix`; only the
+/// remaining `32 - prefix_bits` low-order bits are varied. Pools using
+/// NiceHash-style splitting fix the top nonce byte, and rigs sharing a login
+/// fix further bits with their `instance_id`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NonceSpace {
+    /// Value of the fixed high-order bits
+    pub prefix: u32,
+    /// Number of fixed high-order bits (0 = the whole space is free)
+    pub prefix_bits: u32,
+}
+
+impl NonceSpace {
+    /// Builds the NiceHash space from the fixed top nonce byte of a blob
+    ///
+    /// # Returns
+    /// `None` if the blob is too short to contain a nonce
+    pub fn nicehash(blob: &[u8]) -> Option<Self> {
+        blob.get(NONCE_OFFSET + 3).map(|&byte| NonceSpace {
+            prefix: byte as u32,
+            prefix_bits: 8,
+        })
+    }
+
+    /// Narrows the space by fixing the next `bits` high-order bits to `value`
+    ///
+    /// # Arguments
+    /// * `value` - Value of the newly fixed bits (truncated to `bits`)
+    /// * `bits` - Number of bits to fix (capped at the free bits left)
+    pub fn split(self, value: u32, bits: u32) -> Self {
+        let bits = bits.min(32 - self.prefix_bits);
+        let mask = (1u64 << bits) - 1;
+        NonceSpace {
+            prefix: (((self.prefix as u64) << bits) | (value as u64 & mask)) as u32,
+            prefix_bits: self.prefix_bits + bits,
+        }
+    }
+
+    /// Number of nonces in the space
+    pub fn size(&self) -> u64 {
+        1 << (32 - self.prefix_bits)
+    }
+
+    /// Returns the nonce at `offset` within the space
+    pub fn nonce(&self, offset: u64) -> u64 {
+        ((self.prefix as u64) << (32 - self.prefix_bits)) | (offset & (self.size() - 1))
+    }
+}
 
 /// Represents a mining job received from the pool or node
 #[derive(Debug, Clone)]
@@ -24,6 +87,15 @@ pub struct MiningJob {
     pub target: Vec<u8>,
     /// Algorithm to use for this job
     pub algorithm: AlgorithmType,
+    /// RandomX seed hash (hex) if provided by the pool or node
+    pub seed_hash: Option<String>,
+    /// Part of the nonce space the pool allows this job to be mined in
+    pub nonce_space: NonceSpace,
+    /// Block template for solo mining jobs
+    ///
+    /// When present, the scheduler rebuilds the hashing blob with a new extra
+    /// nonce every time the 32-bit nonce space is exhausted.
+    pub template: Option<Arc<BlockTemplate>>,
 }
 
 /// Represents a valid share found by a worker
@@ -35,6 +107,58 @@ pub struct Share {
     pub nonce: u64,
     /// Resulting hash that meets the target
     pub result: [u8; 32],
+    /// Extra nonce written into the block template (0 for pool jobs)
+    pub extra_nonce: u64,
+    /// When the share was found; bounds how long it is held for submission
+    pub found: Instant,
+}
+
+/// Configured thread count and the reductions requested by controllers
+#[derive(Debug, Default)]
+struct ThreadTarget {
+    /// Thread count set by the user (configuration, signals)
+    base: usize,
+    /// Threads retired by each controller; the largest applies
+    reductions: HashMap<&'static str, usize>,
+}
+
+impl ThreadTarget {
+    /// Number of threads that should be running
+    fn effective(&self) -> usize {
+        let reduction = self.reductions.values().copied().max().unwrap_or(0);
+        self.base.saturating_sub(reduction)
+    }
+}
+
+/// Mining instance of each algorithm with the key it was created with
+type AlgorithmCache = Mutex<HashMap<AlgorithmType, ([u8; KEY_SIZE], Arc<dyn Algorithm>)>>;
+
+/// Per-thread cache of the hashing blob for the current extra nonce round
+#[derive(Default)]
+struct RoundBlob {
+    /// Job the cached blob belongs to
+    job_id: String,
+    /// Extra nonce round the cached blob was built for
+    round: u32,
+    /// Cached hashing blob
+    blob: Vec<u8>,
+}
+
+impl RoundBlob {
+    /// Returns the hashing blob for `round`, rebuilding it when the job or round changed
+    fn get(
+        &mut self,
+        job: &MiningJob,
+        template: &BlockTemplate,
+        round: u32,
+    ) -> Result<&[u8], MinerError> {
+        if self.blob.is_empty() || self.job_id != job.job_id || self.round != round {
+            self.blob = template.hashing_blob(template.extra_nonce(round))?;
+            self.job_id = job.job_id.clone();
+            self.round = round;
+        }
+        Ok(&self.blob)
+    }
 }
 
 /// Coordinates mining jobs across worker threads
@@ -49,6 +173,31 @@ pub struct Scheduler {
     active: Arc<AtomicBool>,
     /// Number of nonces each worker processes per batch
     batch_size: u64,
+    /// Nonce byte reserved for this rig among rigs sharing a login
+    instance_id: Option<u8>,
+    /// Algorithm instances for the latest key of each algorithm, shared by
+    /// all workers
+    algorithms: Arc<AlgorithmCache>,
+    /// Re-verifies shares before they are sent (disabled when `None`)
+    verifier: Option<Arc<ShareVerifier>>,
+    /// CPUs mining threads are pinned to (unpinned when `None`)
+    affinity: Option<CpuAffinity>,
+    /// Configured algorithm (set by `start_mining`, which enables workers)
+    algorithm: Mutex<Option<AlgorithmType>>,
+    /// Retire flag of each running worker, in worker index order
+    workers: Mutex<Vec<Arc<AtomicBool>>>,
+    /// Configured thread count and the reductions requested by controllers
+    thread_target: Mutex<ThreadTarget>,
+    /// Share of time workers sleep after each batch, in percent
+    idle_percent: Arc<AtomicU32>,
+    /// Idle share requested by each controller; the largest is applied
+    idle_requests: Mutex<HashMap<&'static str, u32>>,
+    /// Workers wait without hashing while set
+    paused: Arc<AtomicBool>,
+    /// Scheduling priority of mining threads
+    priority: Priority,
+    /// Flags RandomX instances are created with
+    randomx_flags: RandomXFlags,
 }
 
 impl Scheduler {
@@ -57,63 +206,422 @@ impl Scheduler {
     /// # Arguments
     /// * `share_sender` - Channel for sending valid shares
     /// * `batch_size` - Number of nonces each worker processes at once
-    pub fn new(share_sender: Sender<Share>, batch_size: u64) -> Self {
+    /// * `instance_id` - Nonce byte reserved for this rig, fixed below any
+    ///   pool-assigned prefix so rigs sharing a login never overlap
+    /// * `verify_shares` - Recompute every share with an independent verifier
+    ///   before sending it
+    pub fn new(
+        share_sender: Sender<Share>,
+        batch_size: u64,
+        instance_id: Option<u8>,
+        verify_shares: bool,
+    ) -> Self {
         Scheduler {
             current_job: Arc::new(ArcSwap::from_pointee(None)),
             nonce_counter: Arc::new(AtomicU64::new(0)),
             share_sender,
             active: Arc::new(AtomicBool::new(true)),
             batch_size,
+            instance_id,
+            algorithms: Arc::new(Mutex::new(HashMap::new())),
+            verifier: verify_shares.then(|| Arc::new(ShareVerifier::new())),
+            affinity: None,
+            algorithm: Mutex::new(None),
+            workers: Mutex::new(Vec::new()),
+            thread_target: Mutex::new(ThreadTarget::default()),
+            idle_percent: Arc::new(AtomicU32::new(0)),
+            idle_requests: Mutex::new(HashMap::new()),
+            paused: Arc::new(AtomicBool::new(false)),
+            priority: Priority::Normal,
+            randomx_flags: RandomXFlags::detect(),
         }
     }
 
+    /// Pins mining threads started afterwards to the given CPUs
+    ///
+    /// # Arguments
+    /// * `affinity` - CPU selection, or `None` to leave threads unpinned
+    pub fn with_affinity(mut self, affinity: Option<CpuAffinity>) -> Self {
+        self.affinity = affinity;
+        self
+    }
+
+    /// Runs mining threads started afterwards at the given priority
+    ///
+    /// Covers the worker threads and any algorithm a worker creates when a
+    /// job switches algorithms.
+    ///
+    /// # Arguments
+    /// * `priority` - Scheduling priority of mining threads
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Creates RandomX instances, for mining and share verification, with
+    /// the given flags instead of the fastest ones the CPU supports
+    ///
+    /// # Arguments
+    /// * `flags` - Flags selected with [`RandomXFlags::select`]
+    pub fn with_randomx_flags(mut self, flags: RandomXFlags) -> Self {
+        self.randomx_flags = flags;
+        if let Some(verifier) = self.verifier.as_mut().and_then(Arc::get_mut) {
+            *verifier = std::mem::take(verifier).with_cache(VerifierCache::new(flags));
+        }
+        self
+    }
+
+    /// Reports shares dropped by local re-verification to the statistics
+    ///
+    /// Has no effect when share verification is disabled.
+    ///
+    /// # Arguments
+    /// * `stats` - Sender obtained from [`StatsReporter::share_sender`]
+    ///
+    /// [`StatsReporter::share_sender`]: crate::stats::StatsReporter::share_sender
+    pub fn with_stats(mut self, stats: Sender<ShareResult>) -> Self {
+        if let Some(verifier) = &self.verifier {
+            let verifier = ShareVerifier::new()
+                .with_cache(verifier.cache().clone())
+                .with_stats(stats);
+            self.verifier = Some(Arc::new(verifier));
+        }
+        self
+    }
+
     /// Updates the current mining job
     ///
+    /// The first job of an algorithm, and every job bringing a new RandomX
+    /// seed hash, first creates the algorithm instance for its key (at the
+    /// mining priority), so workers keep hashing the previous job until the
+    /// new dataset is ready.
+    ///
     /// # Arguments
     /// * `new_job` - The new job to replace the current one
     pub fn update_job(&self, new_job: MiningJob) {
+        match algorithm::job_key(new_job.algorithm, new_job.seed_hash.as_deref()) {
+            Ok(key) => {
+                let task = format!("{} initialization", new_job.algorithm);
+                self.priority.run(&task, || {
+                    algorithm_for(&self.algorithms, new_job.algorithm, key, self.randomx_flags)
+                });
+            }
+            // Workers report the job as unminable
+            Err(e) => log::debug!("No algorithm key for job {}: {}", new_job.job_id, e),
+        }
         self.current_job.store(Arc::new(Some(new_job)));
         self.nonce_counter.store(0, Ordering::SeqCst);
     }
 
+    /// Withdraws the current job
+    ///
+    /// Workers finish their current batch and then wait for the next job.
+    pub fn clear_job(&self) {
+        self.current_job.store(Arc::new(None));
+    }
+
     /// Starts the mining process with the given algorithm
     ///
+    /// No algorithm instance is created up front: RandomX needs the seed hash
+    /// of the first job, so [`update_job`](Self::update_job) creates each
+    /// instance for the key of the job that needs it and workers wait until
+    /// then. Jobs requesting a different algorithm (e.g. from multi-algo
+    /// pools) switch the workers over; each algorithm is created once and
+    /// shared. RandomX is re-keyed whenever a job brings a new seed hash,
+    /// replacing the previous dataset.
+    ///
+    /// With a CPU affinity configured every worker is pinned to its CPU. On
+    /// multi-node NUMA hosts every worker stays on one node (its pinned CPU's
+    /// node, or nodes in turn) and hashes against that node's dataset. Every
+    /// worker hashes its batches on its own thread, so the number of workers
+    /// bounds the CPUs mining uses.
+    ///
     /// # Arguments
-    /// * `algorithm` - The mining algorithm to use
+    /// * `algorithm` - The configured mining algorithm
     /// * `workers` - Number of worker threads to spawn
-    pub fn start_mining(&self, algorithm: Arc<dyn Algorithm + Send + Sync>, workers: usize) {
-        (0..workers).for_each(|_| {
-            let job_arc = self.current_job.clone();
-            let nonce_ctr = self.nonce_counter.clone();
-            let sender = self.share_sender.clone();
-            let active = self.active.clone();
-            let batch = self.batch_size;
-            let algo = algorithm.clone();
-
-            std::thread::spawn(move || {
-                while active.load(Ordering::Relaxed) {
-                    let current_job = job_arc.load();
-                    if let Some(job) = &**current_job {
-                        let start_nonce = nonce_ctr.fetch_add(batch, Ordering::SeqCst);
-                        (start_nonce..start_nonce + batch)
-                            .into_par_iter()
-                            .for_each(|nonce| match algo.hash(&job.blob, nonce) {
-                                Ok(hash) => {
-                                    if hash.as_ref() < job.target.as_slice() {
-                                        let _ = sender.send(Share {
-                                            job_id: job.job_id.clone(),
-                                            nonce,
-                                            result: hash,
-                                        });
+    pub fn start_mining(&self, algorithm: AlgorithmType, workers: usize) {
+        *self.algorithm.lock().expect("algorithm poisoned") = Some(algorithm);
+
+        let nodes = numa::nodes();
+        if nodes.len() > 1 {
+            log::info!("Spreading mining threads over {} NUMA nodes", nodes.len());
+        }
+        if let Some(affinity) = &self.affinity
+            && affinity.assign(1).is_empty()
+        {
+            log::warn!("No CPUs available for affinity, mining threads stay unpinned");
+        }
+        if self.priority != Priority::Normal {
+            log::info!("Running mining threads at {} priority", self.priority);
+        }
+        self.set_threads(workers);
+    }
+
+    /// Changes the configured number of mining threads
+    ///
+    /// Reductions requested with
+    /// [`request_fewer_threads`](Self::request_fewer_threads) stay in effect
+    /// and are applied on top of the new count.
+    ///
+    /// # Arguments
+    /// * `threads` - Number of mining threads to run when nothing throttles
+    pub fn set_threads(&self, threads: usize) {
+        let mut target = self.thread_target.lock().expect("thread target poisoned");
+        target.base = threads;
+        self.resize(target.effective());
+    }
+
+    /// Configured number of mining threads, before any reductions
+    pub fn base_threads(&self) -> usize {
+        self.thread_target
+            .lock()
+            .expect("thread target poisoned")
+            .base
+    }
+
+    /// Retires threads on behalf of a controller
+    ///
+    /// Several controllers (thermal, foreign load, ...) can request fewer
+    /// threads independently; the largest reduction applies, so each one only
+    /// ever undoes its own throttling.
+    ///
+    /// # Arguments
+    /// * `source` - Name of the requesting controller (e.g. "thermal")
+    /// * `threads` - Number of threads to retire (0 = withdraw the request)
+    pub fn request_fewer_threads(&self, source: &'static str, threads: usize) {
+        let mut target = self.thread_target.lock().expect("thread target poisoned");
+        target.reductions.insert(source, threads);
+        self.resize(target.effective());
+    }
+
+    /// Starts or retires workers until `threads` are running
+    ///
+    /// New workers pick up the current job immediately; retired workers finish
+    /// the batch they are hashing and exit. The job and nonce counter are left
+    /// untouched, so no work is dropped. Has no effect before
+    /// [`start_mining`](Self::start_mining).
+    fn resize(&self, threads: usize) {
+        if self.algorithm.lock().expect("algorithm poisoned").is_none() {
+            return;
+        }
+        let mut workers = self.workers.lock().expect("worker list poisoned");
+        if workers.len() == threads {
+            return;
+        }
+        log::info!("Mining threads: {} -> {}", workers.len(), threads);
+
+        while workers.len() > threads {
+            if let Some(retired) = workers.pop() {
+                retired.store(true, Ordering::Relaxed);
+            }
+        }
+        let nodes = numa::nodes();
+        while workers.len() < threads {
+            let retired = Arc::new(AtomicBool::new(false));
+            self.spawn_worker(workers.len(), &nodes, retired.clone());
+            workers.push(retired);
+        }
+    }
+
+    /// Number of mining threads currently running
+    pub fn threads(&self) -> usize {
+        self.workers.lock().expect("worker list poisoned").len()
+    }
+
+    /// Makes workers sleep for part of the time (duty cycling)
+    ///
+    /// After each batch a worker sleeps long enough that the given share of
+    /// its time is spent idle. Several controllers (thermal, CPU cap, ...) can
+    /// request idle time independently; the largest request applies, to
+    /// running workers immediately.
+    ///
+    /// # Arguments
+    /// * `source` - Name of the requesting controller (e.g. "thermal")
+    /// * `percent` - Idle share, capped at [`MAX_IDLE_PERCENT`] (0 = withdraw the request)
+    pub fn request_idle(&self, source: &'static str, percent: u32) {
+        let mut requests = self.idle_requests.lock().expect("idle requests poisoned");
+        requests.insert(source, percent.min(MAX_IDLE_PERCENT));
+        let idle = requests.values().copied().max().unwrap_or(0);
+        if self.idle_percent.swap(idle, Ordering::Relaxed) != idle {
+            log::info!("Mining duty cycle: {}% idle", idle);
+        }
+    }
+
+    /// Idle share currently applied to workers, in percent
+    pub fn idle_percent(&self) -> u32 {
+        self.idle_percent.load(Ordering::Relaxed)
+    }
+
+    /// Pauses or resumes hashing without stopping the workers
+    ///
+    /// Paused workers finish their current batch and then wait; the thread
+    /// count, job and nonce counter are kept, so resuming continues where
+    /// mining left off.
+    ///
+    /// # Arguments
+    /// * `paused` - `true` to pause, `false` to resume
+    pub fn set_paused(&self, paused: bool) {
+        if self.paused.swap(paused, Ordering::Relaxed) != paused {
+            log::info!("Mining {}", if paused { "paused" } else { "resumed" });
+        }
+    }
+
+    /// Whether hashing is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Spawns one mining thread
+    ///
+    /// # Arguments
+    /// * `worker` - Index of the worker, used for CPU and NUMA node placement
+    /// * `nodes` - NUMA nodes of the host
+    /// * `retired` - Set to make this worker exit after its current batch
+    fn spawn_worker(&self, worker: usize, nodes: &[numa::NumaNode], retired: Arc<AtomicBool>) {
+        let job_arc = self.current_job.clone();
+        let nonce_ctr = self.nonce_counter.clone();
+        let sender = self.share_sender.clone();
+        let active = self.active.clone();
+        let batch = self.batch_size;
+        let instance_id = self.instance_id;
+        let algorithms = self.algorithms.clone();
+        let verifier = self.verifier.clone();
+        let idle_percent = self.idle_percent.clone();
+        let paused = self.paused.clone();
+        let priority = self.priority;
+        let randomx_flags = self.randomx_flags;
+        // Algorithm of the current job and the key it was created with
+        let mut current: Option<([u8; KEY_SIZE], Arc<dyn Algorithm>)> = None;
+        let cpu = self
+            .affinity
+            .as_ref()
+            .and_then(|affinity| affinity.assign(worker + 1).get(worker).copied());
+        // NUMA node (index and CPUs) this worker hashes on
+        let node = (nodes.len() > 1)
+            .then(|| match cpu {
+                Some(cpu) => numa::node_of(nodes, cpu),
+                None => Some(worker % nodes.len()),
+            })
+            .flatten()
+            .map(|index| (index, nodes[index].cpus.clone()));
+
+        std::thread::spawn(move || {
+            let mut round_blob = RoundBlob::default();
+            if !priority.apply() {
+                log::warn!(
+                    "Failed to set {} priority for mining thread {}",
+                    priority,
+                    worker
+                );
+            }
+            let mut confined = cpu.is_some_and(|cpu| {
+                let pinned = affinity::pin_current_thread(cpu);
+                if pinned {
+                    log::debug!("Mining thread {} pinned to CPU {}", worker, cpu);
+                } else {
+                    log::warn!("Failed to pin mining thread {} to CPU {}", worker, cpu);
+                }
+                pinned
+            });
+            if let Some((index, node_cpus)) = &node {
+                numa::set_current_node(*index);
+                if !confined {
+                    confined = numa::bind_current_thread(node_cpus);
+                    if !confined {
+                        log::warn!("Failed to bind mining thread {} to NUMA node", worker);
+                    }
+                }
+            }
+
+            while active.load(Ordering::Relaxed) && !retired.load(Ordering::Relaxed) {
+                if paused.load(Ordering::Relaxed) {
+                    std::thread::sleep(std::time::Duration::from_millis(100));
+                    continue;
+                }
+                let current_job = job_arc.load();
+                if let Some(job) = &**current_job {
+                    let key = match algorithm::job_key(job.algorithm, job.seed_hash.as_deref()) {
+                        Ok(key) => key,
+                        Err(e) => {
+                            log::error!("Cannot mine job {}: {}", job.job_id, e);
+                            std::thread::sleep(std::time::Duration::from_millis(100));
+                            continue;
+                        }
+                    };
+                    let algo = match &current {
+                        Some((algo_key, algo))
+                            if algo.algorithm_type() == job.algorithm && *algo_key == key =>
+                        {
+                            algo.clone()
+                        }
+                        _ => {
+                            let algo =
+                                algorithm_for(&algorithms, job.algorithm, key, randomx_flags);
+                            current = Some((key, algo.clone()));
+                            algo
+                        }
+                    };
+                    let space = match instance_id {
+                        Some(id) => job.nonce_space.split(id as u32, 8),
+                        None => job.nonce_space,
+                    };
+                    // The counter runs over consecutive copies of the nonce
+                    // space; each copy is one extra nonce round
+                    let start = nonce_ctr.fetch_add(batch, Ordering::SeqCst);
+                    let round = (start / space.size()) as u32;
+                    let offset = start % space.size();
+                    let offsets = offset..(offset + batch).min(space.size());
+
+                    let (blob, extra_nonce) = match &job.template {
+                        Some(template) => match round_blob.get(job, template, round) {
+                            Ok(blob) => (blob, template.extra_nonce(round)),
+                            Err(e) => {
+                                log::error!("Failed to build hashing blob: {}", e);
+                                std::thread::sleep(std::time::Duration::from_millis(100));
+                                continue;
+                            }
+                        },
+                        None if round == 0 => (job.blob.as_slice(), 0),
+                        None => {
+                            // Pool jobs have no extra nonce; wait for the next job
+                            std::thread::sleep(std::time::Duration::from_millis(100));
+                            continue;
+                        }
+                    };
+
+                    let hash_nonce = |offset: u64| {
+                        let nonce = space.nonce(offset);
+                        match algo.hash(blob, nonce) {
+                            Ok(hash) => {
+                                if meets_target(&hash, &job.target) {
+                                    let share = Share {
+                                        job_id: job.job_id.clone(),
+                                        nonce,
+                                        result: hash,
+                                        extra_nonce,
+                                        found: Instant::now(),
+                                    };
+                                    if verifier
+                                        .as_ref()
+                                        .is_none_or(|v| v.verify(job, blob, &share))
+                                    {
+                                        let _ = sender.send(share);
                                     }
                                 }
-                                Err(e) => log::error!("Hashing failed: {}", e),
-                            });
-                    } else {
-                        std::thread::sleep(std::time::Duration::from_millis(100));
+                            }
+                            Err(e) => log::error!("Hashing failed: {}", e),
+                        }
+                    };
+                    let started = Instant::now();
+                    offsets.for_each(hash_nonce);
+                    let idle = idle_percent.load(Ordering::Relaxed);
+                    if idle > 0 {
+                        std::thread::sleep(started.elapsed() * idle / (100 - idle));
                     }
+                } else {
+                    std::thread::sleep(std::time::Duration::from_millis(100));
                 }
-            });
+            }
         });
     }
 
@@ -122,3 +630,209 @@ impl Scheduler {
         self.active.store(false, Ordering::SeqCst);
     }
 }
+
+/// Returns the shared instance of `algorithm` for `key`, creating it on first use
+///
+/// Only the latest key of each algorithm is kept, so a new RandomX seed
+/// replaces the previous dataset instead of adding one. Workers asking while
+/// the instance is created wait for it.
+fn algorithm_for(
+    algorithms: &AlgorithmCache,
+    algorithm: AlgorithmType,
+    key: [u8; KEY_SIZE],
+    randomx_flags: RandomXFlags,
+) -> Arc<dyn Algorithm> {
+    let mut algorithms = algorithms.lock().expect("algorithm cache poisoned");
+    match algorithms.get(&algorithm) {
+        Some((cached_key, instance)) if *cached_key == key => return instance.clone(),
+        Some(_) => log::info!("Re-keying {} for seed {}", algorithm, hex::encode(key)),
+        None => log::info!("Creating {} for new job", algorithm),
+    }
+    let instance = algorithm::create(algorithm, &key, randomx_flags);
+    algorithms.insert(algorithm, (key, instance.clone()));
+    instance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Difficulty 1 job with a 76-byte blob of `byte`
+    fn job(job_id: &str, algorithm: AlgorithmType, byte: u8) -> MiningJob {
+        MiningJob {
+            job_id: job_id.to_string(),
+            blob: vec![byte; 76],
+            target: vec![0xff; 4],
+            algorithm,
+            seed_hash: None,
+            nonce_space: NonceSpace::default(),
+            template: None,
+        }
+    }
+
+    /// Waits for the next share of `job_id`, skipping shares of earlier jobs
+    fn next_share(shares: &crossbeam_channel::Receiver<Share>, job_id: &str) -> Share {
+        loop {
+            let share = shares
+                .recv_timeout(Duration::from_secs(60))
+                .expect("no share found");
+            if share.job_id == job_id {
+                return share;
+            }
+        }
+    }
+
+    #[test]
+    fn jobs_switch_the_mining_algorithm() {
+        let (share_sender, shares) = crossbeam_channel::unbounded();
+        let scheduler = Scheduler::new(share_sender, 1, None, false);
+        scheduler.start_mining(AlgorithmType::CryptoNightV7, 1);
+
+        let first = job("a", AlgorithmType::CryptoNightV7, 0x07);
+        scheduler.update_job(first.clone());
+        let share = next_share(&shares, "a");
+        let v7 = algorithm::create(
+            AlgorithmType::CryptoNightV7,
+            &[0; KEY_SIZE],
+            RandomXFlags::detect(),
+        );
+        assert_eq!(share.result, v7.hash(&first.blob, share.nonce).unwrap());
+
+        // A job for another algorithm is mined with that algorithm
+        let next = job("b", AlgorithmType::CryptoNightR, 0x07);
+        scheduler.update_job(next.clone());
+        let share = next_share(&shares, "b");
+        let r = algorithm::create(
+            AlgorithmType::CryptoNightR,
+            &[0; KEY_SIZE],
+            RandomXFlags::detect(),
+        );
+        assert_eq!(share.result, r.hash(&next.blob, share.nonce).unwrap());
+
+        // An instance was created for each algorithm and both are kept
+        scheduler.stop();
+        let algorithms = scheduler.algorithms.lock().unwrap();
+        assert_eq!(algorithms.len(), 2);
+        assert!(algorithms.contains_key(&AlgorithmType::CryptoNightV7));
+        assert!(algorithms.contains_key(&AlgorithmType::CryptoNightR));
+    }
+
+    #[test]
+    fn largest_thread_reduction_applies() {
+        let mut target = ThreadTarget {
+            base: 8,
+            ..Default::default()
+        };
+        assert_eq!(target.effective(), 8);
+
+        target.reductions.insert("thermal", 2);
+        target.reductions.insert("foreign_load", 3);
+        assert_eq!(target.effective(), 5);
+
+        // Withdrawing one request leaves the other in place
+        target.reductions.insert("foreign_load", 0);
+        assert_eq!(target.effective(), 6);
+
+        // A new base keeps the reduction
+        target.base = 4;
+        assert_eq!(target.effective(), 2);
+        target.base = 1;
+        assert_eq!(target.effective(), 0);
+    }
+
+    #[test]
+    fn full_nonce_space_is_unchanged() {
+        let space = NonceSpace::default();
+        assert_eq!(space.size(), 1 << 32);
+        assert_eq!(space.nonce(0x1234_5678), 0x1234_5678);
+        // Offsets wrap inside the 32-bit nonce
+        assert_eq!(space.nonce(0x1_0000_0005), 5);
+    }
+
+    #[test]
+    fn nicehash_fixes_top_nonce_byte() {
+        let mut blob = vec![0u8; NONCE_OFFSET + 4];
+        blob[NONCE_OFFSET + 3] = 0xab;
+        let space = NonceSpace::nicehash(&blob).unwrap();
+        assert_eq!(
+            space,
+            NonceSpace {
+                prefix: 0xab,
+                prefix_bits: 8
+            }
+        );
+        assert_eq!(space.size(), 1 << 24);
+        assert_eq!(space.nonce(0x12), 0xab00_0012);
+        assert_eq!(space.nonce(0x0100_0001), 0xab00_0001);
+        assert!(NonceSpace::nicehash(&blob[..NONCE_OFFSET + 3]).is_none());
+    }
+
+    #[test]
+    fn instance_id_splits_below_prefix() {
+        let space = NonceSpace {
+            prefix: 0xab,
+            prefix_bits: 8,
+        }
+        .split(0x07, 8);
+        assert_eq!(
+            space,
+            NonceSpace {
+                prefix: 0xab07,
+                prefix_bits: 16
+            }
+        );
+        assert_eq!(space.size(), 1 << 16);
+        assert_eq!(space.nonce(0), 0xab07_0000);
+        assert_eq!(space.nonce(0xffff), 0xab07_ffff);
+
+        // Without a pool prefix the instance owns the top byte
+        let space = NonceSpace::default().split(0x42, 8);
+        assert_eq!(space.nonce(1), 0x4200_0001);
+    }
+
+    #[test]
+    fn split_truncates_value_to_bits() {
+        let space = NonceSpace::default().split(0x1ff, 4);
+        assert_eq!(
+            space,
+            NonceSpace {
+                prefix: 0xf,
+                prefix_bits: 4
+            }
+        );
+    }
+
+    #[test]
+    fn split_is_capped_at_32_bits() {
+        let space = NonceSpace {
+            prefix: 0xab,
+            prefix_bits: 8,
+        }
+        .split(0xcd, 8)
+        .split(0xef01, 16)
+        .split(0xff, 8);
+        assert_eq!(
+            space,
+            NonceSpace {
+                prefix: 0xabcd_ef01,
+                prefix_bits: 32
+            }
+        );
+        assert_eq!(space.size(), 1);
+        assert_eq!(space.nonce(12345), 0xabcd_ef01);
+
+        let space = NonceSpace {
+            prefix: 0x1,
+            prefix_bits: 28,
+        }
+        .split(0xff, 8);
+        assert_eq!(
+            space,
+            NonceSpace {
+                prefix: 0x1f,
+                prefix_bits: 32
+            }
+        );
+    }
+}
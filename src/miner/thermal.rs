@@ -0,0 +1,264 @@
+// src/miner/thermal.rs
+//! Thermal throttling
+//!
+//! Watches the CPU temperature and backs mining off when it climbs above a
+//! high watermark, either by retiring threads or by making workers sleep for
+//! part of each batch. Once the temperature falls below the low watermark the
+//! throttling is undone step by step. The gap between the watermarks keeps
+//! the controller from oscillating around a single limit.
+
+use crate::miner::Scheduler;
+use crate::miner::scheduler::MAX_IDLE_PERCENT;
+use crate::stats::cpu_temperature;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use sysinfo::Components;
+
+/// Idle share added or removed per duty-cycle step, in percent
+const DUTY_CYCLE_STEP: u32 = 10;
+
+/// Name under which idle time and thread reductions are requested
+const SOURCE: &str = "thermal";
+
+/// How the controller reduces heat
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrottleMode {
+    /// Retire one mining thread per step
+    #[default]
+    Threads,
+    /// Add idle time to every worker's duty cycle per step
+    DutyCycle,
+}
+
+/// Configuration for thermal throttling
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThermalConfig {
+    /// Temperature (°C) above which mining is throttled
+    pub high_temperature: f32,
+    /// Temperature (°C) below which throttling is undone
+    pub low_temperature: f32,
+    /// How mining is throttled (default: threads)
+    #[serde(default)]
+    pub mode: ThrottleMode,
+    /// Seconds between temperature checks (default: 5)
+    #[serde(default = "default_check_interval")]
+    pub check_interval: u64,
+    /// Threads kept running however hot the CPU gets (default: 1)
+    #[serde(default = "default_min_threads")]
+    pub min_threads: usize,
+}
+
+fn default_check_interval() -> u64 {
+    5
+}
+
+fn default_min_threads() -> usize {
+    1
+}
+
+/// Adjusts mining intensity from CPU temperature readings
+pub struct ThermalController {
+    /// Watermarks and throttling mode
+    config: ThermalConfig,
+    /// Scheduler whose workers are throttled
+    scheduler: Arc<Scheduler>,
+    /// Threads retired by this controller and not yet restored
+    retired: usize,
+    /// Idle share imposed by this controller, in percent
+    idle: u32,
+}
+
+impl ThermalController {
+    /// Creates a controller for `scheduler`
+    ///
+    /// # Arguments
+    /// * `config` - Watermarks and throttling mode
+    /// * `scheduler` - Scheduler whose workers are throttled
+    pub fn new(config: ThermalConfig, scheduler: Arc<Scheduler>) -> Self {
+        ThermalController {
+            config,
+            scheduler,
+            retired: 0,
+            idle: 0,
+        }
+    }
+
+    /// Starts checking the temperature on a background thread
+    pub fn start(mut self) {
+        std::thread::spawn(move || {
+            let mut components = Components::new_with_refreshed_list();
+            let interval = Duration::from_secs(self.config.check_interval.max(1));
+            let mut warned = false;
+
+            loop {
+                std::thread::sleep(interval);
+                components.refresh(true);
+                match cpu_temperature(&components) {
+                    Some(temperature) => self.update(temperature),
+                    None if !warned => {
+                        log::warn!("No CPU temperature sensor found, thermal throttling inactive");
+                        warned = true;
+                    }
+                    None => {}
+                }
+            }
+        });
+    }
+
+    /// Applies one control step for a temperature reading
+    ///
+    /// Above the high watermark mining is reduced by one step; below the low
+    /// watermark one step of earlier throttling is undone. In between nothing
+    /// changes.
+    ///
+    /// # Arguments
+    /// * `temperature` - Current CPU temperature in Celsius
+    pub fn update(&mut self, temperature: f32) {
+        if temperature > self.config.high_temperature {
+            self.throttle(temperature);
+        } else if temperature < self.config.low_temperature {
+            self.restore(temperature);
+        }
+    }
+
+    /// Reduces mining intensity by one step
+    fn throttle(&mut self, temperature: f32) {
+        match self.config.mode {
+            ThrottleMode::Threads => {
+                let threads = self.scheduler.base_threads().saturating_sub(self.retired);
+                if threads <= self.config.min_threads {
+                    return;
+                }
+                log::warn!(
+                    "CPU at {:.1}°C (limit {:.1}°C), reducing mining threads to {}",
+                    temperature,
+                    self.config.high_temperature,
+                    threads - 1
+                );
+                self.retired += 1;
+                self.scheduler.request_fewer_threads(SOURCE, self.retired);
+            }
+            ThrottleMode::DutyCycle => {
+                let throttled = (self.idle + DUTY_CYCLE_STEP).min(MAX_IDLE_PERCENT);
+                if throttled == self.idle {
+                    return;
+                }
+                log::warn!(
+                    "CPU at {:.1}°C (limit {:.1}°C), idling mining threads {}% of the time",
+                    temperature,
+                    self.config.high_temperature,
+                    throttled
+                );
+                self.idle = throttled;
+                self.scheduler.request_idle(SOURCE, throttled);
+            }
+        }
+    }
+
+    /// Undoes one step of throttling
+    fn restore(&mut self, temperature: f32) {
+        match self.config.mode {
+            ThrottleMode::Threads => {
+                if self.retired == 0 {
+                    return;
+                }
+                self.retired -= 1;
+                log::info!(
+                    "CPU cooled to {:.1}°C, restoring mining threads to {}",
+                    temperature,
+                    self.scheduler.base_threads().saturating_sub(self.retired)
+                );
+                self.scheduler.request_fewer_threads(SOURCE, self.retired);
+            }
+            ThrottleMode::DutyCycle => {
+                if self.idle == 0 {
+                    return;
+                }
+                let restored = self.idle.saturating_sub(DUTY_CYCLE_STEP);
+                log::info!(
+                    "CPU cooled to {:.1}°C, idling mining threads {}% of the time",
+                    temperature,
+                    restored
+                );
+                self.idle = restored;
+                self.scheduler.request_idle(SOURCE, restored);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controller(mode: ThrottleMode, threads: usize) -> ThermalController {
+        let (shares, _) = crossbeam_channel::unbounded();
+        let scheduler = Arc::new(Scheduler::new(shares, 1000, None, false));
+        scheduler.set_threads(threads);
+        let config = ThermalConfig {
+            high_temperature: 85.0,
+            low_temperature: 75.0,
+            mode,
+            check_interval: 5,
+            min_threads: 2,
+        };
+        ThermalController::new(config, scheduler)
+    }
+
+    #[test]
+    fn threads_step_down_to_minimum() {
+        let mut thermal = controller(ThrottleMode::Threads, 4);
+        thermal.update(90.0);
+        assert_eq!(thermal.retired, 1);
+        thermal.update(90.0);
+        assert_eq!(thermal.retired, 2);
+        // 2 threads left, the configured minimum
+        thermal.update(90.0);
+        assert_eq!(thermal.retired, 2);
+        assert_eq!(thermal.scheduler.base_threads(), 4);
+    }
+
+    #[test]
+    fn threads_restore_one_step_at_a_time() {
+        let mut thermal = controller(ThrottleMode::Threads, 4);
+        thermal.update(90.0);
+        thermal.update(90.0);
+
+        // Between the watermarks nothing changes
+        thermal.update(80.0);
+        assert_eq!(thermal.retired, 2);
+
+        thermal.update(70.0);
+        assert_eq!(thermal.retired, 1);
+        thermal.update(70.0);
+        assert_eq!(thermal.retired, 0);
+        thermal.update(70.0);
+        assert_eq!(thermal.retired, 0);
+    }
+
+    #[test]
+    fn duty_cycle_steps_up_to_cap_and_back() {
+        let mut thermal = controller(ThrottleMode::DutyCycle, 4);
+        thermal.update(90.0);
+        assert_eq!(thermal.scheduler.idle_percent(), DUTY_CYCLE_STEP);
+        for _ in 0..20 {
+            thermal.update(90.0);
+        }
+        assert_eq!(thermal.scheduler.idle_percent(), MAX_IDLE_PERCENT);
+        assert_eq!(thermal.retired, 0);
+
+        thermal.update(80.0);
+        assert_eq!(thermal.scheduler.idle_percent(), MAX_IDLE_PERCENT);
+        thermal.update(70.0);
+        assert_eq!(
+            thermal.scheduler.idle_percent(),
+            MAX_IDLE_PERCENT - DUTY_CYCLE_STEP
+        );
+        for _ in 0..20 {
+            thermal.update(70.0);
+        }
+        assert_eq!(thermal.scheduler.idle_percent(), 0);
+    }
+}
@@ -0,0 +1,337 @@
+// src/miner/topology.rs
+//! Cache-aware automatic thread count
+//!
+//! Memory-hard algorithms run at full speed only while each thread's
+//! scratchpad stays in the last-level cache. Running more threads than the
+//! cache can hold evicts scratchpads and lowers the total hashrate, so the
+//! automatic thread count is derived from the size of every shared cache
+//! instance rather than from the number of logical CPUs.
+
+use crate::types::AlgorithmType;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Sysfs directory describing the logical CPUs (Linux)
+const SYSFS_CPU_DIR: &str = "/sys/devices/system/cpu";
+
+/// One cache instance shared by a group of logical CPUs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedCache {
+    /// Cache level (3 for L3)
+    pub level: u32,
+    /// Size in bytes
+    pub size: u64,
+    /// Number of logical CPUs sharing this cache
+    pub cpus: usize,
+}
+
+/// Recommended thread count and how it was derived
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadPlan {
+    /// Number of mining threads to start
+    pub threads: usize,
+    /// Human-readable explanation, logged at startup
+    pub reason: String,
+}
+
+/// Reads the last-level cache instances from sysfs
+///
+/// Only unified or data caches are considered; every instance is reported
+/// once, however many CPUs share it.
+///
+/// # Returns
+/// The caches of the highest level present, or an empty list if the cache
+/// topology is unavailable (non-Linux hosts, restricted containers)
+pub fn last_level_caches() -> Vec<SharedCache> {
+    last_level_caches_in(Path::new(SYSFS_CPU_DIR))
+}
+
+/// Reads the last-level cache instances from the CPU directory `sysfs`
+///
+/// # Arguments
+/// * `sysfs` - Directory laid out like `/sys/devices/system/cpu`
+pub fn last_level_caches_in(sysfs: &Path) -> Vec<SharedCache> {
+    let Ok(entries) = std::fs::read_dir(sysfs) else {
+        return Vec::new();
+    };
+
+    // Keyed by level and sharing CPU list, so each instance appears once
+    let mut caches: BTreeMap<(u32, String), SharedCache> = BTreeMap::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(index) = name.to_str().and_then(|n| n.strip_prefix("cpu")) else {
+            continue;
+        };
+        if index.parse::<usize>().is_err() {
+            continue;
+        }
+
+        let Ok(indexes) = std::fs::read_dir(entry.path().join("cache")) else {
+            continue;
+        };
+        for cache in indexes.flatten() {
+            let dir = cache.path();
+            let read = |file: &str| std::fs::read_to_string(dir.join(file)).ok();
+            if read("type").is_some_and(|t| t.trim() == "Instruction") {
+                continue;
+            }
+            let (Some(level), Some(size), Some(shared)) = (
+                read("level").and_then(|l| l.trim().parse().ok()),
+                read("size").and_then(|s| parse_size(&s)),
+                read("shared_cpu_list"),
+            ) else {
+                continue;
+            };
+            let shared = shared.trim().to_string();
+            caches
+                .entry((level, shared.clone()))
+                .or_insert_with(|| SharedCache {
+                    level,
+                    size,
+                    cpus: parse_cpu_list(&shared).len(),
+                });
+        }
+    }
+
+    let top = caches.keys().map(|(level, _)| *level).max().unwrap_or(0);
+    caches
+        .into_values()
+        .filter(|cache| cache.level == top)
+        .collect()
+}
+
+/// Computes the thread count for `algorithm` on this host
+///
+/// Each cache instance runs as many threads as it has room for whole
+/// scratchpads, but never more than the CPUs sharing it (at least one).
+/// Without cache information every logical CPU gets a thread.
+///
+/// # Arguments
+/// * `algorithm` - Algorithm whose scratchpad size is used
+pub fn plan_threads(algorithm: AlgorithmType) -> ThreadPlan {
+    plan_for_caches(algorithm, &last_level_caches(), num_cpus::get())
+}
+
+/// Computes the thread count for `algorithm` from a known cache layout
+///
+/// # Arguments
+/// * `algorithm` - Algorithm whose scratchpad size is used
+/// * `caches` - Last-level cache instances
+/// * `logical_cpus` - Fallback when `caches` is empty
+pub fn plan_for_caches(
+    algorithm: AlgorithmType,
+    caches: &[SharedCache],
+    logical_cpus: usize,
+) -> ThreadPlan {
+    let scratchpad = algorithm.scratchpad_size();
+    if caches.is_empty() {
+        return ThreadPlan {
+            threads: logical_cpus.max(1),
+            reason: format!(
+                "cache topology unavailable, using all {} logical CPUs",
+                logical_cpus
+            ),
+        };
+    }
+
+    let threads: usize = caches
+        .iter()
+        .map(|cache| ((cache.size / scratchpad) as usize).clamp(1, cache.cpus.max(1)))
+        .sum();
+    let sizes: Vec<String> = caches.iter().map(|c| format_size(c.size)).collect();
+    let cpus: usize = caches.iter().map(|c| c.cpus).sum();
+
+    ThreadPlan {
+        threads,
+        reason: format!(
+            "{} L{} cache(s) ({}) / {} {} scratchpad = {} threads on {} logical CPUs",
+            caches.len(),
+            caches[0].level,
+            sizes.join(", "),
+            format_size(scratchpad),
+            algorithm,
+            threads,
+            cpus
+        ),
+    }
+}
+
+/// Parses a sysfs cache size such as "32768K" or "32M"
+fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let (digits, unit) = match size.find(|c: char| !c.is_ascii_digit()) {
+        Some(split) => size.split_at(split),
+        None => (size, ""),
+    };
+    let value: u64 = digits.parse().ok()?;
+    match unit {
+        "" => Some(value),
+        "K" => Some(value * 1024),
+        "M" => Some(value * 1024 * 1024),
+        "G" => Some(value * 1024 * 1024 * 1024),
+        _ => None,
+    }
+}
+
+/// Parses a sysfs CPU list such as "0-7,32-39"
+///
+/// # Returns
+/// The listed CPUs in order; malformed ranges are skipped
+pub(crate) fn parse_cpu_list(list: &str) -> Vec<usize> {
+    list.trim()
+        .split(',')
+        .filter(|part| !part.is_empty())
+        .flat_map(|part| match part.split_once('-') {
+            Some((first, last)) => match (first.parse::<usize>(), last.parse::<usize>()) {
+                (Ok(first), Ok(last)) => first..last + 1,
+                _ => 0..0,
+            },
+            None => match part.parse::<usize>() {
+                Ok(cpu) => cpu..cpu + 1,
+                Err(_) => 0..0,
+            },
+        })
+        .collect()
+}
+
+/// Formats a byte count as whole MB or KB
+fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{} MB", bytes / (1024 * 1024))
+    } else {
+        format!("{} KB", bytes / 1024)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// One cache of a fake sysfs tree: level, type, size and shared CPU list
+    type Cache<'a> = (u32, &'a str, &'a str, &'a str);
+
+    /// Builds a fake sysfs CPU directory with a `cpuN/cache/indexM` entry
+    /// for every cache each CPU lists
+    fn sysfs(name: &str, cpus: &[(usize, Vec<Cache>)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "xmr_miner-topology-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        for (cpu, caches) in cpus {
+            for (index, (level, kind, size, shared)) in caches.iter().enumerate() {
+                let dir = root.join(format!("cpu{}/cache/index{}", cpu, index));
+                std::fs::create_dir_all(&dir).unwrap();
+                std::fs::write(dir.join("level"), format!("{}\n", level)).unwrap();
+                std::fs::write(dir.join("type"), format!("{}\n", kind)).unwrap();
+                std::fs::write(dir.join("size"), format!("{}\n", size)).unwrap();
+                std::fs::write(dir.join("shared_cpu_list"), format!("{}\n", shared)).unwrap();
+            }
+        }
+        // Not a CPU directory
+        std::fs::create_dir_all(root.join("cpufreq")).unwrap();
+        root
+    }
+
+    fn l3(size: u64, cpus: usize) -> SharedCache {
+        SharedCache {
+            level: 3,
+            size,
+            cpus,
+        }
+    }
+
+    #[test]
+    fn parses_cpu_ranges_and_singles() {
+        let cpus = parse_cpu_list("0-7,32-39\n");
+        assert_eq!(cpus, (0..8).chain(32..40).collect::<Vec<_>>());
+        assert_eq!(parse_cpu_list("0,2,4"), vec![0, 2, 4]);
+        assert_eq!(parse_cpu_list("5"), vec![5]);
+    }
+
+    #[test]
+    fn skips_malformed_cpu_ranges() {
+        assert_eq!(parse_cpu_list("0-x,3,,y"), vec![3]);
+        assert!(parse_cpu_list("").is_empty());
+    }
+
+    #[test]
+    fn parses_cache_sizes() {
+        assert_eq!(parse_size("32768K\n"), Some(32 * 1024 * 1024));
+        assert_eq!(parse_size("32M"), Some(32 * 1024 * 1024));
+        assert_eq!(parse_size("1G"), Some(1024 * 1024 * 1024));
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("32KB"), None);
+        assert_eq!(parse_size("K"), None);
+    }
+
+    #[test]
+    fn reads_each_last_level_cache_instance_once() {
+        // Two CCDs of two cores (two threads each), each CCD with its own L3
+        let cpus: Vec<(usize, Vec<Cache>)> = (0..8)
+            .map(|cpu| {
+                let core = cpu % 4;
+                let siblings = ["0,4", "1,5", "2,6", "3,7"][core];
+                let ccd = if core < 2 { "0-1,4-5" } else { "2-3,6-7" };
+                let l3_size = if core < 2 { "32768K" } else { "16384K" };
+                (
+                    cpu,
+                    vec![
+                        (1, "Data", "32K", siblings),
+                        (1, "Instruction", "32K", siblings),
+                        (2, "Unified", "512K", siblings),
+                        (3, "Unified", l3_size, ccd),
+                    ],
+                )
+            })
+            .collect();
+        let root = sysfs("ccds", &cpus);
+        let caches = last_level_caches_in(&root);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            caches,
+            vec![l3(32 * 1024 * 1024, 4), l3(16 * 1024 * 1024, 4)]
+        );
+    }
+
+    #[test]
+    fn missing_sysfs_has_no_caches() {
+        let root =
+            std::env::temp_dir().join(format!("xmr_miner-topology-missing-{}", std::process::id()));
+        assert!(last_level_caches_in(&root).is_empty());
+    }
+
+    #[test]
+    fn falls_back_to_logical_cpus() {
+        let plan = plan_for_caches(AlgorithmType::RandomX, &[], 12);
+        assert_eq!(plan.threads, 12);
+        assert_eq!(plan_for_caches(AlgorithmType::RandomX, &[], 0).threads, 1);
+    }
+
+    #[test]
+    fn sums_threads_across_multiple_l3_instances() {
+        // Two CCDs with 32 MB L3 each, 16 logical CPUs per CCD
+        let caches = [l3(32 * 1024 * 1024, 16), l3(32 * 1024 * 1024, 16)];
+        let plan = plan_for_caches(AlgorithmType::RandomX, &caches, 32);
+        assert_eq!(plan.threads, 32);
+        assert!(plan.reason.starts_with("2 L3 cache(s) (32 MB, 32 MB)"));
+    }
+
+    #[test]
+    fn limits_threads_by_cache_size_and_cpus() {
+        // 6 MB fits three scratchpads; 16 MB would fit eight but only has four CPUs
+        let caches = [l3(6 * 1024 * 1024, 8), l3(16 * 1024 * 1024, 4)];
+        let plan = plan_for_caches(AlgorithmType::RandomX, &caches, 12);
+        assert_eq!(plan.threads, 7);
+    }
+
+    #[test]
+    fn small_cache_still_runs_one_thread() {
+        let caches = [l3(1024 * 1024, 4)];
+        let plan = plan_for_caches(AlgorithmType::CryptoNightR, &caches, 4);
+        assert_eq!(plan.threads, 1);
+    }
+}
@@ -0,0 +1,340 @@
+// src/miner/verifier.rs
+//! Local share re-verification
+//!
+//! Overclocked or undervolted machines can silently produce wrong hashes.
+//! Before a share leaves the scheduler, its hash is recomputed with a separate
+//! algorithm instance (a light-mode VM for RandomX) and checked against the
+//! job target. Mismatches are counted as hardware errors and never submitted.
+//!
+//! RandomX instances are keyed by the seed hash of the job being checked, so
+//! shares are verified the way the pool or node will verify them.
+
+use crate::miner::algorithm::{self, Algorithm, KEY_SIZE};
+use crate::miner::features::RandomXFlags;
+use crate::miner::scheduler::{MiningJob, Share};
+use crate::stats::ShareResult;
+use crate::types::AlgorithmType;
+use crate::utils::difficulty::meets_target;
+use crate::utils::error::MinerError;
+use crossbeam_channel::Sender;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Keys kept per algorithm: the current RandomX seed and the previous one,
+/// for shares of jobs issued just before a seed change
+const KEYS_PER_ALGORITHM: usize = 2;
+
+/// Verifier instance together with the algorithm and key it is created with
+///
+/// The instance is built on first use, outside the cache lock.
+type CachedVerifier = (
+    AlgorithmType,
+    [u8; KEY_SIZE],
+    Arc<OnceLock<Arc<dyn Algorithm>>>,
+);
+
+/// Verifier instances shared by everything that recomputes share hashes
+///
+/// One instance (a light-mode VM for RandomX) is created per algorithm and
+/// key on first use. Clones share the same instances, so all users of one
+/// cache (e.g. every connection of a Stratum server) never build a second
+/// VM for the same seed.
+#[derive(Clone)]
+pub struct VerifierCache {
+    /// Instances in creation order, oldest first
+    instances: Arc<Mutex<VecDeque<CachedVerifier>>>,
+    /// Flags RandomX instances are created with
+    randomx_flags: RandomXFlags,
+}
+
+impl Default for VerifierCache {
+    fn default() -> Self {
+        Self::new(RandomXFlags::detect())
+    }
+}
+
+impl VerifierCache {
+    /// Creates an empty cache
+    ///
+    /// # Arguments
+    /// * `randomx_flags` - Flags RandomX instances are created with
+    pub fn new(randomx_flags: RandomXFlags) -> Self {
+        VerifierCache {
+            instances: Arc::default(),
+            randomx_flags,
+        }
+    }
+
+    /// Returns the verifier for a job's algorithm and seed hash
+    ///
+    /// Creating an instance blocks until it is ready. Only callers asking for
+    /// the same algorithm and key wait for it; the cache itself stays
+    /// available. The oldest key of the algorithm is dropped once
+    /// [`KEYS_PER_ALGORITHM`] are cached.
+    ///
+    /// # Arguments
+    /// * `algorithm` - Algorithm of the job
+    /// * `seed_hash` - Seed hash (hex) sent with the job
+    ///
+    /// # Errors
+    /// Returns `MinerError` if the seed hash is malformed
+    pub fn get(
+        &self,
+        algorithm: AlgorithmType,
+        seed_hash: Option<&str>,
+    ) -> Result<Arc<dyn Algorithm>, MinerError> {
+        let key = algorithm::job_key(algorithm, seed_hash)?;
+        let slot = self.slot(algorithm, key);
+        let verifier =
+            slot.get_or_init(|| algorithm::create_verifier(algorithm, &key, self.randomx_flags));
+        Ok(verifier.clone())
+    }
+
+    /// Returns the slot holding the instance for `algorithm` and `key`,
+    /// adding an empty one (and evicting the oldest key) if there is none
+    fn slot(
+        &self,
+        algorithm: AlgorithmType,
+        key: [u8; KEY_SIZE],
+    ) -> Arc<OnceLock<Arc<dyn Algorithm>>> {
+        let mut instances = self.instances.lock().expect("verifier cache poisoned");
+        if let Some((_, _, slot)) = instances
+            .iter()
+            .find(|(cached, cached_key, _)| *cached == algorithm && *cached_key == key)
+        {
+            return slot.clone();
+        }
+
+        if instances
+            .iter()
+            .filter(|(cached, _, _)| *cached == algorithm)
+            .count()
+            >= KEYS_PER_ALGORITHM
+            && let Some(oldest) = instances
+                .iter()
+                .position(|(cached, _, _)| *cached == algorithm)
+        {
+            instances.remove(oldest);
+        }
+        let slot = Arc::new(OnceLock::new());
+        instances.push_back((algorithm, key, slot.clone()));
+        slot
+    }
+}
+
+/// Recomputes share hashes with an independent algorithm instance
+#[derive(Default)]
+pub struct ShareVerifier {
+    /// Verifier instances, keyed by algorithm and seed
+    verifiers: VerifierCache,
+    /// Number of shares whose hash could not be reproduced
+    hardware_errors: AtomicU64,
+    /// Receives a [`ShareResult::HardwareError`] for every failed share
+    stats: Option<Sender<ShareResult>>,
+}
+
+impl ShareVerifier {
+    /// Creates a verifier; algorithm instances are created lazily
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses instances from a shared cache instead of a private one
+    ///
+    /// # Arguments
+    /// * `verifiers` - Cache shared with other verifiers
+    pub fn with_cache(mut self, verifiers: VerifierCache) -> Self {
+        self.verifiers = verifiers;
+        self
+    }
+
+    /// Cache the verifier instances come from
+    pub fn cache(&self) -> &VerifierCache {
+        &self.verifiers
+    }
+
+    /// Reports hardware errors to a statistics reporter
+    ///
+    /// # Arguments
+    /// * `stats` - Sender obtained from [`StatsReporter::share_sender`]
+    ///
+    /// [`StatsReporter::share_sender`]: crate::stats::StatsReporter::share_sender
+    pub fn with_stats(mut self, stats: Sender<ShareResult>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Checks a share before submission
+    ///
+    /// The hash is recomputed with an instance keyed by the job's seed hash.
+    ///
+    /// # Arguments
+    /// * `job` - Job the share was found for
+    /// * `blob` - Hashing blob the share was computed from
+    /// * `share` - The share to verify
+    ///
+    /// # Returns
+    /// `true` if the recomputed hash matches and meets the job target.
+    /// Otherwise the share is counted as a hardware error and `false` is returned.
+    pub fn verify(&self, job: &MiningJob, blob: &[u8], share: &Share) -> bool {
+        let hash = self
+            .verifiers
+            .get(job.algorithm, job.seed_hash.as_deref())
+            .and_then(|verifier| verifier.hash(blob, share.nonce));
+
+        let valid = match hash {
+            Ok(hash) => hash == share.result && meets_target(&hash, &job.target),
+            Err(e) => {
+                log::error!("Share verification failed: {}", e);
+                false
+            }
+        };
+
+        if !valid {
+            let errors = self.hardware_errors.fetch_add(1, Ordering::Relaxed) + 1;
+            log::warn!(
+                "Hardware error: share for job {} nonce {} did not verify ({} total)",
+                share.job_id,
+                share.nonce,
+                errors
+            );
+            if let Some(stats) = &self.stats {
+                let _ = stats.send(ShareResult::HardwareError);
+            }
+        }
+        valid
+    }
+
+    /// Number of shares rejected locally because their hash did not verify
+    pub fn hardware_errors(&self) -> u64 {
+        self.hardware_errors.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn seed(byte: u8) -> String {
+        hex::encode([byte; KEY_SIZE])
+    }
+
+    /// CryptoNight v7 job with a difficulty 1 target
+    fn job() -> MiningJob {
+        MiningJob {
+            job_id: "a".to_string(),
+            blob: vec![0x07; 76],
+            target: vec![0xff; 4],
+            algorithm: AlgorithmType::CryptoNightV7,
+            seed_hash: None,
+            nonce_space: Default::default(),
+            template: None,
+        }
+    }
+
+    fn share(nonce: u64, result: [u8; 32]) -> Share {
+        Share {
+            job_id: "a".to_string(),
+            nonce,
+            result,
+            extra_nonce: 0,
+            found: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn cache_keeps_two_keys_per_algorithm() {
+        let cache = VerifierCache::default();
+        let first = cache.get(AlgorithmType::RandomX, Some(&seed(1))).unwrap();
+        let second = cache.get(AlgorithmType::RandomX, Some(&seed(2))).unwrap();
+        let cryptonight = cache.get(AlgorithmType::CryptoNightV7, None).unwrap();
+
+        // Both seeds are cached
+        let again = cache.get(AlgorithmType::RandomX, Some(&seed(1))).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        assert!(!Arc::ptr_eq(&first, &second));
+
+        // A third seed evicts the oldest one only
+        cache.get(AlgorithmType::RandomX, Some(&seed(3))).unwrap();
+        assert_eq!(cache.instances.lock().unwrap().len(), 3);
+        let again = cache.get(AlgorithmType::RandomX, Some(&seed(2))).unwrap();
+        assert!(Arc::ptr_eq(&second, &again));
+        let again = cache.get(AlgorithmType::CryptoNightV7, None).unwrap();
+        assert!(Arc::ptr_eq(&cryptonight, &again));
+        let recreated = cache.get(AlgorithmType::RandomX, Some(&seed(1))).unwrap();
+        assert!(!Arc::ptr_eq(&first, &recreated));
+
+        // Clones share the instances
+        let again = cache
+            .clone()
+            .get(AlgorithmType::RandomX, Some(&seed(1)))
+            .unwrap();
+        assert!(Arc::ptr_eq(&recreated, &again));
+
+        assert!(cache.get(AlgorithmType::RandomX, Some("zz")).is_err());
+    }
+
+    #[test]
+    fn other_keys_are_served_while_an_instance_is_built() {
+        let cache = VerifierCache::default();
+        let building = {
+            let cache = cache.clone();
+            std::thread::spawn(move || cache.get(AlgorithmType::RandomX, Some(&seed(4))).unwrap())
+        };
+        while cache.instances.lock().unwrap().is_empty() {
+            std::thread::yield_now();
+        }
+
+        // Building the RandomX cache takes far longer than a CryptoNight instance
+        cache.get(AlgorithmType::CryptoNightV7, None).unwrap();
+        assert!(!building.is_finished());
+
+        // Callers of the same key wait for the instance being built
+        let waiting = cache.get(AlgorithmType::RandomX, Some(&seed(4))).unwrap();
+        assert!(Arc::ptr_eq(&waiting, &building.join().unwrap()));
+    }
+
+    #[test]
+    fn mismatched_result_is_a_hardware_error() {
+        let (stats, results) = crossbeam_channel::unbounded();
+        let verifier = ShareVerifier::new().with_stats(stats);
+        let job = job();
+        let hash = verifier
+            .cache()
+            .get(job.algorithm, None)
+            .unwrap()
+            .hash(&job.blob, 5)
+            .unwrap();
+
+        assert!(verifier.verify(&job, &job.blob, &share(5, hash)));
+        assert_eq!(verifier.hardware_errors(), 0);
+        assert!(results.try_recv().is_err());
+
+        // A bit flip in the result, and a result for another nonce
+        let mut flipped = hash;
+        flipped[0] ^= 1;
+        assert!(!verifier.verify(&job, &job.blob, &share(5, flipped)));
+        assert!(!verifier.verify(&job, &job.blob, &share(6, hash)));
+        assert_eq!(verifier.hardware_errors(), 2);
+        assert!(matches!(results.try_recv(), Ok(ShareResult::HardwareError)));
+        assert!(matches!(results.try_recv(), Ok(ShareResult::HardwareError)));
+    }
+
+    #[test]
+    fn correct_hash_above_target_is_rejected() {
+        let verifier = ShareVerifier::new();
+        let mut job = job();
+        job.target = vec![0x01, 0, 0, 0];
+        let hash = verifier
+            .cache()
+            .get(job.algorithm, None)
+            .unwrap()
+            .hash(&job.blob, 5)
+            .unwrap();
+
+        assert!(!verifier.verify(&job, &job.blob, &share(5, hash)));
+        assert_eq!(verifier.hardware_errors(), 1);
+    }
+}
@@ -6,9 +6,11 @@
 
 use crate::miner::algorithm::Algorithm;
 use crate::miner::scheduler::{MiningJob, Share};
+use crate::utils::difficulty::meets_target;
 use crossbeam_channel::Sender;
 use rayon::prelude::*;
 use std::sync::Arc;
+use std::time::Instant;
 
 /// Worker thread that performs mining computations
 ///
@@ -61,15 +63,18 @@ impl<A: Algorithm + Send + Sync> Worker<A> {
     /// Automatically sends any valid shares found through the share channel.
     pub fn run(&self) {
         if let Some(job) = &self.job {
+            let extra_nonce = job.template.as_ref().map_or(0, |t| t.extra_nonce(0));
             (self.nonce_start..self.nonce_end)
                 .into_par_iter()
                 .for_each(|nonce| match self.algorithm.hash(&job.blob, nonce) {
                     Ok(hash) => {
-                        if hash.as_ref() < job.target.as_slice() {
+                        if meets_target(&hash, &job.target) {
                             let _ = self.share_sender.send(Share {
                                 job_id: job.job_id.clone(),
                                 nonce,
                                 result: hash,
+                                extra_nonce,
+                                found: Instant::now(),
                             });
                         }
                     }
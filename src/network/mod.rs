@@ -12,12 +12,69 @@
 /// Manages WebSocket connections, job distribution, and share submission.
 pub mod pool;
 
+/// Share submission queue
+///
+/// Drops stale and duplicate shares before they reach the pool and holds
+/// shares found during a reconnect.
+pub mod share_queue;
+
+/// Pool session recording and replay
+///
+/// Records every message exchanged with the pool and reloads recordings
+/// to replay their jobs and re-verify their shares offline.
+pub mod recording;
+
 /// Monero node client implementation
 ///
 /// Handles communication with a local Monero node for solo mining.
 /// Uses JSON-RPC to interact with the node's mining API.
 pub mod node;
 
+/// HTTP digest authentication
+///
+/// Answers the digest challenges monerod uses to protect its RPC interface.
+pub mod digest;
+
+/// Block template handling
+///
+/// Parses node block templates and rebuilds hashing blobs for each
+/// extra nonce written into the coinbase reserved area.
+pub mod template;
+
+/// Node ZMQ notifications
+///
+/// Subscribes to the node's ZMQ publisher so block templates can be
+/// refreshed the moment a new block or pool transaction arrives.
+pub mod zmq;
+
+/// Stratum server building blocks
+///
+/// Transport detection (TCP or WebSocket) and message builders shared by
+/// the servers downstream miners connect to.
+pub mod stratum;
+
+/// Node-backed solo mining Stratum server
+///
+/// Hands out node block templates with per-connection extra nonces and
+/// forwards found blocks to the node client.
+pub mod server;
+
+/// Stratum proxy
+///
+/// Multiplexes many downstream miners onto one upstream pool session by
+/// giving each miner its own slice of the nonce space.
+pub mod proxy;
+
+/// Pool simulator
+///
+/// Local Stratum server issuing synthetic jobs and validating shares, with
+/// optional disconnect and error injection for offline testing.
+pub mod simulator;
+
 // Re-export main components for cleaner imports
 pub use node::NodeClient;
 pub use pool::PoolClient;
+pub use proxy::StratumProxy;
+pub use server::SoloServer;
+pub use simulator::PoolSimulator;
+pub use template::BlockTemplate;
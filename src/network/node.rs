@@ -1,11 +1,33 @@
 // src/network/node.rs
 use crate::AlgorithmType;
-use crate::miner::scheduler::{MiningJob, Share};
+use crate::miner::scheduler::{MiningJob, NonceSpace, Share};
+use crate::network::digest::DigestChallenge;
+use crate::network::stratum::ServerConfig;
+use crate::network::template::BlockTemplate;
+use crate::network::zmq::{ChainEvent, ZmqSubscriber};
 use crate::utils::error::MinerError;
-use reqwest::Client;
+use reqwest::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use reqwest::{Client, StatusCode, Url};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use std::time::Duration;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Number of bytes reserved in the coinbase transaction for the extra nonce
+const RESERVE_SIZE: usize = 8;
+
+/// Timeout for node health checks
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Minimum time between template refreshes triggered by pool transactions
+const TXPOOL_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Time between node checks while mining
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Time between node checks while no node can supply a template
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
 
 /// Configuration for connecting to a node's RPC interface
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,7 +39,45 @@ pub struct NodeConfig {
     /// Password for RPC authentication (if required)
     pub rpc_password: String,
     /// Wallet address that will receive mining rewards
+    /// (unused in self-select pool mode, where the pool supplies it)
+    #[serde(default)]
     pub wallet_address: String,
+    /// Identifier of this rig, written into the upper half of the extra nonce
+    /// so rigs solo mining the same template never overlap (default: 0)
+    #[serde(default)]
+    pub rig_id: u32,
+    /// Additional node RPC endpoints used when `rpc_url` lags or fails
+    /// (share the same credentials)
+    #[serde(default)]
+    pub backup_rpc_urls: Vec<String>,
+    /// Maximum number of blocks a node may trail the highest known node
+    /// before it is considered stale (default: 2)
+    #[serde(default = "default_max_height_lag")]
+    pub max_height_lag: u64,
+    /// ZMQ publisher of the node (e.g., "tcp://127.0.0.1:18083") for instant
+    /// block notifications; polling only when unset
+    #[serde(default)]
+    pub zmq_url: Option<String>,
+    /// ZMQ publishers of the backup nodes, in `backup_rpc_urls` order
+    /// (backups without an entry are polled only)
+    #[serde(default)]
+    pub backup_zmq_urls: Vec<String>,
+    /// Stratum server letting other miners on the LAN solo mine through this node
+    #[serde(default)]
+    pub server: Option<ServerConfig>,
+}
+
+fn default_max_height_lag() -> u64 {
+    2
+}
+
+/// Result of a single node health check
+#[derive(Debug, Clone)]
+struct NodeHealth {
+    /// Reported chain height (0 if the node could not be reached)
+    height: u64,
+    /// Whether the node reports itself as synchronized
+    synchronized: bool,
 }
 
 /// Client for interacting with a node's RPC interface
@@ -26,8 +86,28 @@ pub struct NodeClient {
     config: NodeConfig,
     /// HTTP client for making RPC requests
     client: Client,
+    /// Digest challenges received from each endpoint, reused for later requests
+    digest: Mutex<HashMap<String, DigestChallenge>>,
     /// Current blockchain height known to this client
     current_height: u64,
+    /// Node RPC endpoints, primary first
+    endpoints: Vec<String>,
+    /// Index of the endpoint currently used for mining
+    active: usize,
+    /// Job currently being mined (carries its block template)
+    current_job: Option<MiningJob>,
+    /// Templates handed out for the current height, by job ID
+    ///
+    /// Refreshes at the same height (e.g. new pool transactions) and a
+    /// withdrawn job keep the earlier templates, so blocks found on them can
+    /// still be submitted.
+    templates: HashMap<String, Arc<BlockTemplate>>,
+    /// Sequence number used to build unique job IDs
+    job_seq: u64,
+    /// Channel for sending new jobs to miners (`None` withdraws the job)
+    job_sender: crossbeam_channel::Sender<Option<MiningJob>>,
+    /// Channel for receiving solved blocks from miners
+    share_receiver: crossbeam_channel::Receiver<Share>,
 }
 
 impl NodeClient {
@@ -35,16 +115,39 @@ impl NodeClient {
     ///
     /// # Arguments
     /// * `config` - Node configuration containing RPC connection details
-    pub fn new(config: NodeConfig) -> Self {
+    /// * `job_sender` - Channel for sending jobs to miner workers
+    /// * `share_receiver` - Channel for receiving solved blocks from miners
+    pub fn new(
+        config: NodeConfig,
+        job_sender: crossbeam_channel::Sender<Option<MiningJob>>,
+        share_receiver: crossbeam_channel::Receiver<Share>,
+    ) -> Self {
+        let endpoints = std::iter::once(config.rpc_url.clone())
+            .chain(config.backup_rpc_urls.iter().cloned())
+            .collect();
+
         NodeClient {
             config,
             client: Client::new(),
+            digest: Mutex::new(HashMap::new()),
             current_height: 0,
+            endpoints,
+            active: 0,
+            current_job: None,
+            templates: HashMap::new(),
+            job_seq: 0,
+            job_sender,
+            share_receiver,
         }
     }
 
     /// Requests a new block template from the node
     ///
+    /// The reserved area of the template is used as an extra nonce; the
+    /// returned job carries the template so the scheduler can rebuild the
+    /// hashing blob whenever the 32-bit nonce space is exhausted. Templates
+    /// of earlier heights are forgotten.
+    ///
     /// # Returns
     /// * `Ok(MiningJob)` - Contains the job details if successful
     /// * `Err(MinerError)` - If there was an error getting the block template
@@ -54,49 +157,167 @@ impl NodeClient {
                 "getblocktemplate",
                 json!({
                     "wallet_address": self.config.wallet_address,
-                    "reserve_size": 8
+                    "reserve_size": RESERVE_SIZE
                 }),
             )
             .await?;
 
+        if let Some(error) = response.get("error") {
+            return Err(MinerError::ProtocolError(format!(
+                "Template request rejected: {}",
+                error["message"].as_str().unwrap_or("unknown error")
+            )));
+        }
         let result = response["result"]
             .as_object()
             .ok_or_else(|| MinerError::ProtocolError("Missing result object".to_string()))?;
 
-        Ok(MiningJob {
-            job_id: result["job_id"]
-                .as_str()
-                .ok_or_else(|| MinerError::ProtocolError("Missing job_id".to_string()))?
-                .to_string(),
+        let template = BlockTemplate {
             blob: hex::decode(result["blocktemplate_blob"].as_str().ok_or_else(|| {
                 MinerError::ProtocolError("Missing blocktemplate_blob".to_string())
             })?)?,
-            target: hex::decode(
-                result["target"]
-                    .as_str()
-                    .ok_or_else(|| MinerError::ProtocolError("Missing target".to_string()))?,
-            )?,
+            reserved_offset: result["reserved_offset"]
+                .as_u64()
+                .ok_or_else(|| MinerError::ProtocolError("Missing reserved_offset".to_string()))?
+                as usize,
+            reserve_size: RESERVE_SIZE,
+            height: result["height"]
+                .as_u64()
+                .ok_or_else(|| MinerError::ProtocolError("Missing height".to_string()))?,
+            difficulty: result["difficulty"]
+                .as_u64()
+                .ok_or_else(|| MinerError::ProtocolError("Missing difficulty".to_string()))?,
+            seed_hash: result["seed_hash"].as_str().unwrap_or_default().to_string(),
+            rig_id: self.config.rig_id,
+        };
+
+        if self
+            .templates
+            .values()
+            .any(|known| known.height != template.height)
+        {
+            self.templates.clear();
+        }
+        let template = Arc::new(template);
+        self.job_seq += 1;
+        let job = MiningJob {
+            job_id: format!("{}-{}", template.height, self.job_seq),
+            blob: template.hashing_blob(template.extra_nonce(0))?,
+            target: template.target(),
             algorithm: AlgorithmType::RandomX,
-        })
+            seed_hash: Some(template.seed_hash.clone()),
+            nonce_space: NonceSpace::default(),
+            template: Some(template.clone()),
+        };
+
+        self.templates.insert(job.job_id.clone(), template);
+        self.current_job = Some(job.clone());
+        Ok(job)
     }
 
-    /// Submits a solved block to the node
+    /// Requests a block template paying to a pool wallet
+    ///
+    /// Used in self-select pool mode: the pool supplies the wallet and the
+    /// extra nonce identifying this miner, while transaction selection stays
+    /// with our own node.
     ///
     /// # Arguments
-    /// * `share` - The solved block to submit
+    /// * `wallet_address` - Pool wallet that receives the coinbase reward
+    /// * `extra_nonce` - Pool-assigned extra nonce (hex) for the coinbase extra field
     ///
     /// # Returns
-    /// * `Ok(())` - If the submission was successful
-    /// * `Err(MinerError)` - If there was an error submitting the block
-    pub async fn submit_block(&self, share: Share) -> Result<(), MinerError> {
-        let _ = self
+    /// * `Ok(Value)` - The `getblocktemplate` result object
+    /// * `Err(MinerError)` - If the node rejects the request or cannot be reached
+    pub async fn get_pool_template(
+        &self,
+        wallet_address: &str,
+        extra_nonce: &str,
+    ) -> Result<Value, MinerError> {
+        let response = self
             .rpc_call(
-                "submitblock",
+                "getblocktemplate",
                 json!({
-                    "block": hex::encode(share.result)
+                    "wallet_address": wallet_address,
+                    "extra_nonce": extra_nonce
                 }),
             )
             .await?;
+
+        if let Some(error) = response.get("error") {
+            return Err(MinerError::ProtocolError(format!(
+                "Template request rejected: {}",
+                error["message"].as_str().unwrap_or("unknown error")
+            )));
+        }
+        if !response["result"].is_object() {
+            return Err(MinerError::ProtocolError(
+                "Missing result object".to_string(),
+            ));
+        }
+        Ok(response["result"].clone())
+    }
+
+    /// Submits a solved block to the node
+    ///
+    /// Rebuilds the full block blob from the template of the share's job
+    /// using the share's extra nonce and nonce. Jobs of any template handed
+    /// out for the current height are accepted.
+    ///
+    /// If the active node fails to take the block, it is submitted to every
+    /// other node that is synchronized at the template's height, so a node
+    /// failure does not cost a solved block.
+    ///
+    /// # Arguments
+    /// * `share` - The solved block to submit
+    ///
+    /// # Returns
+    /// * `Ok(())` - If one of the nodes accepted the block
+    /// * `Err(MinerError)` - The active node's error if no node accepted the block
+    pub async fn submit_block(&self, share: Share) -> Result<(), MinerError> {
+        let template = self
+            .templates
+            .get(&share.job_id)
+            .ok_or_else(|| MinerError::ProtocolError(format!("Stale job {}", share.job_id)))?;
+        let block = hex::encode(template.block_blob(share.extra_nonce, share.nonce)?);
+
+        let active = &self.endpoints[self.active];
+        let error = match self.submit_block_to(active, &block).await {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        log::warn!("Node {} did not take the block: {}", active, error);
+
+        for (index, url) in self.endpoints.iter().enumerate() {
+            if index == self.active {
+                continue;
+            }
+            let health = self.check_node(url).await;
+            if !health.synchronized || health.height != template.height {
+                continue;
+            }
+            match self.submit_block_to(url, &block).await {
+                Ok(()) => {
+                    log::info!("Block submitted to {} instead", url);
+                    return Ok(());
+                }
+                Err(e) => log::warn!("Node {} did not take the block: {}", url, e),
+            }
+        }
+        Err(error)
+    }
+
+    /// Submits a hex-encoded block blob to a specific node endpoint
+    ///
+    /// # Errors
+    /// Returns `MinerError` if the node cannot be reached or rejects the block
+    async fn submit_block_to(&self, url: &str, block: &str) -> Result<(), MinerError> {
+        let response = self.rpc_call_to(url, "submitblock", json!([block])).await?;
+        if let Some(error) = response.get("error") {
+            return Err(MinerError::ProtocolError(format!(
+                "Block rejected: {}",
+                error["message"].as_str().unwrap_or("unknown error")
+            )));
+        }
         Ok(())
     }
 
@@ -110,51 +331,338 @@ impl NodeClient {
     /// * `Ok(Value)` - The JSON-RPC response if successful
     /// * `Err(MinerError)` - If there was an error making the RPC call
     async fn rpc_call(&self, method: &str, params: Value) -> Result<Value, MinerError> {
-        let response = self
-            .client
-            .post(&self.config.rpc_url)
-            .basic_auth(&self.config.rpc_user, Some(&self.config.rpc_password))
-            .json(&json!({
-                "jsonrpc": "2.0",
-                "id": "0",
-                "method": method,
-                "params": params
-            }))
-            .send()
-            .await?
-            .json()
-            .await?;
+        self.rpc_call_to(&self.endpoints[self.active], method, params)
+            .await
+    }
+
+    /// Makes an RPC call to a specific node endpoint
+    ///
+    /// Credentials are sent with basic auth until the node answers with a
+    /// digest challenge (as monerod does with `--rpc-login`); the request is
+    /// then repeated with digest auth, which is used for all later requests.
+    ///
+    /// # Arguments
+    /// * `url` - The node RPC endpoint
+    /// * `method` - The RPC method to call
+    /// * `params` - Parameters for the RPC call
+    ///
+    /// # Returns
+    /// * `Ok(Value)` - The JSON-RPC response if successful
+    /// * `Err(MinerError)` - If there was an error making the RPC call or the
+    ///   node refused the credentials
+    async fn rpc_call_to(
+        &self,
+        url: &str,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, MinerError> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": "0",
+            "method": method,
+            "params": params
+        });
 
-        Ok(response)
+        let mut response = self.send_rpc(url, &body).await?;
+        if response.status() == StatusCode::UNAUTHORIZED && self.store_challenge(url, &response) {
+            response = self.send_rpc(url, &body).await?;
+        }
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    /// Sends one RPC request, authenticated with the stored digest challenge
+    /// for `url` or with basic auth before one was received
+    async fn send_rpc(&self, url: &str, body: &Value) -> Result<reqwest::Response, MinerError> {
+        let digest = self
+            .digest
+            .lock()
+            .expect("digest lock poisoned")
+            .get_mut(url)
+            .map(|challenge| {
+                let uri =
+                    Url::parse(url).map_or_else(|_| "/".to_string(), |u| u.path().to_string());
+                challenge.authorization(
+                    &self.config.rpc_user,
+                    &self.config.rpc_password,
+                    "POST",
+                    &uri,
+                )
+            });
+
+        let request = self.client.post(url).json(body);
+        let request = match digest {
+            Some(authorization) => request.header(AUTHORIZATION, authorization),
+            None => request.basic_auth(&self.config.rpc_user, Some(&self.config.rpc_password)),
+        };
+        Ok(request.send().await?)
+    }
+
+    /// Stores the digest challenge of a `401` response
+    ///
+    /// # Returns
+    /// `true` if a usable challenge was found and the request should be retried
+    fn store_challenge(&self, url: &str, response: &reqwest::Response) -> bool {
+        let challenge = response
+            .headers()
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(DigestChallenge::parse);
+
+        match challenge {
+            Some(challenge) => {
+                self.digest
+                    .lock()
+                    .expect("digest lock poisoned")
+                    .insert(url.to_string(), challenge);
+                true
+            }
+            None => false,
+        }
     }
 
     /// Monitors the blockchain for new blocks
     ///
-    /// This function runs in a loop, checking for new blocks every 30 seconds.
-    /// When a new block is detected, it updates the current height.
+    /// This function runs in a loop, checking all configured nodes every 30 seconds.
+    /// The primary node is used while it is synchronized and within
+    /// `max_height_lag` blocks of the highest node; otherwise mining fails over
+    /// to the first healthy backup. When a new block is detected (or the active
+    /// node changes), it fetches a fresh template and sends the resulting job to
+    /// the miners. Solved blocks received from the miners are submitted as they
+    /// arrive, and retried on the other synchronized nodes if that fails.
     ///
-    /// # Returns
-    /// * `Ok(())` - If monitoring started successfully
-    /// * `Err(MinerError)` - If there was an error getting the current height
+    /// When no node is healthy or the template request fails, the miners' job
+    /// is withdrawn (a stale template would only find orphaned blocks) and the
+    /// nodes are checked every `RETRY_INTERVAL` until one recovers.
+    ///
+    /// If `zmq_url` is configured, the node's ZMQ publisher is also watched so
+    /// templates are refreshed the moment a block arrives, and at most every
+    /// `TXPOOL_REFRESH_INTERVAL` when new transactions enter the pool. While the
+    /// subscription is down, monitoring falls back to polling and subscribes
+    /// again on every check. Failing over to another node subscribes to that
+    /// node's publisher instead.
+    ///
+    /// # Errors
+    /// Returns `MinerError` only if the job channel to the miners is closed
     pub async fn monitor_chain(&mut self) -> Result<(), MinerError> {
-        let mut interval = tokio::time::interval(Duration::from_secs(30));
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        let mut share_poll = tokio::time::interval(Duration::from_millis(100));
+        // Subscribed on the first check, which runs immediately
+        let mut zmq = None;
+        let mut last_refresh = Instant::now();
+
         loop {
-            interval.tick().await;
-            let height = self.get_current_height().await?;
-            if height > self.current_height {
-                self.current_height = height;
-                // Trigger new job request
+            tokio::select! {
+                _ = interval.tick() => {
+                    let active = self.active;
+                    match self.select_node().await {
+                        Ok(height) if height > self.current_height => {
+                            self.try_refresh_template().await?;
+                            last_refresh = Instant::now();
+                        }
+                        Ok(_) => {}
+                        Err(e) => self.withdraw_job(&e)?,
+                    }
+                    if self.current_job.is_none() {
+                        interval.reset_after(RETRY_INTERVAL);
+                    }
+                    if self.active != active {
+                        zmq = None;
+                    }
+                    if zmq.is_none() {
+                        zmq = self.connect_zmq().await;
+                    }
+                }
+                event = next_zmq_event(&mut zmq) => {
+                    match event {
+                        Ok(ChainEvent::NewBlock { height }) if height >= self.current_height => {
+                            log::debug!("ZMQ: new block {}", height);
+                            self.try_refresh_template().await?;
+                            last_refresh = Instant::now();
+                        }
+                        Ok(ChainEvent::TxPoolAdd { count })
+                            if last_refresh.elapsed() >= TXPOOL_REFRESH_INTERVAL =>
+                        {
+                            log::debug!("ZMQ: {} new pool transactions", count);
+                            self.try_refresh_template().await?;
+                            last_refresh = Instant::now();
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            log::warn!("ZMQ subscription lost, polling until it is back: {}", e);
+                            zmq = None;
+                        }
+                    }
+                }
+                _ = share_poll.tick() => {
+                    while let Ok(share) = self.share_receiver.try_recv() {
+                        let job_id = share.job_id.clone();
+                        match self.submit_block(share).await {
+                            Ok(()) => log::info!("Block submitted for job {}", job_id),
+                            Err(e) => log::error!("Block submission failed: {}", e),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fetches a fresh template from the active node and sends it to the miners
+    ///
+    /// # Errors
+    /// Returns `MinerError` if the template request or job send fails
+    async fn refresh_template(&mut self) -> Result<(), MinerError> {
+        let job = self.get_block_template().await?;
+        if let Some(template) = &job.template {
+            self.current_height = template.height;
+        }
+        log::info!("New block template at height {}", self.current_height);
+        self.job_sender.send(Some(job))?;
+        Ok(())
+    }
+
+    /// Refreshes the template, withdrawing the miners' job if that fails
+    ///
+    /// # Errors
+    /// Returns `MinerError` if the job channel is closed
+    async fn try_refresh_template(&mut self) -> Result<(), MinerError> {
+        if let Err(e) = self.refresh_template().await {
+            self.withdraw_job(&e)?;
+        }
+        Ok(())
+    }
+
+    /// Stops the miners until a node can supply a template again
+    ///
+    /// # Arguments
+    /// * `error` - Why no template is available
+    ///
+    /// # Errors
+    /// Returns `MinerError` if the job channel is closed
+    fn withdraw_job(&mut self, error: &MinerError) -> Result<(), MinerError> {
+        // Fetch a fresh template as soon as a node is healthy again. The
+        // templates are kept: blocks the miners found before the failure are
+        // still waiting to be submitted, and a new height replaces them anyway.
+        self.current_height = 0;
+        if self.current_job.take().is_none() {
+            log::warn!("Still no template available: {}", error);
+            return Ok(());
+        }
+        log::error!("{}; mining paused until a node recovers", error);
+        self.job_sender.send(None)?;
+        Ok(())
+    }
+
+    /// Connects to the ZMQ publisher of the active node, if configured
+    ///
+    /// # Returns
+    /// The subscriber, or `None` if ZMQ is not configured or unreachable
+    async fn connect_zmq(&self) -> Option<ZmqSubscriber> {
+        let url = match self.active {
+            0 => self.config.zmq_url.as_deref(),
+            backup => self
+                .config
+                .backup_zmq_urls
+                .get(backup - 1)
+                .map(String::as_str),
+        }?;
+        match ZmqSubscriber::connect(url).await {
+            Ok(subscriber) => {
+                log::info!("Subscribed to node notifications at {}", url);
+                Some(subscriber)
+            }
+            Err(e) => {
+                log::warn!("ZMQ unavailable at {}, polling only: {}", url, e);
+                None
             }
         }
     }
 
-    /// Gets the current blockchain height from the node
+    /// Checks every configured node and selects the one to mine against
+    ///
+    /// A node is healthy when it reports itself synchronized and is no more
+    /// than `max_height_lag` blocks behind the highest node. The first healthy
+    /// node in configuration order wins, so mining returns to the primary as
+    /// soon as it recovers. Switching nodes forces a template refresh.
     ///
     /// # Returns
-    /// * `Ok(u64)` - The current blockchain height
-    /// * `Err(MinerError)` - If there was an error getting the height
-    async fn get_current_height(&self) -> Result<u64, MinerError> {
-        let response = self.rpc_call("get_info", json!({})).await?;
-        Ok(response["result"]["height"].as_u64().unwrap_or(0))
+    /// * `Ok(u64)` - The chain height reported by the selected node
+    /// * `Err(MinerError)` - If no node is healthy
+    async fn select_node(&mut self) -> Result<u64, MinerError> {
+        let checks = self.endpoints.iter().map(|url| self.check_node(url));
+        let health = futures::future::join_all(checks).await;
+
+        let best_height = health.iter().map(|h| h.height).max().unwrap_or(0);
+        let selected = health.iter().position(|h| {
+            h.synchronized && h.height > 0 && h.height + self.config.max_height_lag >= best_height
+        });
+
+        let Some(index) = selected else {
+            return Err(MinerError::ConnectionError(format!(
+                "No synchronized node available (best height {})",
+                best_height
+            )));
+        };
+
+        if index != self.active {
+            log::warn!(
+                "Switching node from {} to {} (height {}, best {})",
+                self.endpoints[self.active],
+                self.endpoints[index],
+                health[index].height,
+                best_height
+            );
+            self.active = index;
+            self.current_height = 0; // Force a template from the new node
+        }
+
+        Ok(health[index].height)
+    }
+
+    /// Queries a node's sync state
+    ///
+    /// Errors and timeouts are reported as an unsynchronized node at height 0.
+    ///
+    /// # Arguments
+    /// * `url` - The node RPC endpoint to check
+    async fn check_node(&self, url: &str) -> NodeHealth {
+        let response = tokio::time::timeout(
+            HEALTH_CHECK_TIMEOUT,
+            self.rpc_call_to(url, "get_info", json!({})),
+        )
+        .await;
+
+        match response {
+            Ok(Ok(response)) => {
+                let info = &response["result"];
+                NodeHealth {
+                    height: info["height"].as_u64().unwrap_or(0),
+                    synchronized: info["synchronized"].as_bool().unwrap_or(false)
+                        && info["status"].as_str() == Some("OK"),
+                }
+            }
+            Ok(Err(e)) => {
+                log::warn!("Node {} unreachable: {}", url, e);
+                NodeHealth {
+                    height: 0,
+                    synchronized: false,
+                }
+            }
+            Err(_) => {
+                log::warn!("Node {} timed out", url);
+                NodeHealth {
+                    height: 0,
+                    synchronized: false,
+                }
+            }
+        }
+    }
+}
+
+/// Waits for the next ZMQ event, or forever when ZMQ is not in use
+async fn next_zmq_event(zmq: &mut Option<ZmqSubscriber>) -> Result<ChainEvent, MinerError> {
+    match zmq {
+        Some(subscriber) => subscriber.next_event().await,
+        None => std::future::pending().await,
     }
 }
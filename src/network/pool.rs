@@ -4,22 +4,37 @@
 //!
 //! Handles communication with mining pools using the Stratum protocol over WebSocket.
 //! Manages connection lifecycle, job distribution, and share submission.
-use crate::miner::scheduler::{MiningJob, Share};
-use crate::types::AlgorithmType;
+use crate::miner::algorithm::NONCE_OFFSET;
+use crate::miner::features::RandomXFlags;
+use crate::miner::scheduler::{MiningJob, NonceSpace, Share};
+use crate::miner::verifier::{ShareVerifier, VerifierCache};
+use crate::network::node::{NodeClient, NodeConfig};
+use crate::network::recording::{Direction, SessionRecorder};
+use crate::network::share_queue::ShareQueue;
+use crate::stats::{BenchmarkResults, ShareResult};
+use crate::types::{AlgorithmType, RejectReason};
+use crate::utils::difficulty::{difficulty_from_target, full_target, meets_target};
 use crate::utils::error::MinerError;
+use futures::stream::{SplitSink, SplitStream};
 use futures::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use tokio::net::TcpStream;
 use tokio::sync::Mutex;
 use tokio::time;
-use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 use tungstenite::protocol::Message;
 use url::Url;
 
+/// WebSocket stream to the pool
+type PoolStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
 /// Configuration for connecting to a mining pool
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PoolConfig {
@@ -31,6 +46,95 @@ pub struct PoolConfig {
     pub password: String,
     /// Worker identifier for statistics tracking
     pub worker_id: String,
+    /// Own node used to build block templates for pools supporting the
+    /// `self-select` extension (the pool still supplies wallet and difficulty)
+    #[serde(default)]
+    pub self_select: Option<NodeConfig>,
+    /// Stored benchmark results advertised to the pool as `algo-perf`
+    /// (default: "benchmark.json")
+    #[serde(default = "default_benchmark_file")]
+    pub benchmark_file: PathBuf,
+    /// Request a fixed share difficulty aiming at this many shares per
+    /// minute, using the `wallet+difficulty` login convention (default: pool vardiff)
+    #[serde(default)]
+    pub target_shares_per_minute: Option<f64>,
+    /// URL receiving a JSON `POST` whenever a share meets the network
+    /// difficulty (default: log only)
+    #[serde(default)]
+    pub block_notify_url: Option<String>,
+    /// Append every message exchanged with the pool to this file, for
+    /// offline replay with the `replay` subcommand (default: not recorded)
+    #[serde(default)]
+    pub record_file: Option<PathBuf>,
+}
+
+fn default_benchmark_file() -> PathBuf {
+    "benchmark.json".into()
+}
+
+/// Minimum measurement time before the share-based hashrate estimate is trusted
+const MIN_ESTIMATE_TIME: Duration = Duration::from_secs(60);
+
+/// Request id of the first share submission or template report; later
+/// requests count up
+const FIRST_SUBMIT_ID: u64 = 100;
+
+/// Number of recent jobs kept for re-verifying rejected shares
+const RECENT_JOBS: usize = 8;
+
+/// Low difficulty rejections within [`LOW_DIFFICULTY_WINDOW`] that trigger re-verification
+const LOW_DIFFICULTY_BURST: usize = 3;
+
+/// Time window for counting low difficulty rejections
+const LOW_DIFFICULTY_WINDOW: Duration = Duration::from_secs(60);
+
+/// Hashrate estimate derived from the difficulty of found shares
+#[derive(Debug)]
+struct HashrateEstimate {
+    /// Start of the measurement
+    since: Instant,
+    /// Sum of the difficulty of all shares found since `since`
+    difficulty_sum: u64,
+    /// Algorithm of the current job
+    algorithm: AlgorithmType,
+}
+
+impl HashrateEstimate {
+    /// Estimated hashrate in H/s, once enough time has passed
+    fn hashrate(&self) -> Option<f64> {
+        let elapsed = self.since.elapsed();
+        (elapsed >= MIN_ESTIMATE_TIME && self.difficulty_sum > 0)
+            .then(|| self.difficulty_sum as f64 / elapsed.as_secs_f64())
+    }
+}
+
+/// Network block target of the current job, when the pool sends it
+#[derive(Debug, Clone, Copy)]
+struct NetworkTarget {
+    /// Height of the block being mined
+    height: Option<u64>,
+    /// Network difficulty of that block
+    difficulty: u64,
+}
+
+impl NetworkTarget {
+    /// Whether a share hash also solves the block
+    ///
+    /// Compared over the full 256-bit target, as monerod checks blocks; the
+    /// most significant 64 bits alone accept hashes just above it.
+    fn solved_by(&self, hash: &[u8; 32]) -> bool {
+        meets_target(hash, &full_target(self.difficulty))
+    }
+}
+
+/// Self-select job waiting for the pool to accept its block template
+struct ReportedTemplate {
+    /// Job mined once the pool accepts the template
+    job: MiningJob,
+    /// Height of the template's block
+    height: Option<u64>,
+    /// Network difficulty of that block
+    difficulty: Option<u64>,
 }
 
 /// Client for communicating with a mining pool
@@ -43,10 +147,44 @@ pub struct PoolConfig {
 pub struct PoolClient {
     /// Pool connection configuration
     config: PoolConfig,
-    /// Thread-safe WebSocket connection handle
-    connection: Mutex<Option<WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>>>>,
+    /// Thread-safe WebSocket write half
+    connection: Mutex<Option<SplitSink<PoolStream, Message>>>,
+    /// WebSocket read half, owned by the event loop while it runs
+    incoming: Mutex<Option<SplitStream<PoolStream>>>,
+    /// Session identifier assigned by the pool on login
+    session_id: std::sync::Mutex<Option<String>>,
+    /// Node client supplying block templates in self-select mode
+    node: Option<NodeClient>,
+    /// Whether the pool's login response enabled NiceHash nonce splitting
+    /// (`None` if it did not say)
+    nicehash: std::sync::Mutex<Option<bool>>,
+    /// Hashrate measured from found shares, kept across reconnects
+    estimate: std::sync::Mutex<HashrateEstimate>,
+    /// Shares waiting for submission, filtered for stale and duplicate shares
+    queue: std::sync::Mutex<ShareQueue>,
+    /// Network target of the current job, used to spot block candidates
+    network: std::sync::Mutex<Option<NetworkTarget>>,
+    /// Channel for reporting share events to the statistics reporter
+    stats: Option<crossbeam_channel::Sender<ShareResult>>,
+    /// Channel receiving each submitted share together with the pool's verdict
+    outcomes: Option<crossbeam_channel::Sender<(Share, ShareResult)>>,
+    /// Request id for the next share submission or template report
+    next_submit_id: AtomicU64,
+    /// Submitted shares awaiting the pool's response, keyed by request id
+    in_flight: std::sync::Mutex<HashMap<u64, Share>>,
+    /// Reported self-select templates awaiting the pool's response, keyed by
+    /// request id
+    reported_templates: std::sync::Mutex<HashMap<u64, ReportedTemplate>>,
+    /// Recently received jobs, newest last
+    recent_jobs: std::sync::Mutex<VecDeque<MiningJob>>,
+    /// Shares recently rejected for low difficulty and when
+    low_difficulty: std::sync::Mutex<VecDeque<(Instant, Share)>>,
+    /// Recomputes shares after a burst of low difficulty rejections
+    verifier: Arc<ShareVerifier>,
+    /// Session recorder, opened on the first connect when `record_file` is set
+    recorder: std::sync::Mutex<Option<SessionRecorder>>,
     /// Channel for sending received jobs to miners
-    job_sender: crossbeam_channel::Sender<MiningJob>,
+    job_sender: crossbeam_channel::Sender<Option<MiningJob>>,
     /// Channel for receiving shares from miners (wrapped in Arc for thread safety)
     share_receiver: Arc<crossbeam_channel::Receiver<Share>>,
 }
@@ -60,17 +198,88 @@ impl PoolClient {
     /// * `share_receiver` - Channel for receiving shares from miners
     pub fn new(
         config: PoolConfig,
-        job_sender: crossbeam_channel::Sender<MiningJob>,
+        job_sender: crossbeam_channel::Sender<Option<MiningJob>>,
         share_receiver: crossbeam_channel::Receiver<Share>,
     ) -> Self {
+        // The node client is only used for template requests; it never runs
+        // its own monitoring loop, so sharing the pool's channels is harmless.
+        let node = config
+            .self_select
+            .clone()
+            .map(|node_cfg| NodeClient::new(node_cfg, job_sender.clone(), share_receiver.clone()));
+
         PoolClient {
             config,
             connection: Mutex::new(None),
+            incoming: Mutex::new(None),
+            session_id: std::sync::Mutex::new(None),
+            node,
+            nicehash: std::sync::Mutex::new(None),
+            estimate: std::sync::Mutex::new(HashrateEstimate {
+                since: Instant::now(),
+                difficulty_sum: 0,
+                algorithm: AlgorithmType::RandomX,
+            }),
+            queue: std::sync::Mutex::new(ShareQueue::new()),
+            network: std::sync::Mutex::new(None),
+            stats: None,
+            outcomes: None,
+            next_submit_id: AtomicU64::new(FIRST_SUBMIT_ID),
+            in_flight: std::sync::Mutex::new(HashMap::new()),
+            reported_templates: std::sync::Mutex::new(HashMap::new()),
+            recent_jobs: std::sync::Mutex::new(VecDeque::new()),
+            low_difficulty: std::sync::Mutex::new(VecDeque::new()),
+            verifier: Arc::new(ShareVerifier::new()),
+            recorder: std::sync::Mutex::new(None),
             job_sender,
             share_receiver: Arc::new(share_receiver),
         }
     }
 
+    /// Reports share events (such as block candidates) to the statistics reporter
+    ///
+    /// # Arguments
+    /// * `stats` - Sender obtained from [`StatsReporter::share_sender`]
+    ///
+    /// [`StatsReporter::share_sender`]: crate::stats::StatsReporter::share_sender
+    pub fn with_stats(mut self, stats: crossbeam_channel::Sender<ShareResult>) -> Self {
+        self.queue = std::sync::Mutex::new(ShareQueue::new().with_stats(stats.clone()));
+        self.verifier = Arc::new(
+            ShareVerifier::new()
+                .with_cache(self.verifier.cache().clone())
+                .with_stats(stats.clone()),
+        );
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Creates RandomX verifier instances with the given flags instead of
+    /// the fastest ones the CPU supports
+    ///
+    /// # Arguments
+    /// * `flags` - Flags selected with [`RandomXFlags::select`]
+    pub fn with_randomx_flags(mut self, flags: RandomXFlags) -> Self {
+        if let Some(verifier) = Arc::get_mut(&mut self.verifier) {
+            *verifier = std::mem::take(verifier).with_cache(VerifierCache::new(flags));
+        }
+        self
+    }
+
+    /// Reports the pool's verdict on every submitted share
+    ///
+    /// Unlike [`with_stats`](Self::with_stats), each result is sent together
+    /// with the share it refers to, so callers can credit whoever found it.
+    ///
+    /// # Arguments
+    /// * `outcomes` - Channel receiving `(share, result)` pairs
+    pub fn with_outcomes(
+        mut self,
+        outcomes: crossbeam_channel::Sender<(Share, ShareResult)>,
+    ) -> Self {
+        self.outcomes = Some(outcomes);
+        self
+    }
+
     /// Establishes connection to the mining pool
     ///
     /// # Errors
@@ -78,11 +287,26 @@ impl PoolClient {
     /// - URL is invalid
     /// - DNS resolution fails
     /// - WebSocket handshake fails
+    /// - The recording file cannot be opened
     pub async fn connect(&self) -> Result<(), MinerError> {
         let url_str = &self.config.url;
         let url = Url::parse(url_str)
             .map_err(|e| MinerError::ConfigError(format!("Invalid URL '{}': {}", url_str, e)))?;
 
+        if let Some(path) = &self.config.record_file {
+            let mut recorder = self.recorder.lock().expect("recorder lock poisoned");
+            if recorder.is_none() {
+                *recorder = Some(SessionRecorder::open(path).map_err(|e| {
+                    MinerError::ConfigError(format!(
+                        "Cannot open record file {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?);
+                log::info!("Recording pool session to {}", path.display());
+            }
+        }
+
         if url.scheme() != "ws" && url.scheme() != "wss" {
             log::warn!(
                 "Pool URL '{}' uses non-WebSocket scheme. Consider using 'ws://' or 'wss://'",
@@ -92,8 +316,9 @@ impl PoolClient {
 
         match tokio_tungstenite::connect_async(url_str).await {
             Ok((ws_stream, _)) => {
-                let mut conn = self.connection.lock().await;
-                *conn = Some(ws_stream);
+                let (sink, stream) = ws_stream.split();
+                *self.connection.lock().await = Some(sink);
+                *self.incoming.lock().await = Some(stream);
                 Ok(())
             }
             Err(e) => {
@@ -114,27 +339,46 @@ impl PoolClient {
     ///
     /// Handles:
     /// - Receiving jobs from pool
-    /// - Submitting shares to pool
+    /// - Submitting shares to pool, skipping stale and duplicate shares
     /// - Sending keepalive messages
     ///
+    /// Shares found while disconnected are held until the first job of the
+    /// new session shows whether their job is still live.
+    ///
     /// # Errors
-    /// Returns `MinerError` if communication fails
+    /// Returns `MinerError` if communication fails or the pool reports the
+    /// session as unauthenticated (the caller should reconnect)
     pub async fn run(&self) -> Result<(), MinerError> {
+        self.queue
+            .lock()
+            .expect("share queue poisoned")
+            .session_started();
+        self.in_flight
+            .lock()
+            .expect("in-flight lock poisoned")
+            .clear();
+        self.reported_templates
+            .lock()
+            .expect("reported templates lock poisoned")
+            .clear();
         self.login().await?;
         self.subscribe().await?;
 
         let mut interval = time::interval(Duration::from_secs(30));
-        let mut conn = self.connection.lock().await;
-        let ws = conn
+        let mut share_poll = time::interval(Duration::from_millis(100));
+        let mut incoming = self.incoming.lock().await;
+        let ws = incoming
             .as_mut()
             .ok_or(MinerError::ConnectionError("Not connected".into()))?;
 
         loop {
-            let receiver = Arc::clone(&self.share_receiver);
             tokio::select! {
                 msg = ws.next() => {
                     match msg {
-                        Some(Ok(Message::Text(text))) => self.handle_message(&text).await?,
+                        Some(Ok(Message::Text(text))) => {
+                            self.record(Direction::In, &text);
+                            self.handle_message(&text).await?
+                        }
                         Some(Err(e)) => return Err(e.into()),
                         None => return Ok(()),
                         _ => {}
@@ -143,10 +387,8 @@ impl PoolClient {
                 _ = interval.tick() => {
                     self.keep_alive().await?;
                 }
-                share = tokio::task::spawn_blocking(move || receiver.recv()) => {
-                    if let Ok(share) = share? {
-                        self.submit_share(&share).await?;
-                    }
+                _ = share_poll.tick() => {
+                    self.submit_ready_shares().await?;
                 }
             }
         }
@@ -161,23 +403,216 @@ impl PoolClient {
     /// Returns `MinerError` if:
     /// - Message parsing fails
     /// - Job handling fails
+    /// - The pool reports the session as unauthenticated
     async fn handle_message(&self, message: &str) -> Result<(), MinerError> {
         let json: Value = serde_json::from_str(message)?;
 
         if let Some(method) = json.get("method").and_then(|m| m.as_str()) {
             match method {
-                "job" => self.handle_job(&json).await?,
+                "job" => self.handle_job(&json["params"]).await?,
                 _ => log::warn!("Unknown method received: {}", method),
             }
+        } else if !json["error"].is_null() {
+            self.handle_error(&json)?;
+        } else if json["result"]["job"].is_object() {
+            // Login response carrying the session id and first job
+            if let Some(id) = json["result"]["id"].as_str() {
+                *self.session_id.lock().expect("session id lock poisoned") = Some(id.to_string());
+            }
+            *self.nicehash.lock().expect("nicehash lock poisoned") =
+                login_nicehash(&json["result"]);
+            self.handle_job(&json["result"]["job"]).await?;
+        } else if let Some(share) = self.take_in_flight(&json) {
+            log::debug!("Share for job {} accepted", share.job_id);
+            self.report_outcome(share, ShareResult::Accepted);
+        } else if let Some(reported) = self.take_reported_template(&json) {
+            match json["result"]["status"].as_str() {
+                Some("OK") => self.start_self_select_job(reported)?,
+                status => self.template_rejected(reported, status.unwrap_or("no status"))?,
+            }
         }
 
         Ok(())
     }
 
+    /// Handles an error response from the pool
+    ///
+    /// The message is classified into a [`RejectReason`]; rejected shares are
+    /// counted per reason. A burst of low difficulty rejections re-verifies
+    /// the rejected shares locally.
+    ///
+    /// # Arguments
+    /// * `json` - Response carrying a non-null `error`
+    ///
+    /// # Errors
+    /// Returns `MinerError::ConnectionError` if the session is no longer
+    /// authenticated, so the caller reconnects and logs in again
+    fn handle_error(&self, json: &Value) -> Result<(), MinerError> {
+        let error = &json["error"];
+        let message = error["message"]
+            .as_str()
+            .or(error.as_str())
+            .unwrap_or("unknown error");
+        let reason = RejectReason::classify(message);
+
+        match self.take_in_flight(json) {
+            Some(share) => {
+                log::warn!(
+                    "Share for job {} rejected ({}): {}",
+                    share.job_id,
+                    reason,
+                    message
+                );
+                if reason == RejectReason::LowDifficulty {
+                    self.low_difficulty_rejected(share.clone());
+                }
+                self.report_outcome(share, ShareResult::Rejected(reason));
+            }
+            None => match self.take_reported_template(json) {
+                Some(reported) => self.template_rejected(reported, message)?,
+                None => log::warn!("Pool error ({}): {}", reason, message),
+            },
+        }
+
+        if reason == RejectReason::Unauthenticated {
+            return Err(MinerError::ConnectionError(format!(
+                "Pool session is not authenticated: {}",
+                message
+            )));
+        }
+        Ok(())
+    }
+
+    /// Reports the pool's verdict on a share to the statistics reporter and
+    /// the outcome channel
+    fn report_outcome(&self, share: Share, result: ShareResult) {
+        if let Some(stats) = &self.stats {
+            let _ = stats.send(result);
+        }
+        if let Some(outcomes) = &self.outcomes {
+            let _ = outcomes.send((share, result));
+        }
+    }
+
+    /// Removes and returns the submitted share a response refers to
+    fn take_in_flight(&self, json: &Value) -> Option<Share> {
+        let id = json["id"].as_u64()?;
+        self.in_flight
+            .lock()
+            .expect("in-flight lock poisoned")
+            .remove(&id)
+    }
+
+    /// Removes and returns the reported template a response refers to
+    fn take_reported_template(&self, json: &Value) -> Option<ReportedTemplate> {
+        let id = json["id"].as_u64()?;
+        self.reported_templates
+            .lock()
+            .expect("reported templates lock poisoned")
+            .remove(&id)
+    }
+
+    /// Starts mining a self-select job whose template the pool accepted
+    ///
+    /// # Errors
+    /// Returns `MinerError` if the job channel is closed
+    fn start_self_select_job(&self, reported: ReportedTemplate) -> Result<(), MinerError> {
+        let ReportedTemplate {
+            job,
+            height,
+            difficulty,
+        } = reported;
+        log::info!(
+            "Self-select template at height {} for job {} accepted",
+            height.map_or_else(|| "unknown".to_string(), |h| h.to_string()),
+            job.job_id
+        );
+        self.track_job(&job, height, difficulty);
+        self.job_sender.send(Some(job))?;
+        Ok(())
+    }
+
+    /// Stops the miners after the pool refused a self-select template
+    ///
+    /// The previous job belongs to an older template, so mining pauses until
+    /// the pool sends the next job.
+    ///
+    /// # Errors
+    /// Returns `MinerError` if the job channel is closed
+    fn template_rejected(
+        &self,
+        reported: ReportedTemplate,
+        message: &str,
+    ) -> Result<(), MinerError> {
+        log::error!(
+            "Pool rejected the block template for job {}: {}; mining paused until the next job",
+            reported.job.job_id,
+            message
+        );
+        self.job_sender.send(None)?;
+        Ok(())
+    }
+
+    /// Records a low difficulty rejection and re-verifies on a burst
+    ///
+    /// Once [`LOW_DIFFICULTY_BURST`] shares were rejected within
+    /// [`LOW_DIFFICULTY_WINDOW`], they are recomputed in the background. Shares
+    /// that do not reproduce are counted as hardware errors; shares that do
+    /// point at a pool using a different algorithm, seed or target.
+    fn low_difficulty_rejected(&self, share: Share) {
+        let burst: Vec<Share> = {
+            let mut recent = self.low_difficulty.lock().expect("rejection log poisoned");
+            recent.retain(|(at, _)| at.elapsed() < LOW_DIFFICULTY_WINDOW);
+            recent.push_back((Instant::now(), share));
+            if recent.len() < LOW_DIFFICULTY_BURST {
+                return;
+            }
+            recent.drain(..).map(|(_, share)| share).collect()
+        };
+
+        log::warn!(
+            "{} low difficulty rejections within {} seconds; re-verifying them",
+            burst.len(),
+            LOW_DIFFICULTY_WINDOW.as_secs()
+        );
+        let checks: Vec<(MiningJob, Share)> = {
+            let jobs = self.recent_jobs.lock().expect("recent jobs poisoned");
+            burst
+                .into_iter()
+                .filter_map(|share| {
+                    let job = jobs.iter().find(|job| job.job_id == share.job_id)?;
+                    Some((job.clone(), share))
+                })
+                .collect()
+        };
+        let verifier = self.verifier.clone();
+        tokio::task::spawn_blocking(move || {
+            for (job, share) in checks {
+                if verifier.verify(&job, &job.blob, &share) {
+                    log::warn!(
+                        "Rejected share for job {} nonce {} verifies locally; \
+                         check that the pool uses the same algorithm ({}) and seed",
+                        share.job_id,
+                        share.nonce,
+                        job.algorithm
+                    );
+                }
+            }
+        });
+    }
+
     /// Processes incoming mining job notifications
     ///
+    /// Jobs carrying a `pool_wallet` instead of a blob are self-select jobs and
+    /// are completed with a template from the configured node.
+    ///
+    /// When the pool enabled `nicehash` on login, or did not say and the job
+    /// arrives with the top nonce byte already set, that byte is kept fixed
+    /// while mining. An optional `network_difficulty` enables block candidate
+    /// detection.
+    ///
     /// # Arguments
-    /// * `json` - Parsed JSON message containing job details
+    /// * `params` - Job parameters from a `job` notification or login response
     ///
     /// # Errors
     /// Returns `MinerError` if:
@@ -185,53 +620,150 @@ impl PoolClient {
     /// - Hex decoding fails
     /// - Algorithm parsing fails
     /// - Job channel send fails
-    async fn handle_job(&self, json: &Value) -> Result<(), MinerError> {
-        let params = json["params"]
+    async fn handle_job(&self, params: &Value) -> Result<(), MinerError> {
+        let params = params
             .as_object()
             .ok_or_else(|| MinerError::ProtocolError("Missing params object".to_string()))?;
 
-        let job = MiningJob {
-            job_id: params["job_id"]
-                .as_str()
-                .ok_or_else(|| MinerError::ProtocolError("Missing job_id".to_string()))?
-                .to_string(),
-            blob: hex::decode(
-                params["blob"]
-                    .as_str()
-                    .ok_or_else(|| MinerError::ProtocolError("Missing blob".to_string()))?,
-            )?,
-            target: hex::decode(
-                params["target"]
-                    .as_str()
-                    .ok_or_else(|| MinerError::ProtocolError("Missing target".to_string()))?,
-            )?,
-            algorithm: AlgorithmType::from_str(
-                params["algo"]
-                    .as_str()
-                    .ok_or_else(|| MinerError::ProtocolError("Missing algo".to_string()))?,
+        if params.contains_key("pool_wallet") {
+            return self.handle_self_select_job(params).await;
+        }
+
+        let nicehash = *self.nicehash.lock().expect("nicehash lock poisoned");
+        let job = parse_job(params, nicehash)?;
+        let network_difficulty = params.get("network_difficulty").and_then(|d| d.as_u64());
+        self.track_job(
+            &job,
+            params.get("height").and_then(|h| h.as_u64()),
+            network_difficulty,
+        );
+        self.job_sender.send(Some(job))?;
+        Ok(())
+    }
+
+    /// Processes a self-select job
+    ///
+    /// Fetches a block template paying to the pool wallet with the pool's extra
+    /// nonce from our own node and reports it to the pool with `block_template`.
+    /// Once the pool accepts the template, its hashing blob is mined against
+    /// the pool target; if it refuses, the miners' job is withdrawn.
+    ///
+    /// # Arguments
+    /// * `params` - Job parameters containing `pool_wallet` and `extra_nonce`
+    ///
+    /// # Errors
+    /// Returns `MinerError` if:
+    /// - No node is configured for self-select mode
+    /// - Required fields are missing
+    /// - The node template request fails
+    /// - WebSocket communication fails
+    async fn handle_self_select_job(
+        &self,
+        params: &serde_json::Map<String, Value>,
+    ) -> Result<(), MinerError> {
+        let node = self.node.as_ref().ok_or_else(|| {
+            MinerError::ConfigError(
+                "Pool sent a self-select job but no self_select node is configured".to_string(),
             )
-            .map_err(|e| MinerError::ProtocolError(e))?,
+        })?;
+
+        let field = |name: &str| {
+            params
+                .get(name)
+                .and_then(Value::as_str)
+                .ok_or_else(|| MinerError::ProtocolError(format!("Missing {}", name)))
         };
+        let job_id = field("job_id")?;
+        let template = node
+            .get_pool_template(field("pool_wallet")?, field("extra_nonce")?)
+            .await?;
 
-        self.job_sender.send(job)?;
-        Ok(())
+        let job = MiningJob {
+            job_id: job_id.to_string(),
+            blob: hex::decode(template["blockhashing_blob"].as_str().ok_or_else(|| {
+                MinerError::ProtocolError("Missing blockhashing_blob".to_string())
+            })?)?,
+            target: hex::decode(field("target")?)?,
+            algorithm: match params.get("algo").and_then(|a| a.as_str()) {
+                Some(algo) => AlgorithmType::from_str(algo).map_err(MinerError::ProtocolError)?,
+                None => AlgorithmType::RandomX,
+            },
+            seed_hash: template["seed_hash"].as_str().map(str::to_string),
+            nonce_space: NonceSpace::default(),
+            template: None,
+        };
+
+        let id = self.next_submit_id.fetch_add(1, Ordering::Relaxed);
+        let message = json!({
+            "method": "block_template",
+            "params": {
+                "id": self.session_id(),
+                "job_id": job_id,
+                "blob": template["blocktemplate_blob"],
+                "height": template["height"],
+                "difficulty": template["difficulty"],
+                "prev_hash": template["prev_hash"],
+                "seed_hash": template["seed_hash"],
+                "next_seed_hash": template["next_seed_hash"]
+            },
+            "id": id
+        });
+
+        log::info!(
+            "Reporting self-select template at height {} for job {}",
+            template["height"],
+            job.job_id
+        );
+        self.reported_templates
+            .lock()
+            .expect("reported templates lock poisoned")
+            .insert(
+                id,
+                ReportedTemplate {
+                    job,
+                    height: template["height"].as_u64(),
+                    difficulty: template["difficulty"].as_u64(),
+                },
+            );
+        let sent = self.send(message).await;
+        if sent.is_err() {
+            self.reported_templates
+                .lock()
+                .expect("reported templates lock poisoned")
+                .remove(&id);
+        }
+        sent
     }
 
     /// Sends login request to the mining pool
     ///
+    /// Advertises every algorithm this build supports (`algo`) along with the
+    /// stored benchmark hashrates (`algo-perf`), so multi-algo pools can pick
+    /// the work; the algorithm of each job is honored by the scheduler.
+    /// Requests `self-select` mode when a node is configured for it, and a
+    /// fixed difficulty when `target_shares_per_minute` is set.
+    ///
     /// # Errors
     /// Returns `MinerError` if:
     /// - WebSocket communication fails
     async fn login(&self) -> Result<(), MinerError> {
-        let message = json!({
+        let mut message = json!({
             "method": "login",
             "params": {
-                "login": self.config.user,
+                "login": self.login_name(),
                 "pass": self.config.password,
-                "agent": format!("xmr_miner-rs/{}", env!("CARGO_PKG_VERSION"))
+                "agent": format!("xmr_miner-rs/{}", env!("CARGO_PKG_VERSION")),
+                "algo": AlgorithmType::supported()
+                    .iter()
+                    .map(|algo| algo.stratum_name())
+                    .collect::<Vec<_>>(),
+                "algo-perf": self.algo_perf()
             },
             "id": 1
         });
+        if self.node.is_some() {
+            message["params"]["mode"] = json!("self-select");
+        }
 
         self.send(message).await
     }
@@ -253,8 +785,41 @@ impl PoolClient {
         self.send(message).await
     }
 
+    /// Moves found shares into the queue and submits those still worth sending
+    ///
+    /// If a submission fails, the unsent shares are put back so they can be
+    /// retried after reconnecting.
+    ///
+    /// # Errors
+    /// Returns `MinerError` if:
+    /// - WebSocket communication fails
+    async fn submit_ready_shares(&self) -> Result<(), MinerError> {
+        let ready = {
+            let mut queue = self.queue.lock().expect("share queue poisoned");
+            while let Ok(share) = self.share_receiver.try_recv() {
+                queue.push(share);
+            }
+            queue.take_ready()
+        };
+
+        for (index, share) in ready.iter().enumerate() {
+            if let Err(e) = self.submit_share(share).await {
+                let mut queue = self.queue.lock().expect("share queue poisoned");
+                ready[index..]
+                    .iter()
+                    .rev()
+                    .for_each(|share| queue.requeue(share.clone()));
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
     /// Submits a completed share to the mining pool
     ///
+    /// Shares that also meet the network difficulty of their job are reported
+    /// as block candidates before being submitted.
+    ///
     /// # Arguments
     /// * `share` - The share to submit
     ///
@@ -262,18 +827,207 @@ impl PoolClient {
     /// Returns `MinerError` if:
     /// - WebSocket communication fails
     async fn submit_share(&self, share: &Share) -> Result<(), MinerError> {
+        let id = self.next_submit_id.fetch_add(1, Ordering::Relaxed);
+        self.count_share(share);
+        let network = *self.network.lock().expect("network target poisoned");
+        if let Some(network) = network
+            && network.solved_by(&share.result)
+        {
+            self.report_block_candidate(share, network);
+        }
+
         let message = json!({
             "method": "submit",
             "params": {
-                "id": self.config.worker_id,
+                "id": self.session_id(),
                 "job_id": share.job_id,
-                "nonce": format!("{:08x}", share.nonce),
+                "nonce": hex::encode((share.nonce as u32).to_le_bytes()),
                 "result": hex::encode(share.result)
             },
-            "id": 3
+            "id": id
         });
 
-        self.send(message).await
+        self.in_flight
+            .lock()
+            .expect("in-flight lock poisoned")
+            .insert(id, share.clone());
+        let sent = self.send(message).await;
+        if sent.is_err() {
+            self.in_flight
+                .lock()
+                .expect("in-flight lock poisoned")
+                .remove(&id);
+        }
+        sent
+    }
+
+    /// Builds the login name, appending `+difficulty` for fixed difficulty requests
+    ///
+    /// The difficulty aims at `target_shares_per_minute` using the hashrate
+    /// measured from found shares, or the stored benchmark before enough shares
+    /// were found. It is recomputed on every (re)connect. A difficulty already
+    /// present in `user` is left untouched.
+    fn login_name(&self) -> String {
+        let user = &self.config.user;
+        let Some(shares_per_minute) = self.config.target_shares_per_minute else {
+            return user.clone();
+        };
+        if user.contains('+') || shares_per_minute <= 0.0 {
+            return user.clone();
+        }
+
+        let (measured, algorithm) = {
+            let estimate = self.estimate.lock().expect("hashrate estimate poisoned");
+            (estimate.hashrate(), estimate.algorithm)
+        };
+        let Some(hashrate) = measured.or_else(|| self.benchmark_results().hashrate(algorithm))
+        else {
+            log::warn!("No hashrate measured or benchmarked yet; using pool difficulty");
+            return user.clone();
+        };
+
+        let difficulty = ((hashrate * 60.0 / shares_per_minute) as u64).max(1);
+        log::info!(
+            "Requesting fixed difficulty {} ({:.2} H/s, {} shares/min)",
+            difficulty,
+            hashrate,
+            shares_per_minute
+        );
+        format!("{}+{}", user, difficulty)
+    }
+
+    /// Logs, counts and optionally announces a share solving a block
+    ///
+    /// The notification is sent in the background so submission is never delayed.
+    fn report_block_candidate(&self, share: &Share, network: NetworkTarget) {
+        let height = network
+            .height
+            .map_or_else(|| "unknown".to_string(), |h| h.to_string());
+        log::warn!("************************************************************");
+        log::warn!(
+            "BLOCK CANDIDATE at height {} (network difficulty {})",
+            height,
+            network.difficulty
+        );
+        log::warn!(
+            "job {} nonce {:08x} hash {}",
+            share.job_id,
+            share.nonce,
+            hex::encode(share.result)
+        );
+        log::warn!("************************************************************");
+
+        if let Some(stats) = &self.stats {
+            let _ = stats.send(ShareResult::BlockCandidate);
+        }
+
+        if let Some(url) = self.config.block_notify_url.clone() {
+            let body = json!({
+                "worker_id": self.config.worker_id,
+                "height": network.height,
+                "network_difficulty": network.difficulty,
+                "job_id": share.job_id,
+                "nonce": hex::encode((share.nonce as u32).to_le_bytes()),
+                "hash": hex::encode(share.result)
+            });
+            tokio::spawn(async move {
+                let result = reqwest::Client::new().post(&url).json(&body).send().await;
+                if let Err(e) = result.and_then(|response| response.error_for_status()) {
+                    log::error!("Block candidate notification to {} failed: {}", url, e);
+                }
+            });
+        }
+    }
+
+    /// Records a new job for share filtering, difficulty estimation and
+    /// block candidate detection
+    ///
+    /// # Arguments
+    /// * `job` - The job received from the pool
+    /// * `height` - Block height of the job, if the pool sent it
+    /// * `network_difficulty` - Network difficulty of the block, if the pool sent it
+    fn track_job(&self, job: &MiningJob, height: Option<u64>, network_difficulty: Option<u64>) {
+        self.queue
+            .lock()
+            .expect("share queue poisoned")
+            .job_received(&job.job_id, height);
+        *self.network.lock().expect("network target poisoned") =
+            network_difficulty.map(|difficulty| NetworkTarget { height, difficulty });
+
+        let mut jobs = self.recent_jobs.lock().expect("recent jobs poisoned");
+        if jobs.len() == RECENT_JOBS {
+            jobs.pop_front();
+        }
+        jobs.push_back(job.clone());
+
+        self.estimate
+            .lock()
+            .expect("hashrate estimate poisoned")
+            .algorithm = job.algorithm;
+    }
+
+    /// Adds a submitted share's difficulty to the hashrate estimate
+    ///
+    /// Uses the difficulty of the job the share was found for, which differs
+    /// from the current job's after a difficulty change. Shares of jobs no
+    /// longer among the recent jobs are not counted.
+    ///
+    /// # Arguments
+    /// * `share` - The share being submitted
+    fn count_share(&self, share: &Share) {
+        let difficulty = self
+            .recent_jobs
+            .lock()
+            .expect("recent jobs poisoned")
+            .iter()
+            .rev()
+            .find(|job| job.job_id == share.job_id)
+            .and_then(|job| difficulty_from_target(&job.target).ok());
+        if let Some(difficulty) = difficulty {
+            self.estimate
+                .lock()
+                .expect("hashrate estimate poisoned")
+                .difficulty_sum += difficulty;
+        }
+    }
+
+    /// Loads stored benchmark results, treating unreadable files as empty
+    fn benchmark_results(&self) -> BenchmarkResults {
+        BenchmarkResults::load(&self.config.benchmark_file).unwrap_or_else(|e| {
+            log::warn!(
+                "Ignoring benchmark results at {}: {}",
+                self.config.benchmark_file.display(),
+                e
+            );
+            BenchmarkResults::default()
+        })
+    }
+
+    /// Builds the `algo-perf` login field from stored benchmark results
+    ///
+    /// Algorithms that were never benchmarked are left out.
+    fn algo_perf(&self) -> Value {
+        let results = self.benchmark_results();
+
+        AlgorithmType::supported()
+            .iter()
+            .filter_map(|algo| {
+                Some((
+                    algo.stratum_name().to_string(),
+                    json!(results.hashrate(*algo)?),
+                ))
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+
+    /// Returns the pool-assigned session id, or the worker id before login
+    fn session_id(&self) -> String {
+        self.session_id
+            .lock()
+            .expect("session id lock poisoned")
+            .clone()
+            .unwrap_or_else(|| self.config.worker_id.clone())
     }
 
     /// Sends keepalive message to maintain connection
@@ -299,7 +1053,270 @@ impl PoolClient {
         let ws = conn
             .as_mut()
             .ok_or(MinerError::ConnectionError("Not connected".into()))?;
-        ws.send(Message::Text(value.to_string().into())).await?;
+        let text = value.to_string();
+        self.record(Direction::Out, &text);
+        ws.send(Message::Text(text.into())).await?;
         Ok(())
     }
+
+    /// Appends a message to the session recording, if enabled
+    fn record(&self, direction: Direction, message: &str) {
+        if let Some(recorder) = self
+            .recorder
+            .lock()
+            .expect("recorder lock poisoned")
+            .as_mut()
+        {
+            recorder.record(direction, message);
+        }
+    }
+}
+
+/// Reads whether a login result enables NiceHash nonce splitting
+///
+/// Pools either list `"nicehash"` in `extensions` or send a `nicehash` flag.
+///
+/// # Returns
+/// `None` if the pool says neither
+pub(crate) fn login_nicehash(result: &Value) -> Option<bool> {
+    result["nicehash"].as_bool().or_else(|| {
+        result["extensions"]
+            .as_array()
+            .map(|extensions| extensions.iter().any(|e| e == "nicehash"))
+    })
+}
+
+/// Builds a mining job from the parameters of a pool `job` notification
+///
+/// With `nicehash` enabled the top nonce byte of the blob is kept fixed while
+/// mining. If the pool did not say, the byte is kept when the blob arrives
+/// with it already set.
+///
+/// # Arguments
+/// * `params` - Job parameters from a `job` notification or login response
+/// * `nicehash` - Whether the pool enabled NiceHash on login, if it said
+///
+/// # Errors
+/// Returns `MinerError` if:
+/// - Required fields are missing
+/// - Hex decoding fails
+/// - Algorithm parsing fails
+pub(crate) fn parse_job(
+    params: &serde_json::Map<String, Value>,
+    nicehash: Option<bool>,
+) -> Result<MiningJob, MinerError> {
+    let field = |name: &str| {
+        params
+            .get(name)
+            .and_then(Value::as_str)
+            .ok_or_else(|| MinerError::ProtocolError(format!("Missing {}", name)))
+    };
+    let blob = hex::decode(field("blob")?)?;
+    let nicehash =
+        nicehash.unwrap_or_else(|| blob.get(NONCE_OFFSET + 3).is_some_and(|&byte| byte != 0));
+    let nonce_space = if nicehash {
+        NonceSpace::nicehash(&blob).unwrap_or_default()
+    } else {
+        NonceSpace::default()
+    };
+
+    Ok(MiningJob {
+        job_id: field("job_id")?.to_string(),
+        blob,
+        target: hex::decode(field("target")?)?,
+        algorithm: AlgorithmType::from_str(field("algo")?)
+            .map_err(|e| MinerError::ProtocolError(e))?,
+        seed_hash: params
+            .get("seed_hash")
+            .and_then(|s| s.as_str())
+            .map(str::to_string),
+        nonce_space,
+        template: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::difficulty::encode_target;
+
+    const WALLET: &str = "wallet";
+
+    fn config() -> PoolConfig {
+        PoolConfig {
+            url: "stratum+tcp://pool.example:3333".to_string(),
+            user: WALLET.to_string(),
+            password: "x".to_string(),
+            worker_id: "rig".to_string(),
+            self_select: None,
+            benchmark_file: std::env::temp_dir().join(format!(
+                "xmr_miner-no-benchmark-{}.json",
+                std::process::id()
+            )),
+            target_shares_per_minute: Some(2.0),
+            block_notify_url: None,
+            record_file: None,
+        }
+    }
+
+    fn client(config: PoolConfig) -> PoolClient {
+        let (job_sender, _) = crossbeam_channel::unbounded();
+        let (_, share_receiver) = crossbeam_channel::unbounded();
+        PoolClient::new(config, job_sender, share_receiver)
+    }
+
+    fn job(job_id: &str, difficulty: u64) -> MiningJob {
+        MiningJob {
+            job_id: job_id.to_string(),
+            blob: vec![0; 76],
+            target: hex::decode(encode_target(difficulty)).unwrap(),
+            algorithm: AlgorithmType::RandomX,
+            seed_hash: None,
+            nonce_space: NonceSpace::default(),
+            template: None,
+        }
+    }
+
+    fn share(job_id: &str) -> Share {
+        Share {
+            job_id: job_id.to_string(),
+            nonce: 1,
+            result: [0; 32],
+            extra_nonce: 0,
+            found: Instant::now(),
+        }
+    }
+
+    /// Difficulty requested by a `user+difficulty` login name
+    fn requested(login: &str) -> Option<u64> {
+        login.strip_prefix(WALLET)?.strip_prefix('+')?.parse().ok()
+    }
+
+    #[test]
+    fn login_says_whether_nicehash_is_enabled() {
+        assert_eq!(
+            login_nicehash(&json!({ "extensions": ["algo", "nicehash"] })),
+            Some(true)
+        );
+        assert_eq!(
+            login_nicehash(&json!({ "extensions": ["algo"] })),
+            Some(false)
+        );
+        assert_eq!(login_nicehash(&json!({ "nicehash": true })), Some(true));
+        assert_eq!(
+            login_nicehash(&json!({ "nicehash": false, "extensions": ["nicehash"] })),
+            Some(false)
+        );
+        assert_eq!(login_nicehash(&json!({ "status": "OK" })), None);
+    }
+
+    #[test]
+    fn nicehash_byte_heuristic_only_without_login_flag() {
+        let mut blob = vec![0u8; 76];
+        blob[NONCE_OFFSET + 3] = 0xab;
+        let params = |blob: &[u8]| {
+            json!({
+                "job_id": "a",
+                "blob": hex::encode(blob),
+                "target": "ffffffff",
+                "algo": "rx/0"
+            })
+            .as_object()
+            .unwrap()
+            .clone()
+        };
+        let nicehash = NonceSpace {
+            prefix: 0xab,
+            prefix_bits: 8,
+        };
+
+        let job = parse_job(&params(&blob), None).unwrap();
+        assert_eq!(job.nonce_space, nicehash);
+        let job = parse_job(&params(&blob), Some(true)).unwrap();
+        assert_eq!(job.nonce_space, nicehash);
+        // The pool said no: a set byte is just part of its blob
+        let job = parse_job(&params(&blob), Some(false)).unwrap();
+        assert_eq!(job.nonce_space, NonceSpace::default());
+
+        blob[NONCE_OFFSET + 3] = 0;
+        let job = parse_job(&params(&blob), None).unwrap();
+        assert_eq!(job.nonce_space, NonceSpace::default());
+        let job = parse_job(&params(&blob), Some(true)).unwrap();
+        assert_eq!(job.nonce_space.prefix_bits, 8);
+    }
+
+    #[test]
+    fn shares_count_their_own_job_difficulty() {
+        let client = client(config());
+        client.track_job(&job("a", 1_000), Some(100), None);
+        client.track_job(&job("b", 5_000), Some(100), None);
+
+        // Found before the difficulty change, submitted after it
+        client.count_share(&share("a"));
+        client.count_share(&share("b"));
+        client.count_share(&share("unknown"));
+        assert_eq!(client.estimate.lock().unwrap().difficulty_sum, 6_000);
+    }
+
+    #[test]
+    fn login_name_without_fixed_difficulty() {
+        let mut config = config();
+        config.target_shares_per_minute = None;
+        assert_eq!(client(config).login_name(), WALLET);
+
+        let mut config = self::config();
+        config.user = format!("{}+50000", WALLET);
+        assert_eq!(client(config.clone()).login_name(), config.user);
+
+        // Nothing measured or benchmarked yet
+        assert_eq!(client(self::config()).login_name(), WALLET);
+    }
+
+    #[test]
+    fn login_name_uses_benchmark_then_measured_hashrate() {
+        let mut config = config();
+        config.benchmark_file =
+            std::env::temp_dir().join(format!("xmr_miner-benchmark-{}.json", std::process::id()));
+        let mut results = BenchmarkResults::default();
+        results.record(AlgorithmType::RandomX, 1_000.0);
+        results.save(&config.benchmark_file).unwrap();
+        let client = client(config.clone());
+
+        // 1000 H/s at 2 shares per minute
+        assert_eq!(requested(&client.login_name()), Some(30_000));
+
+        // Shares found over 100 seconds: about 500 H/s
+        {
+            let mut estimate = client.estimate.lock().unwrap();
+            estimate.since = Instant::now() - Duration::from_secs(100);
+            estimate.difficulty_sum = 50_000;
+        }
+        let difficulty = requested(&client.login_name()).unwrap();
+        std::fs::remove_file(&config.benchmark_file).unwrap();
+        assert!((14_900..=15_000).contains(&difficulty), "{}", difficulty);
+    }
+
+    #[test]
+    fn block_candidates_use_the_full_network_target() {
+        let network = NetworkTarget {
+            height: Some(3_000_000),
+            difficulty: 3,
+        };
+        let target = full_target(network.difficulty);
+        assert!(network.solved_by(&target));
+
+        // One above the target in the least significant byte
+        let mut above = target;
+        above[0] += 1;
+        assert!(!network.solved_by(&above));
+
+        // Same top 64 bits as the target, larger below them
+        let mut low_bits_above = [0xff; 32];
+        low_bits_above[24..].copy_from_slice(&(u64::MAX / 3).to_le_bytes());
+        assert!(!network.solved_by(&low_bits_above));
+
+        let mut below = target;
+        below[31] -= 1;
+        assert!(network.solved_by(&below));
+    }
 }
@@ -0,0 +1,757 @@
+// src/network/proxy.rs
+//! Stratum proxy aggregating many downstream miners onto one pool session
+//!
+//! Works like xmrig-proxy in NiceHash mode: every downstream connection is
+//! given a slot, and the next byte of the 32-bit header nonce below any
+//! prefix the pool fixed is set to that slot in the jobs it receives.
+//! Downstream miners only iterate the remaining bits, so up to 256 miners
+//! share one upstream [`PoolClient`] session without ever repeating work;
+//! when the pool leaves fewer free nonce bits, fewer miners are accepted. The
+//! upstream connection is re-established on failure while downstream miners
+//! stay connected.
+//!
+//! Submitted shares are recomputed and checked for duplicates before they are
+//! forwarded, and a miner is only credited once the pool accepted its share.
+
+use crate::miner::algorithm::NONCE_OFFSET;
+use crate::miner::features::RandomXFlags;
+use crate::miner::scheduler::{MiningJob, NonceSpace, Share};
+use crate::network::pool::{PoolClient, PoolConfig};
+use crate::network::stratum::{
+    self, ShareHasher, StratumConnection, error_response, job_notification, result_response,
+};
+use crate::stats::ShareResult;
+use crate::utils::difficulty::{difficulty_from_target, meets_difficulty};
+use crate::utils::error::MinerError;
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::time;
+
+/// Number of nonce bits reserved for the downstream slot
+const SLOT_BITS: u32 = 8;
+
+/// Maximum number of downstream miners per upstream session
+const MAX_DOWNSTREAM: usize = 1 << SLOT_BITS;
+
+/// Delay before reconnecting to the pool after a failure
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Interval between per-downstream statistics reports
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Share counters for one downstream miner
+#[derive(Debug, Clone)]
+struct DownstreamStats {
+    /// Remote address of the miner
+    peer: SocketAddr,
+    /// Login (usually worker name) sent by the miner
+    login: String,
+    /// Time the miner connected
+    connected: Instant,
+    /// Shares accepted by the pool
+    accepted: u64,
+    /// Shares rejected by the proxy or the pool
+    rejected: u64,
+    /// Sum of the difficulty of all accepted shares
+    difficulty_sum: u64,
+    /// Forwarded shares awaiting the pool's verdict, keyed by (job, nonce),
+    /// with the difficulty to credit
+    pending: HashMap<(String, u32), u64>,
+}
+
+impl DownstreamStats {
+    /// Empty counters for a miner connecting now
+    fn new(peer: SocketAddr) -> Self {
+        DownstreamStats {
+            peer,
+            login: String::new(),
+            connected: Instant::now(),
+            accepted: 0,
+            rejected: 0,
+            difficulty_sum: 0,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Estimated hashrate from accepted share difficulty since connecting
+    fn hashrate(&self) -> f64 {
+        self.difficulty_sum as f64 / self.connected.elapsed().as_secs_f64().max(1.0)
+    }
+}
+
+/// Downstream slots and their statistics, keyed by slot
+type Downstreams = Arc<Mutex<HashMap<u8, DownstreamStats>>>;
+
+/// Stratum proxy multiplexing downstream miners onto one pool connection
+pub struct StratumProxy {
+    /// Address to listen on for downstream miners
+    bind: String,
+    /// Upstream pool configuration
+    pool: PoolConfig,
+    /// Connected downstream miners
+    downstreams: Downstreams,
+    /// Flags RandomX verifier instances are created with
+    randomx_flags: RandomXFlags,
+}
+
+impl StratumProxy {
+    /// Creates a new StratumProxy
+    ///
+    /// # Arguments
+    /// * `bind` - Address to listen on (e.g., "0.0.0.0:3333")
+    /// * `pool` - Upstream pool configuration
+    pub fn new(bind: String, pool: PoolConfig) -> Self {
+        StratumProxy {
+            bind,
+            pool,
+            downstreams: Arc::new(Mutex::new(HashMap::new())),
+            randomx_flags: RandomXFlags::detect(),
+        }
+    }
+
+    /// Verifies shares with RandomX instances using the given flags instead
+    /// of the fastest ones the CPU supports
+    ///
+    /// # Arguments
+    /// * `flags` - Flags selected with [`RandomXFlags::select`]
+    pub fn with_randomx_flags(mut self, flags: RandomXFlags) -> Self {
+        self.randomx_flags = flags;
+        self
+    }
+
+    /// Runs the proxy until the listener fails
+    ///
+    /// # Errors
+    /// Returns `MinerError` if binding or accepting fails
+    pub async fn run(self) -> Result<(), MinerError> {
+        let (job_sender, job_receiver) = crossbeam_channel::unbounded();
+        let (share_sender, share_receiver) = crossbeam_channel::unbounded();
+        let (job_watch, jobs) = watch::channel(None);
+
+        std::thread::spawn(move || {
+            for job in job_receiver {
+                job_watch.send_replace(job);
+            }
+        });
+
+        let (outcome_sender, outcomes) = crossbeam_channel::unbounded();
+        let downstreams = self.downstreams.clone();
+        std::thread::spawn(move || {
+            for (share, result) in outcomes {
+                credit_share(&downstreams, &share, result);
+            }
+        });
+
+        let pool = PoolClient::new(self.pool.clone(), job_sender, share_receiver)
+            .with_outcomes(outcome_sender)
+            .with_randomx_flags(self.randomx_flags);
+        tokio::spawn(async move {
+            loop {
+                match pool.connect().await {
+                    Ok(()) => match pool.run().await {
+                        Ok(()) => log::warn!("Upstream pool closed the connection"),
+                        Err(e) => log::error!("Upstream pool error: {}", e),
+                    },
+                    Err(e) => log::error!("Upstream pool connection failed: {}", e),
+                }
+                time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+
+        tokio::spawn(report_stats(self.downstreams.clone()));
+
+        let hasher = ShareHasher::new(self.randomx_flags);
+        let listener = TcpListener::bind(&self.bind).await?;
+        log::info!(
+            "Stratum proxy listening on {} (upstream {})",
+            self.bind,
+            self.pool.url
+        );
+
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let capacity = slot_capacity(jobs.borrow().as_ref());
+            let Some(slot) = self.reserve_slot(peer, capacity) else {
+                log::warn!("Refusing miner {}: all {} slots in use", peer, capacity);
+                continue;
+            };
+
+            let mut session = Downstream {
+                slot,
+                peer,
+                jobs: jobs.clone(),
+                share_sender: share_sender.clone(),
+                downstreams: self.downstreams.clone(),
+                hasher: hasher.clone(),
+                job: None,
+                submitted: HashSet::new(),
+            };
+            tokio::spawn(async move {
+                if let Err(e) = session.run(stream).await {
+                    log::warn!("Miner {} disconnected: {}", peer, e);
+                }
+                session
+                    .downstreams
+                    .lock()
+                    .expect("downstream lock poisoned")
+                    .remove(&slot);
+            });
+        }
+    }
+
+    /// Reserves the lowest free slot for a new downstream miner
+    ///
+    /// # Arguments
+    /// * `peer` - Remote address of the miner
+    /// * `capacity` - Number of slots the current pool job can be split into
+    fn reserve_slot(&self, peer: SocketAddr, capacity: usize) -> Option<u8> {
+        let mut downstreams = self.downstreams.lock().expect("downstream lock poisoned");
+        let slot = (0..=u8::MAX)
+            .take(capacity)
+            .find(|slot| !downstreams.contains_key(slot))?;
+        downstreams.insert(slot, DownstreamStats::new(peer));
+        Some(slot)
+    }
+}
+
+/// Number of downstream slots a pool job can be split into
+///
+/// Pools fixing part of the nonce (NiceHash) leave fewer free bits; all
+/// [`MAX_DOWNSTREAM`] slots are offered before the first job arrives.
+fn slot_capacity(job: Option<&MiningJob>) -> usize {
+    job.map_or(MAX_DOWNSTREAM, |job| {
+        1 << SLOT_BITS.min(32 - job.nonce_space.prefix_bits)
+    })
+}
+
+/// Part of a pool job's nonce space reserved for a downstream slot
+///
+/// # Returns
+/// `None` if the job leaves no room for the slot
+fn slot_space(job: &MiningJob, slot: u8) -> Option<NonceSpace> {
+    let bits = SLOT_BITS.min(32 - job.nonce_space.prefix_bits);
+    ((slot as usize) < slot_capacity(Some(job))).then(|| job.nonce_space.split(slot as u32, bits))
+}
+
+/// A single downstream miner connection
+struct Downstream {
+    /// Slot of this miner, fixed in the nonce below the pool's prefix
+    slot: u8,
+    /// Remote address of the miner
+    peer: SocketAddr,
+    /// Latest job received from the pool
+    jobs: watch::Receiver<Option<MiningJob>>,
+    /// Channel for forwarding shares to the pool client
+    share_sender: crossbeam_channel::Sender<Share>,
+    /// Connected downstream miners
+    downstreams: Downstreams,
+    /// Recomputes submitted shares
+    hasher: ShareHasher,
+    /// Job last sent to this miner
+    job: Option<MiningJob>,
+    /// (job, nonce) pairs already submitted for the current job
+    submitted: HashSet<(String, u32)>,
+}
+
+impl Downstream {
+    /// Serves the connection until the miner disconnects
+    async fn run(&mut self, stream: TcpStream) -> Result<(), MinerError> {
+        let mut conn = StratumConnection::accept(stream).await?;
+        let mut logged_in = false;
+
+        loop {
+            tokio::select! {
+                message = conn.recv() => {
+                    let Some(message) = message? else {
+                        log::info!("Miner {} disconnected", self.peer);
+                        return Ok(());
+                    };
+                    let id = message["id"].clone();
+
+                    match message["method"].as_str() {
+                        Some("login") => {
+                            logged_in = true;
+                            let login = message["params"]["login"].as_str().unwrap_or_default();
+                            log::info!("Miner {} logged in as {} (slot {})", self.peer, login, self.slot);
+                            self.update_stats(|stats| stats.login = login.to_string());
+
+                            let job = self.next_job();
+                            let result = json!({
+                                "id": format!("{:02x}", self.slot),
+                                "job": job,
+                                "extensions": ["nicehash"],
+                                "status": "OK"
+                            });
+                            conn.send(&result_response(&id, result)).await?;
+                            if let Some(job) = job {
+                                conn.send(&job_notification(job)).await?;
+                            }
+                        }
+                        Some("submit") => {
+                            let response = match self.handle_submit(&message["params"]).await {
+                                Ok(()) => result_response(&id, json!({ "status": "OK" })),
+                                Err(e) => {
+                                    self.update_stats(|stats| stats.rejected += 1);
+                                    error_response(&id, &e.to_string())
+                                }
+                            };
+                            conn.send(&response).await?;
+                        }
+                        Some("keepalived") => {
+                            conn.send(&result_response(&id, json!({ "status": "KEEPALIVED" }))).await?;
+                        }
+                        Some("subscribe") => {
+                            conn.send(&result_response(&id, json!({ "status": "OK" }))).await?;
+                        }
+                        _ => conn.send(&error_response(&id, "Unsupported method")).await?,
+                    }
+                }
+                changed = self.jobs.changed() => {
+                    changed.map_err(|_| MinerError::ChannelError("Upstream job feed closed".into()))?;
+                    if logged_in && let Some(job) = self.next_job() {
+                        conn.send(&job_notification(job)).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Builds job parameters for this miner from the latest pool job
+    ///
+    /// # Returns
+    /// * `Some(Value)` - Job parameters with the prefix of this miner's slot
+    ///   written into the nonce
+    /// * `None` - If no job has been received yet, the blob is too short or
+    ///   the pool's nonce space has no room for this miner's slot
+    fn next_job(&mut self) -> Option<Value> {
+        let job = self.jobs.borrow_and_update().clone()?;
+        if job.blob.len() < NONCE_OFFSET + 4 {
+            log::warn!("Pool job {} blob too short to split", job.job_id);
+            return None;
+        }
+        let Some(space) = slot_space(&job, self.slot) else {
+            log::warn!(
+                "Pool job {} leaves no nonce space for miner {} (slot {})",
+                job.job_id,
+                self.peer,
+                self.slot
+            );
+            return None;
+        };
+
+        let mut blob = job.blob.clone();
+        blob[NONCE_OFFSET..NONCE_OFFSET + 4]
+            .copy_from_slice(&(space.nonce(0) as u32).to_le_bytes());
+
+        let params = json!({
+            "job_id": job.job_id,
+            "blob": hex::encode(blob),
+            "target": hex::encode(&job.target),
+            "algo": job.algorithm.stratum_name(),
+            "seed_hash": job.seed_hash,
+        });
+
+        // Verdicts for the previous job may still arrive; older ones are dropped
+        let previous = self.job.as_ref().map(|job| job.job_id.clone());
+        self.update_stats(|stats| {
+            stats
+                .pending
+                .retain(|(job_id, _), _| Some(job_id) == previous.as_ref())
+        });
+        self.job = Some(job);
+        self.submitted.clear();
+        Some(params)
+    }
+
+    /// Validates a submitted share and forwards it to the pool
+    ///
+    /// The hash is recomputed from the job blob and the submitted nonce. The
+    /// share is credited to this miner once the pool accepts it.
+    ///
+    /// # Errors
+    /// Returns `MinerError` describing why the share was rejected
+    async fn handle_submit(&mut self, params: &Value) -> Result<(), MinerError> {
+        let job = self
+            .job
+            .as_ref()
+            .filter(|job| params["job_id"].as_str() == Some(job.job_id.as_str()))
+            .ok_or_else(|| MinerError::ProtocolError("Block expired".to_string()))?;
+
+        let (nonce, result) = stratum::parse_submit(params)?;
+        // A nonce outside the slot's space keeps its offset but not the prefix
+        if slot_space(job, self.slot).is_none_or(|space| space.nonce(nonce as u64) != nonce as u64)
+        {
+            return Err(MinerError::ProtocolError(
+                "Invalid nonce; is miner not compatible with NiceHash?".to_string(),
+            ));
+        }
+        if !self.submitted.insert((job.job_id.clone(), nonce)) {
+            return Err(MinerError::ProtocolError("Duplicate share".to_string()));
+        }
+
+        let hash = self
+            .hasher
+            .hash(
+                job.algorithm,
+                job.seed_hash.clone(),
+                job.blob.clone(),
+                nonce,
+            )
+            .await?;
+        if hash != result {
+            return Err(MinerError::ProtocolError("Invalid result".to_string()));
+        }
+
+        let difficulty = difficulty_from_target(&job.target)?;
+        if !meets_difficulty(&result, difficulty) {
+            return Err(MinerError::ProtocolError(
+                "Low difficulty share".to_string(),
+            ));
+        }
+
+        let job_id = job.job_id.clone();
+        self.update_stats(|stats| {
+            stats.pending.insert((job_id.clone(), nonce), difficulty);
+        });
+        self.share_sender.send(Share {
+            job_id,
+            nonce: nonce as u64,
+            result,
+            extra_nonce: 0,
+            found: Instant::now(),
+        })?;
+        Ok(())
+    }
+
+    /// Applies `update` to this miner's statistics
+    fn update_stats(&self, update: impl FnOnce(&mut DownstreamStats)) {
+        if let Some(stats) = self
+            .downstreams
+            .lock()
+            .expect("downstream lock poisoned")
+            .get_mut(&self.slot)
+        {
+            update(stats);
+        }
+    }
+}
+
+/// Credits the pool's verdict on a forwarded share to the miner that found it
+///
+/// The miner is the one the share is pending for; slots own disjoint nonce
+/// spaces, so at most one matches. Shares whose miner disconnected (or whose
+/// job is long gone) are ignored.
+fn credit_share(downstreams: &Downstreams, share: &Share, result: ShareResult) {
+    let key = (share.job_id.clone(), share.nonce as u32);
+    let mut downstreams = downstreams.lock().expect("downstream lock poisoned");
+    let Some((stats, difficulty)) = downstreams.values_mut().find_map(|stats| {
+        stats
+            .pending
+            .remove(&key)
+            .map(|difficulty| (stats, difficulty))
+    }) else {
+        return;
+    };
+    match result {
+        ShareResult::Accepted => {
+            stats.accepted += 1;
+            stats.difficulty_sum += difficulty;
+        }
+        ShareResult::Rejected(_) => stats.rejected += 1,
+        _ => {}
+    }
+}
+
+/// Periodically logs hashrate and share counts for every downstream miner
+async fn report_stats(downstreams: Downstreams) {
+    let mut interval = time::interval(REPORT_INTERVAL);
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+        let mut miners: Vec<(u8, DownstreamStats)> = downstreams
+            .lock()
+            .expect("downstream lock poisoned")
+            .iter()
+            .map(|(slot, stats)| (*slot, stats.clone()))
+            .collect();
+        miners.sort_by_key(|(slot, _)| *slot);
+
+        let total: f64 = miners.iter().map(|(_, stats)| stats.hashrate()).sum();
+        log::info!("Proxy: {} miners, {:.2} H/s estimated", miners.len(), total);
+        for (slot, stats) in miners {
+            log::info!(
+                "  [{:02x}] {} ({}): {:.2} H/s, {} accepted, {} rejected",
+                slot,
+                stats.login,
+                stats.peer,
+                stats.hashrate(),
+                stats.accepted,
+                stats.rejected
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::miner::algorithm;
+    use crate::types::{AlgorithmType, RejectReason};
+
+    const PEER: &str = "192.0.2.1:4000";
+
+    fn proxy() -> StratumProxy {
+        let pool = serde_json::from_value(json!({
+            "url": "stratum+tcp://pool.example:3333",
+            "user": "wallet",
+            "password": "x",
+            "worker_id": "proxy"
+        }))
+        .unwrap();
+        StratumProxy::new("127.0.0.1:0".to_string(), pool)
+    }
+
+    /// CryptoNight v7 pool job with a difficulty 1 target
+    fn job(nonce_space: NonceSpace) -> MiningJob {
+        MiningJob {
+            job_id: "job-1".to_string(),
+            blob: vec![0x07; 76],
+            target: vec![0xff; 4],
+            algorithm: AlgorithmType::CryptoNightV7,
+            seed_hash: None,
+            nonce_space,
+            template: None,
+        }
+    }
+
+    /// Downstream session for `slot` with `job` as the latest pool job
+    fn downstream(
+        slot: u8,
+        job: MiningJob,
+    ) -> (
+        Downstream,
+        watch::Sender<Option<MiningJob>>,
+        crossbeam_channel::Receiver<Share>,
+    ) {
+        let (job_watch, jobs) = watch::channel(Some(job));
+        let (share_sender, shares) = crossbeam_channel::unbounded();
+        let peer = PEER.parse().unwrap();
+        let downstreams = Arc::new(Mutex::new(HashMap::from([(
+            slot,
+            DownstreamStats::new(peer),
+        )])));
+        let session = Downstream {
+            slot,
+            peer,
+            jobs,
+            share_sender,
+            downstreams,
+            hasher: ShareHasher::new(RandomXFlags::detect()),
+            job: None,
+            submitted: HashSet::new(),
+        };
+        (session, job_watch, shares)
+    }
+
+    fn submit(job: &MiningJob, nonce: u32, result: &[u8; 32]) -> Value {
+        json!({
+            "job_id": job.job_id,
+            "nonce": hex::encode(nonce.to_le_bytes()),
+            "result": hex::encode(result)
+        })
+    }
+
+    fn hash(job: &MiningJob, nonce: u32) -> [u8; 32] {
+        algorithm::create_verifier(job.algorithm, &[0u8; 32], RandomXFlags::detect())
+            .hash(&job.blob, nonce as u64)
+            .unwrap()
+    }
+
+    #[test]
+    fn slots_are_reused_and_limited_by_capacity() {
+        let proxy = proxy();
+        let peer = PEER.parse().unwrap();
+        assert_eq!(proxy.reserve_slot(peer, MAX_DOWNSTREAM), Some(0));
+        assert_eq!(proxy.reserve_slot(peer, MAX_DOWNSTREAM), Some(1));
+        proxy.downstreams.lock().unwrap().remove(&0);
+        assert_eq!(proxy.reserve_slot(peer, MAX_DOWNSTREAM), Some(0));
+        assert_eq!(proxy.reserve_slot(peer, 2), None);
+        assert_eq!(proxy.reserve_slot(peer, 3), Some(2));
+    }
+
+    #[test]
+    fn capacity_follows_pool_nonce_space() {
+        let nicehash = NonceSpace {
+            prefix: 0xab,
+            prefix_bits: 8,
+        };
+        let narrow = NonceSpace {
+            prefix: 0x00ab_cdef,
+            prefix_bits: 28,
+        };
+        let full = NonceSpace {
+            prefix: 0x1234_5678,
+            prefix_bits: 32,
+        };
+        assert_eq!(slot_capacity(None), 256);
+        assert_eq!(slot_capacity(Some(&job(NonceSpace::default()))), 256);
+        assert_eq!(slot_capacity(Some(&job(nicehash))), 256);
+        assert_eq!(slot_capacity(Some(&job(narrow))), 16);
+        assert_eq!(slot_capacity(Some(&job(full))), 1);
+
+        assert_eq!(slot_space(&job(narrow), 15).unwrap().prefix, 0x0abc_deff);
+        assert!(slot_space(&job(narrow), 16).is_none());
+    }
+
+    #[test]
+    fn slot_prefix_is_written_below_pool_prefix() {
+        let nicehash = NonceSpace {
+            prefix: 0xab,
+            prefix_bits: 8,
+        };
+        let (mut session, _job_watch, _shares) = downstream(0x07, job(nicehash));
+        let params = session.next_job().unwrap();
+        let blob = hex::decode(params["blob"].as_str().unwrap()).unwrap();
+        // The pool's NiceHash byte is kept, the slot fills the next byte
+        assert_eq!(&blob[NONCE_OFFSET..NONCE_OFFSET + 4], &[0, 0, 0x07, 0xab]);
+
+        let (mut session, _job_watch, _shares) = downstream(0x07, job(NonceSpace::default()));
+        let params = session.next_job().unwrap();
+        let blob = hex::decode(params["blob"].as_str().unwrap()).unwrap();
+        assert_eq!(&blob[NONCE_OFFSET..NONCE_OFFSET + 4], &[0, 0, 0, 0x07]);
+    }
+
+    #[test]
+    fn miner_without_room_gets_no_job() {
+        let narrow = NonceSpace {
+            prefix: 0x00ab_cdef,
+            prefix_bits: 28,
+        };
+        let (mut session, _job_watch, _shares) = downstream(16, job(narrow));
+        assert!(session.next_job().is_none());
+    }
+
+    #[tokio::test]
+    async fn submitted_shares_are_verified() {
+        let nicehash = NonceSpace {
+            prefix: 0xab,
+            prefix_bits: 8,
+        };
+        let job = job(nicehash);
+        let (mut session, _job_watch, shares) = downstream(0x07, job.clone());
+        session.next_job().unwrap();
+
+        let nonce = 0xab07_0042;
+        let result = hash(&job, nonce);
+        let mut stale = job.clone();
+        stale.job_id = "job-0".to_string();
+        let err = session
+            .handle_submit(&submit(&stale, nonce, &result))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Block expired"), "{}", err);
+
+        // Another slot's nonce, and one that dropped the pool prefix
+        for foreign in [0xab08_0042, 0x0007_0042] {
+            let err = session
+                .handle_submit(&submit(&job, foreign, &hash(&job, foreign)))
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("Invalid nonce"), "{}", err);
+        }
+
+        let err = session
+            .handle_submit(&submit(&job, nonce, &[0u8; 32]))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid result"), "{}", err);
+        assert!(shares.is_empty());
+
+        let nonce = nonce + 1;
+        let result = hash(&job, nonce);
+        session
+            .handle_submit(&submit(&job, nonce, &result))
+            .await
+            .unwrap();
+        let share = shares.try_recv().unwrap();
+        assert_eq!(share.job_id, job.job_id);
+        assert_eq!(share.nonce, nonce as u64);
+        assert_eq!(share.result, result);
+        let stats = session.downstreams.lock().unwrap()[&0x07].clone();
+        assert_eq!(stats.pending.get(&(job.job_id.clone(), nonce)), Some(&1));
+
+        let err = session
+            .handle_submit(&submit(&job, nonce, &result))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Duplicate share"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn low_difficulty_share_is_rejected() {
+        let mut job = job(NonceSpace::default());
+        job.target = vec![0x01, 0, 0, 0];
+        let (mut session, _job_watch, shares) = downstream(0x07, job.clone());
+        session.next_job().unwrap();
+
+        let nonce = 0x0700_0001;
+        let err = session
+            .handle_submit(&submit(&job, nonce, &hash(&job, nonce)))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Low difficulty"), "{}", err);
+        assert!(shares.is_empty());
+    }
+
+    #[test]
+    fn pool_verdicts_credit_the_finding_miner() {
+        let peer = PEER.parse().unwrap();
+        let downstreams: Downstreams = Arc::new(Mutex::new(HashMap::new()));
+        {
+            let mut downstreams = downstreams.lock().unwrap();
+            for slot in [1, 2] {
+                let mut stats = DownstreamStats::new(peer);
+                stats
+                    .pending
+                    .insert(("job-1".to_string(), (slot as u32) << 16), 500);
+                stats
+                    .pending
+                    .insert(("job-1".to_string(), (slot as u32) << 16 | 1), 700);
+                downstreams.insert(slot, stats);
+            }
+        }
+        let share = |nonce: u32| Share {
+            job_id: "job-1".to_string(),
+            nonce: nonce as u64,
+            result: [0u8; 32],
+            extra_nonce: 0,
+            found: Instant::now(),
+        };
+
+        credit_share(&downstreams, &share(2 << 16), ShareResult::Accepted);
+        credit_share(
+            &downstreams,
+            &share(2 << 16 | 1),
+            ShareResult::Rejected(RejectReason::LowDifficulty),
+        );
+        // Already credited, and never forwarded
+        credit_share(&downstreams, &share(2 << 16), ShareResult::Accepted);
+        credit_share(&downstreams, &share(3 << 16), ShareResult::Accepted);
+
+        let downstreams = downstreams.lock().unwrap();
+        let credited = &downstreams[&2];
+        assert_eq!(credited.accepted, 1);
+        assert_eq!(credited.rejected, 1);
+        assert_eq!(credited.difficulty_sum, 500);
+        assert!(credited.pending.is_empty());
+        let other = &downstreams[&1];
+        assert_eq!(other.accepted + other.rejected, 0);
+        assert_eq!(other.pending.len(), 2);
+    }
+}
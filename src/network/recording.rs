@@ -0,0 +1,418 @@
+// src/network/recording.rs
+//! Pool session recording and replay
+//!
+//! When `record_file` is set, every Stratum message exchanged with the pool is
+//! appended to a JSON-lines file together with a timestamp and its direction.
+//! A [`Recording`] loaded from such a file yields the jobs the pool sent and
+//! the shares that were submitted for them, so pool-specific problems can be
+//! reproduced and share validity regression-tested without network access.
+//! The login password is redacted before a message is written.
+
+use crate::miner::ShareVerifier;
+use crate::miner::scheduler::{MiningJob, Share};
+use crate::network::pool::{login_nicehash, parse_job};
+use crate::network::stratum;
+use crate::utils::error::MinerError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Written in place of the login password
+const REDACTED: &str = "<redacted>";
+
+/// Direction of a recorded message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Received from the pool
+    In,
+    /// Sent to the pool
+    Out,
+}
+
+/// One line of a session recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedMessage {
+    /// Unix time in milliseconds when the message was sent or received
+    pub time: u64,
+    /// Whether the message was received or sent
+    pub direction: Direction,
+    /// The Stratum message (kept as a string if it was not valid JSON)
+    pub message: Value,
+}
+
+/// Appends pool messages to a recording file
+pub struct SessionRecorder {
+    /// Recording file, opened for appending
+    file: File,
+}
+
+impl SessionRecorder {
+    /// Opens (or creates) a recording file for appending
+    ///
+    /// # Errors
+    /// Returns `MinerError::IoError` if the file cannot be opened
+    pub fn open(path: &Path) -> Result<Self, MinerError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(SessionRecorder { file })
+    }
+
+    /// Records one message
+    ///
+    /// The `pass` of a login request is replaced before writing. Write
+    /// failures are logged rather than returned so recording never interrupts
+    /// mining.
+    ///
+    /// # Arguments
+    /// * `direction` - Whether the message was received or sent
+    /// * `message` - Raw message text
+    pub fn record(&mut self, direction: Direction, message: &str) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let mut message: Value = serde_json::from_str(message).unwrap_or_else(|_| message.into());
+        if let Some(pass) = message
+            .get_mut("params")
+            .and_then(|params| params.get_mut("pass"))
+        {
+            *pass = REDACTED.into();
+        }
+        let entry = RecordedMessage {
+            time,
+            direction,
+            message,
+        };
+
+        let line = serde_json::to_string(&entry).expect("recorded message serializes");
+        if let Err(e) = writeln!(self.file, "{}", line) {
+            log::error!("Failed to record pool message: {}", e);
+        }
+    }
+}
+
+/// A submitted share together with what the pool said about it
+#[derive(Debug, Clone)]
+pub struct RecordedShare {
+    /// The submitted share
+    pub share: Share,
+    /// Job the share was submitted for, if it was recorded
+    pub job: Option<MiningJob>,
+    /// `Some(true)` if the pool accepted it, `Some(false)` if it rejected it,
+    /// `None` if no response was recorded
+    pub accepted: Option<bool>,
+}
+
+/// Outcome of re-verifying the shares of a recording
+#[derive(Debug, Clone, Default)]
+pub struct ShareCheck {
+    /// Shares that reproduce and meet their job target
+    pub valid: u64,
+    /// Shares that do not reproduce or miss their job target
+    pub invalid: u64,
+    /// Shares whose job is missing from the recording
+    pub unknown_job: u64,
+    /// Shares whose local verdict differs from the pool's response
+    pub pool_disagrees: u64,
+}
+
+/// A loaded session recording
+#[derive(Debug, Clone)]
+pub struct Recording {
+    /// All recorded messages, in file order
+    messages: Vec<RecordedMessage>,
+}
+
+impl Recording {
+    /// Loads a recording written by [`SessionRecorder`]
+    ///
+    /// # Errors
+    /// Returns `MinerError` if the file cannot be read or a line is not a
+    /// recorded message
+    pub fn load(path: &Path) -> Result<Self, MinerError> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut messages = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            messages.push(serde_json::from_str(&line).map_err(|e| {
+                MinerError::InputError(format!("{} line {}: {}", path.display(), index + 1, e))
+            })?);
+        }
+        Ok(Recording { messages })
+    }
+
+    /// Returns the recorded messages
+    pub fn messages(&self) -> &[RecordedMessage] {
+        &self.messages
+    }
+
+    /// Returns the jobs the pool sent, in order, with the time each arrived
+    pub fn jobs(&self) -> Vec<(u64, MiningJob)> {
+        let mut nicehash = None;
+        self.messages
+            .iter()
+            .filter(|entry| entry.direction == Direction::In)
+            .filter_map(|entry| Some((entry.time, recorded_job(&entry.message, &mut nicehash)?)))
+            .collect()
+    }
+
+    /// Returns the submitted shares with their job and the pool's verdict
+    pub fn shares(&self) -> Vec<RecordedShare> {
+        let mut nicehash = None;
+        let mut jobs: Vec<MiningJob> = Vec::new();
+        let mut shares: Vec<(Option<u64>, RecordedShare)> = Vec::new();
+
+        for entry in &self.messages {
+            let message = &entry.message;
+            match entry.direction {
+                Direction::In => {
+                    if let Some(job) = recorded_job(message, &mut nicehash) {
+                        jobs.push(job);
+                    } else if let Some(id) = message["id"].as_u64()
+                        && let Some((_, share)) =
+                            shares.iter_mut().rev().find(|(sent_id, share)| {
+                                *sent_id == Some(id) && share.accepted.is_none()
+                            })
+                    {
+                        share.accepted = Some(message["error"].is_null());
+                    }
+                }
+                Direction::Out if message["method"] == "submit" => {
+                    match recorded_share(&message["params"]) {
+                        Ok(share) => {
+                            let job = jobs.iter().rev().find(|job| job.job_id == share.job_id);
+                            shares.push((
+                                message["id"].as_u64(),
+                                RecordedShare {
+                                    job: job.cloned(),
+                                    share,
+                                    accepted: None,
+                                },
+                            ));
+                        }
+                        Err(e) => log::warn!("Skipping unparsable recorded submit: {}", e),
+                    }
+                }
+                Direction::Out => {}
+            }
+        }
+        shares.into_iter().map(|(_, share)| share).collect()
+    }
+
+    /// Recomputes every recorded share against its recorded job
+    ///
+    /// # Arguments
+    /// * `verifier` - Verifier used to recompute the shares
+    pub fn check_shares(&self, verifier: &ShareVerifier) -> ShareCheck {
+        let mut check = ShareCheck::default();
+
+        for recorded in self.shares() {
+            let share = &recorded.share;
+            let Some(job) = &recorded.job else {
+                log::warn!("Share for job {} has no recorded job", share.job_id);
+                check.unknown_job += 1;
+                continue;
+            };
+
+            let valid = verifier.verify(job, &job.blob, share);
+            if valid {
+                check.valid += 1;
+            } else {
+                check.invalid += 1;
+            }
+            if recorded.accepted.is_some_and(|accepted| accepted != valid) {
+                log::warn!(
+                    "Share for job {} nonce {:08x}: verified {} locally but the pool {} it",
+                    share.job_id,
+                    share.nonce,
+                    if valid { "valid" } else { "invalid" },
+                    if valid { "rejected" } else { "accepted" }
+                );
+                check.pool_disagrees += 1;
+            }
+        }
+        check
+    }
+}
+
+/// Extracts the job from a received message, if it carries one
+///
+/// Tracks whether login responses enabled NiceHash. Self-select
+/// jobs are skipped as their blob came from a node that is not part of the
+/// recording.
+fn recorded_job(message: &Value, nicehash: &mut Option<bool>) -> Option<MiningJob> {
+    let params = if message["method"] == "job" {
+        &message["params"]
+    } else if message["result"]["job"].is_object() {
+        *nicehash = login_nicehash(&message["result"]);
+        &message["result"]["job"]
+    } else {
+        return None;
+    };
+
+    let params = params.as_object()?;
+    if params.contains_key("pool_wallet") {
+        log::debug!(
+            "Skipping self-select job {}",
+            params
+                .get("job_id")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+        );
+        return None;
+    }
+    parse_job(params, *nicehash)
+        .map_err(|e| log::warn!("Skipping unparsable recorded job: {}", e))
+        .ok()
+}
+
+/// Rebuilds a share from the parameters of a recorded `submit`
+fn recorded_share(params: &Value) -> Result<Share, MinerError> {
+    let job_id = params["job_id"]
+        .as_str()
+        .ok_or_else(|| MinerError::ProtocolError("Missing job_id".to_string()))?;
+    let (nonce, result) = stratum::parse_submit(params)?;
+
+    Ok(Share {
+        job_id: job_id.to_string(),
+        nonce: nonce as u64,
+        result,
+        extra_nonce: 0,
+        found: Instant::now(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::miner::algorithm;
+    use crate::miner::features::RandomXFlags;
+    use crate::types::AlgorithmType;
+    use serde_json::json;
+
+    /// Hex of the 76-byte CryptoNight v7 blob of `job_id`
+    fn blob(job_id: &str) -> String {
+        hex::encode([job_id.as_bytes()[4]; 76])
+    }
+
+    fn job_params(job_id: &str) -> Value {
+        json!({ "job_id": job_id, "blob": blob(job_id), "target": "ffffffff", "algo": "cn/1" })
+    }
+
+    fn submit(id: u64, job_id: &str, nonce: u32, result: [u8; 32]) -> String {
+        json!({
+            "id": id,
+            "method": "submit",
+            "params": {
+                "id": "session",
+                "job_id": job_id,
+                "nonce": hex::encode(nonce.to_le_bytes()),
+                "result": hex::encode(result)
+            }
+        })
+        .to_string()
+    }
+
+    fn hash(job_id: &str, nonce: u32) -> [u8; 32] {
+        algorithm::create_verifier(
+            AlgorithmType::CryptoNightV7,
+            &[0u8; 32],
+            RandomXFlags::detect(),
+        )
+        .hash(&hex::decode(blob(job_id)).unwrap(), nonce as u64)
+        .unwrap()
+    }
+
+    #[test]
+    fn recorded_session_replays_and_rechecks_shares() {
+        let path =
+            std::env::temp_dir().join(format!("xmr_miner-recording-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut recorder = SessionRecorder::open(&path).unwrap();
+        let login = json!({
+            "id": 1,
+            "method": "login",
+            "params": { "login": "wallet", "pass": "secret", "agent": "xmr_miner-rs" }
+        });
+        recorder.record(Direction::Out, &login.to_string());
+        let response = json!({
+            "id": 1,
+            "error": null,
+            "result": { "id": "session", "job": job_params("job-1"), "status": "OK" }
+        });
+        recorder.record(Direction::In, &response.to_string());
+
+        // Valid and accepted, invalid but accepted, and a job that was never sent
+        recorder.record(Direction::Out, &submit(2, "job-1", 1, hash("job-1", 1)));
+        recorder.record(
+            Direction::In,
+            r#"{"id":2,"error":null,"result":{"status":"OK"}}"#,
+        );
+        recorder.record(Direction::Out, &submit(3, "job-1", 2, [0u8; 32]));
+        recorder.record(
+            Direction::In,
+            r#"{"id":3,"error":null,"result":{"status":"OK"}}"#,
+        );
+        recorder.record(Direction::Out, &submit(4, "job-0", 3, hash("job-1", 3)));
+        recorder.record(
+            Direction::In,
+            r#"{"id":4,"error":{"code":-1,"message":"Block expired"}}"#,
+        );
+
+        // A notified job whose share never got an answer
+        let notify = json!({ "jsonrpc": "2.0", "method": "job", "params": job_params("job-2") });
+        recorder.record(Direction::In, &notify.to_string());
+        recorder.record(Direction::Out, &submit(5, "job-2", 4, hash("job-2", 4)));
+        recorder.record(Direction::In, "not json");
+        drop(recorder);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(!text.contains("secret"));
+        let recording = Recording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let messages = recording.messages();
+        assert_eq!(messages.len(), 11);
+        assert_eq!(messages[0].direction, Direction::Out);
+        assert_eq!(messages[0].message["params"]["pass"], REDACTED);
+        assert_eq!(messages[0].message["params"]["login"], "wallet");
+        assert_eq!(messages[10].message, "not json");
+
+        let jobs: Vec<_> = recording
+            .jobs()
+            .into_iter()
+            .map(|(_, job)| job.job_id)
+            .collect();
+        assert_eq!(jobs, ["job-1", "job-2"]);
+
+        let shares = recording.shares();
+        let verdicts: Vec<_> = shares.iter().map(|share| share.accepted).collect();
+        assert_eq!(verdicts, [Some(true), Some(true), Some(false), None]);
+        assert!(shares[2].job.is_none());
+        assert_eq!(shares[3].share.nonce, 4);
+
+        let check = recording.check_shares(&ShareVerifier::new());
+        assert_eq!(check.valid, 2);
+        assert_eq!(check.invalid, 1);
+        assert_eq!(check.unknown_job, 1);
+        assert_eq!(check.pool_disagrees, 1);
+    }
+
+    #[test]
+    fn malformed_line_is_reported() {
+        let path = std::env::temp_dir().join(format!(
+            "xmr_miner-bad-recording-{}.jsonl",
+            std::process::id()
+        ));
+        std::fs::write(&path, "\n{\"direction\":\"in\"}\n").unwrap();
+        let err = Recording::load(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(err.to_string().contains("line 2"), "{}", err);
+    }
+}
@@ -0,0 +1,447 @@
+// src/network/server.rs
+//! Node-backed solo mining Stratum server
+//!
+//! Lets other rigs on the LAN (including third-party miners) solo mine through
+//! this miner's node connection. Every connection receives the current block
+//! template with its own extra nonce, so no two miners ever hash the same blob.
+//! Submitted shares are recomputed before they are accepted, and shares
+//! meeting the network difficulty are forwarded as blocks to the
+//! [`NodeClient`] through the regular share channel.
+//!
+//! [`NodeClient`]: crate::network::NodeClient
+
+use crate::miner::features::RandomXFlags;
+use crate::miner::scheduler::{MiningJob, Share};
+use crate::network::stratum::{
+    self, ServerConfig, ShareHasher, StratumConnection, error_response, job_notification,
+    result_response,
+};
+use crate::utils::difficulty::{encode_target, meets_difficulty, meets_target};
+use crate::utils::error::MinerError;
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::time::Instant;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+
+/// First extra nonce round handed to downstream connections
+///
+/// The local scheduler counts its rounds up from zero, so downstream miners
+/// use the upper half of the round space and can never collide with it.
+const SESSION_ROUND_BASE: u32 = 0x8000_0000;
+
+/// Stratum server handing out node block templates to downstream miners
+pub struct SoloServer {
+    /// Listener configuration
+    config: ServerConfig,
+    /// Latest job produced by the node client
+    jobs: watch::Receiver<Option<MiningJob>>,
+    /// Channel for forwarding solved blocks to the node client
+    share_sender: crossbeam_channel::Sender<Share>,
+    /// Recomputes submitted shares for all connections
+    hasher: ShareHasher,
+}
+
+impl SoloServer {
+    /// Creates a new SoloServer
+    ///
+    /// # Arguments
+    /// * `config` - Listener configuration
+    /// * `jobs` - Watch channel carrying the node client's latest job
+    /// * `share_sender` - Channel for forwarding solved blocks to the node client
+    pub fn new(
+        config: ServerConfig,
+        jobs: watch::Receiver<Option<MiningJob>>,
+        share_sender: crossbeam_channel::Sender<Share>,
+    ) -> Self {
+        SoloServer {
+            config,
+            jobs,
+            share_sender,
+            hasher: ShareHasher::default(),
+        }
+    }
+
+    /// Recomputes submitted shares with RandomX instances using the given
+    /// flags instead of the fastest ones the CPU supports
+    ///
+    /// # Arguments
+    /// * `flags` - Flags selected with [`RandomXFlags::select`]
+    pub fn with_randomx_flags(mut self, flags: RandomXFlags) -> Self {
+        self.hasher = ShareHasher::new(flags);
+        self
+    }
+
+    /// Accepts downstream miners until the listener fails
+    ///
+    /// # Errors
+    /// Returns `MinerError` if binding or accepting fails
+    pub async fn run(self) -> Result<(), MinerError> {
+        let listener = TcpListener::bind(&self.config.bind).await?;
+        log::info!("Solo Stratum server listening on {}", self.config.bind);
+
+        let mut next_round = SESSION_ROUND_BASE;
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let session = Session {
+                id: format!("{:08x}", next_round),
+                round: next_round,
+                peer,
+                share_difficulty: self.config.share_difficulty,
+                jobs: self.jobs.clone(),
+                share_sender: self.share_sender.clone(),
+                hasher: self.hasher.clone(),
+                job: None,
+                submitted: HashSet::new(),
+            };
+            next_round = next_session_round(next_round);
+
+            tokio::spawn(async move {
+                if let Err(e) = session.run(stream).await {
+                    log::warn!("Miner {} disconnected: {}", peer, e);
+                }
+            });
+        }
+    }
+}
+
+/// Extra nonce round for the connection after the one given `round`
+///
+/// Wraps back to [`SESSION_ROUND_BASE`] instead of into the scheduler's rounds.
+fn next_session_round(round: u32) -> u32 {
+    round.wrapping_add(1).max(SESSION_ROUND_BASE)
+}
+
+/// State of a single downstream miner connection
+struct Session {
+    /// Session identifier returned on login
+    id: String,
+    /// Extra nonce round reserved for this connection
+    round: u32,
+    /// Remote address of the miner
+    peer: SocketAddr,
+    /// Share difficulty requested from this miner
+    share_difficulty: u64,
+    /// Latest job produced by the node client
+    jobs: watch::Receiver<Option<MiningJob>>,
+    /// Channel for forwarding solved blocks to the node client
+    share_sender: crossbeam_channel::Sender<Share>,
+    /// Recomputes submitted shares
+    hasher: ShareHasher,
+    /// Job last sent to this miner
+    job: Option<MiningJob>,
+    /// (job, nonce) pairs already submitted for the current job
+    submitted: HashSet<(String, u32)>,
+}
+
+impl Session {
+    /// Serves the connection until the miner disconnects
+    async fn run(mut self, stream: TcpStream) -> Result<(), MinerError> {
+        let mut conn = StratumConnection::accept(stream).await?;
+        let mut logged_in = false;
+
+        loop {
+            tokio::select! {
+                message = conn.recv() => {
+                    let Some(message) = message? else {
+                        log::info!("Miner {} disconnected", self.peer);
+                        return Ok(());
+                    };
+                    let id = message["id"].clone();
+
+                    match message["method"].as_str() {
+                        Some("login") => {
+                            logged_in = true;
+                            log::info!("Miner {} logged in as {}", self.peer, message["params"]["login"]);
+                            let job = self.next_job()?;
+                            let result = json!({ "id": self.id, "job": job, "status": "OK" });
+                            conn.send(&result_response(&id, result)).await?;
+                            if let Some(job) = job {
+                                conn.send(&job_notification(job)).await?;
+                            }
+                        }
+                        Some("submit") => {
+                            let response = match self.handle_submit(&message["params"]).await {
+                                Ok(()) => result_response(&id, json!({ "status": "OK" })),
+                                Err(e) => error_response(&id, &e.to_string()),
+                            };
+                            conn.send(&response).await?;
+                        }
+                        Some("keepalived") => {
+                            conn.send(&result_response(&id, json!({ "status": "KEEPALIVED" }))).await?;
+                        }
+                        Some("subscribe") => {
+                            conn.send(&result_response(&id, json!({ "status": "OK" }))).await?;
+                        }
+                        _ => conn.send(&error_response(&id, "Unsupported method")).await?,
+                    }
+                }
+                changed = self.jobs.changed() => {
+                    changed.map_err(|_| MinerError::ChannelError("Node job feed closed".into()))?;
+                    if logged_in && let Some(job) = self.next_job()? {
+                        conn.send(&job_notification(job)).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Builds job parameters for this connection from the latest node job
+    ///
+    /// # Returns
+    /// * `Ok(Some(Value))` - Job parameters with this connection's extra nonce
+    /// * `Ok(None)` - If no template is available (shares for the previous
+    ///   job are then rejected as expired)
+    /// * `Err(MinerError)` - If the hashing blob cannot be built
+    fn next_job(&mut self) -> Result<Option<Value>, MinerError> {
+        let Some(job) = self.jobs.borrow_and_update().clone() else {
+            self.job = None;
+            return Ok(None);
+        };
+        let template = job
+            .template
+            .as_ref()
+            .ok_or_else(|| MinerError::ProtocolError("Node job without template".to_string()))?;
+
+        let blob = template.hashing_blob(template.extra_nonce(self.round))?;
+        let difficulty = self.share_difficulty.min(template.difficulty);
+        let params = json!({
+            "job_id": job.job_id,
+            "blob": hex::encode(blob),
+            "target": encode_target(difficulty),
+            "algo": job.algorithm.stratum_name(),
+            "height": template.height,
+            "seed_hash": template.seed_hash,
+        });
+
+        self.job = Some(job);
+        self.submitted.clear();
+        Ok(Some(params))
+    }
+
+    /// Validates a submitted share and forwards it when it solves the block
+    ///
+    /// The hash is recomputed from this connection's blob and the submitted
+    /// nonce; the reported result is only trusted if it matches.
+    ///
+    /// # Errors
+    /// Returns `MinerError` describing why the share was rejected
+    async fn handle_submit(&mut self, params: &Value) -> Result<(), MinerError> {
+        let job = self
+            .job
+            .as_ref()
+            .filter(|job| params["job_id"].as_str() == Some(job.job_id.as_str()))
+            .ok_or_else(|| MinerError::ProtocolError("Block expired".to_string()))?;
+        let template = job
+            .template
+            .as_ref()
+            .ok_or_else(|| MinerError::ProtocolError("Node job without template".to_string()))?;
+
+        let (nonce, result) = stratum::parse_submit(params)?;
+        if !self.submitted.insert((job.job_id.clone(), nonce)) {
+            return Err(MinerError::ProtocolError("Duplicate share".to_string()));
+        }
+
+        let extra_nonce = template.extra_nonce(self.round);
+        let blob = template.hashing_blob(extra_nonce)?;
+        let hash = self
+            .hasher
+            .hash(job.algorithm, job.seed_hash.clone(), blob, nonce)
+            .await?;
+        if hash != result {
+            return Err(MinerError::ProtocolError("Invalid result".to_string()));
+        }
+
+        let difficulty = self.share_difficulty.min(template.difficulty);
+        if !meets_difficulty(&result, difficulty) {
+            return Err(MinerError::ProtocolError(
+                "Low difficulty share".to_string(),
+            ));
+        }
+
+        if meets_target(&result, &template.target()) {
+            log::info!(
+                "Block candidate at height {} from miner {}",
+                template.height,
+                self.peer
+            );
+            self.share_sender.send(Share {
+                job_id: job.job_id.clone(),
+                nonce: nonce as u64,
+                result,
+                extra_nonce,
+                found: Instant::now(),
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::miner::algorithm;
+    use crate::miner::scheduler::NonceSpace;
+    use crate::network::template::{BlockTemplate, tests::template};
+    use crate::types::AlgorithmType;
+    use std::sync::Arc;
+
+    /// CryptoNight v7 node job for `template`
+    fn job(job_id: &str, template: BlockTemplate) -> MiningJob {
+        MiningJob {
+            job_id: job_id.to_string(),
+            blob: template.hashing_blob(template.extra_nonce(0)).unwrap(),
+            target: template.target(),
+            algorithm: AlgorithmType::CryptoNightV7,
+            seed_hash: None,
+            nonce_space: NonceSpace::default(),
+            template: Some(Arc::new(template)),
+        }
+    }
+
+    /// Session in the first downstream round with `job` as the node's latest job
+    fn session(
+        job: MiningJob,
+        share_difficulty: u64,
+    ) -> (
+        Session,
+        watch::Sender<Option<MiningJob>>,
+        crossbeam_channel::Receiver<Share>,
+    ) {
+        let (job_watch, jobs) = watch::channel(Some(job));
+        let (share_sender, shares) = crossbeam_channel::unbounded();
+        let session = Session {
+            id: format!("{:08x}", SESSION_ROUND_BASE),
+            round: SESSION_ROUND_BASE,
+            peer: "192.0.2.1:4000".parse().unwrap(),
+            share_difficulty,
+            jobs,
+            share_sender,
+            hasher: ShareHasher::new(RandomXFlags::detect()),
+            job: None,
+            submitted: HashSet::new(),
+        };
+        (session, job_watch, shares)
+    }
+
+    fn submit(job_id: &str, nonce: u32, result: &[u8; 32]) -> Value {
+        json!({
+            "job_id": job_id,
+            "nonce": hex::encode(nonce.to_le_bytes()),
+            "result": hex::encode(result)
+        })
+    }
+
+    /// Hash of `nonce` over the session's own blob
+    fn hash(session: &Session, nonce: u32) -> [u8; 32] {
+        let job = session.job.as_ref().unwrap();
+        let template = job.template.as_ref().unwrap();
+        let blob = template
+            .hashing_blob(template.extra_nonce(session.round))
+            .unwrap();
+        algorithm::create_verifier(job.algorithm, &[0u8; 32], RandomXFlags::detect())
+            .hash(&blob, nonce as u64)
+            .unwrap()
+    }
+
+    #[test]
+    fn session_rounds_stay_above_scheduler_rounds() {
+        assert_eq!(next_session_round(SESSION_ROUND_BASE), 0x8000_0001);
+        assert_eq!(next_session_round(u32::MAX), SESSION_ROUND_BASE);
+
+        let template = template(2);
+        let offset = template.reserved_offset;
+        let (mut session, _job_watch, _shares) = session(job("job-1", template.clone()), 1000);
+        let params = session.next_job().unwrap().unwrap();
+        assert_eq!(params["target"], encode_target(1000));
+
+        // The session blob carries its own round, unlike the scheduler's
+        let blob = hex::decode(params["blob"].as_str().unwrap()).unwrap();
+        assert_eq!(
+            blob,
+            template
+                .hashing_blob(template.extra_nonce(SESSION_ROUND_BASE))
+                .unwrap()
+        );
+        assert_ne!(
+            blob,
+            template.hashing_blob(template.extra_nonce(0)).unwrap()
+        );
+        let extra_nonce = template.with_extra_nonce(template.extra_nonce(SESSION_ROUND_BASE));
+        assert_eq!(
+            &extra_nonce.unwrap()[offset..offset + 8],
+            &[0x0a, 0x0b, 0x0c, 0x0d, 0x80, 0, 0, 0]
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_and_duplicate_shares_are_rejected() {
+        let mut template = template(2);
+        template.difficulty = u64::MAX;
+        let (mut session, _job_watch, shares) = session(job("job-1", template), 1);
+        session.next_job().unwrap();
+
+        let nonce = 42;
+        let result = hash(&session, nonce);
+        let err = session
+            .handle_submit(&submit("job-0", nonce, &result))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Block expired"), "{}", err);
+
+        let err = session
+            .handle_submit(&submit("job-1", nonce + 1, &result))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid result"), "{}", err);
+
+        // Accepted at the share difficulty, but far from solving the block
+        session
+            .handle_submit(&submit("job-1", nonce, &result))
+            .await
+            .unwrap();
+        assert!(shares.is_empty());
+
+        let err = session
+            .handle_submit(&submit("job-1", nonce, &result))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Duplicate share"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn low_difficulty_share_is_rejected() {
+        let mut template = template(2);
+        template.difficulty = u64::MAX;
+        let (mut session, _job_watch, shares) = session(job("job-1", template), u64::MAX);
+        session.next_job().unwrap();
+
+        let err = session
+            .handle_submit(&submit("job-1", 7, &hash(&session, 7)))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Low difficulty"), "{}", err);
+        assert!(shares.is_empty());
+    }
+
+    #[tokio::test]
+    async fn block_meeting_full_target_is_forwarded() {
+        let mut template = template(2);
+        template.difficulty = 1;
+        let (mut session, _job_watch, shares) = session(job("job-1", template.clone()), 1000);
+        session.next_job().unwrap();
+
+        let nonce = 0xdead_beef;
+        let result = hash(&session, nonce);
+        session
+            .handle_submit(&submit("job-1", nonce, &result))
+            .await
+            .unwrap();
+        let share = shares.try_recv().unwrap();
+        assert_eq!(share.job_id, "job-1");
+        assert_eq!(share.nonce, nonce as u64);
+        assert_eq!(share.result, result);
+        assert_eq!(share.extra_nonce, template.extra_nonce(SESSION_ROUND_BASE));
+    }
+}
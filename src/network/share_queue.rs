@@ -0,0 +1,316 @@
+// src/network/share_queue.rs
+//! Share submission queue
+//!
+//! Sits between the scheduler and [`PoolClient::submit_share`] and filters out
+//! shares the pool would reject anyway:
+//! - Shares for jobs that are no longer live (stale shares)
+//! - Repeated (job, nonce) pairs (duplicate shares)
+//!
+//! Shares found while the pool connection is down are held briefly and
+//! submitted after reconnecting if the new session announces their job again.
+//!
+//! [`PoolClient::submit_share`]: crate::network::PoolClient
+
+use crate::miner::scheduler::Share;
+use crate::stats::ShareResult;
+use crossbeam_channel::Sender;
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+/// How long shares are held while waiting for a (re)connection
+const SHARE_BUFFER_TTL: Duration = Duration::from_secs(30);
+
+/// A job shares may still be submitted for
+#[derive(Debug, Clone)]
+struct LiveJob {
+    /// Pool job identifier
+    job_id: String,
+    /// Block height of the job, if the pool sent it
+    height: Option<u64>,
+}
+
+/// Queue of shares waiting to be submitted to the pool
+#[derive(Debug, Default)]
+pub struct ShareQueue {
+    /// Jobs shares may still be submitted for, newest last
+    live_jobs: Vec<LiveJob>,
+    /// (job, nonce) pairs already submitted for live jobs
+    submitted: HashSet<(String, u64)>,
+    /// Shares waiting for submission, oldest first
+    pending: VecDeque<Share>,
+    /// Whether submission is paused until the pool sends a job
+    holding: bool,
+    /// Shares dropped because their job was no longer live
+    stale: u64,
+    /// Shares dropped because they were already submitted
+    duplicates: u64,
+    /// Receives a [`ShareResult`] for every dropped share
+    stats: Option<Sender<ShareResult>>,
+}
+
+impl ShareQueue {
+    /// Creates an empty queue
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reports dropped shares to a statistics reporter
+    ///
+    /// # Arguments
+    /// * `stats` - Sender obtained from [`StatsReporter::share_sender`]
+    ///
+    /// [`StatsReporter::share_sender`]: crate::stats::StatsReporter::share_sender
+    pub fn with_stats(mut self, stats: Sender<ShareResult>) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    /// Pauses submission until the pool sends the first job of a new session
+    ///
+    /// Called on every (re)connect so shares buffered while disconnected are
+    /// only submitted once it is known whether their job is still live. Jobs
+    /// of the previous session are no longer live unless the new session
+    /// sends them again.
+    pub fn session_started(&mut self) {
+        self.live_jobs.clear();
+        self.submitted.clear();
+        self.holding = true;
+    }
+
+    /// Registers a new job from the pool
+    ///
+    /// Jobs for the same block height stay live (pools accept shares for them
+    /// after a difficulty change); a new height, or a job without height,
+    /// invalidates all earlier jobs.
+    ///
+    /// # Arguments
+    /// * `job_id` - Pool job identifier
+    /// * `height` - Block height of the job, if known
+    pub fn job_received(&mut self, job_id: &str, height: Option<u64>) {
+        let same_block = |job: &LiveJob| height.is_some() && job.height == height;
+        if !self.live_jobs.iter().all(same_block) {
+            self.live_jobs.clear();
+            self.submitted.clear();
+        }
+        self.live_jobs.push(LiveJob {
+            job_id: job_id.to_string(),
+            height,
+        });
+        self.holding = false;
+    }
+
+    /// Adds a share found by the miners
+    pub fn push(&mut self, share: Share) {
+        self.pending.push_back(share);
+    }
+
+    /// Puts back a share whose submission failed so it is retried after reconnecting
+    ///
+    /// The share keeps the time it was found, so retries do not extend how
+    /// long it is held.
+    pub fn requeue(&mut self, share: Share) {
+        self.submitted.remove(&(share.job_id.clone(), share.nonce));
+        self.pending.push_front(share);
+    }
+
+    /// Takes the shares that should be submitted now
+    ///
+    /// Stale and duplicate shares are dropped and counted. While submission is
+    /// paused, shares are kept until `SHARE_BUFFER_TTL` after they were found
+    /// (and are then counted as stale).
+    pub fn take_ready(&mut self) -> Vec<Share> {
+        if self.holding {
+            let before = self.pending.len();
+            self.pending
+                .retain(|share| share.found.elapsed() < SHARE_BUFFER_TTL);
+            for _ in self.pending.len()..before {
+                self.dropped(ShareResult::Stale);
+            }
+            return Vec::new();
+        }
+
+        let mut ready = Vec::new();
+        while let Some(share) = self.pending.pop_front() {
+            if !self.live_jobs.iter().any(|job| job.job_id == share.job_id) {
+                log::debug!("Dropping stale share for job {}", share.job_id);
+                self.dropped(ShareResult::Stale);
+            } else if !self.submitted.insert((share.job_id.clone(), share.nonce)) {
+                log::debug!(
+                    "Dropping duplicate share for job {} nonce {}",
+                    share.job_id,
+                    share.nonce
+                );
+                self.dropped(ShareResult::Duplicate);
+            } else {
+                ready.push(share);
+            }
+        }
+        ready
+    }
+
+    /// Counts a dropped share and reports it
+    fn dropped(&mut self, result: ShareResult) {
+        match result {
+            ShareResult::Stale => self.stale += 1,
+            ShareResult::Duplicate => self.duplicates += 1,
+            _ => {}
+        }
+        if let Some(stats) = &self.stats {
+            let _ = stats.send(result);
+        }
+    }
+
+    /// Number of shares dropped because their job was no longer live
+    pub fn stale(&self) -> u64 {
+        self.stale
+    }
+
+    /// Number of shares dropped because they were already submitted
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn share(job_id: &str, nonce: u64) -> Share {
+        Share {
+            job_id: job_id.to_string(),
+            nonce,
+            result: [0u8; 32],
+            extra_nonce: 0,
+            found: Instant::now(),
+        }
+    }
+
+    fn nonces(shares: &[Share]) -> Vec<u64> {
+        shares.iter().map(|share| share.nonce).collect()
+    }
+
+    #[test]
+    fn duplicate_shares_are_dropped() {
+        let (stats, results) = crossbeam_channel::unbounded();
+        let mut queue = ShareQueue::new().with_stats(stats);
+        queue.job_received("a", Some(100));
+
+        queue.push(share("a", 1));
+        queue.push(share("a", 1));
+        queue.push(share("a", 2));
+        assert_eq!(nonces(&queue.take_ready()), [1, 2]);
+        queue.push(share("a", 2));
+        assert!(queue.take_ready().is_empty());
+
+        assert_eq!(queue.duplicates(), 2);
+        assert_eq!(queue.stale(), 0);
+        assert!(matches!(results.try_recv(), Ok(ShareResult::Duplicate)));
+        assert!(matches!(results.try_recv(), Ok(ShareResult::Duplicate)));
+        assert!(results.try_recv().is_err());
+    }
+
+    #[test]
+    fn requeued_share_is_not_a_duplicate() {
+        let mut queue = ShareQueue::new();
+        queue.job_received("a", Some(100));
+        queue.push(share("a", 1));
+        let ready = queue.take_ready();
+
+        queue.requeue(ready[0].clone());
+        assert_eq!(nonces(&queue.take_ready()), [1]);
+        assert_eq!(queue.duplicates(), 0);
+    }
+
+    #[test]
+    fn shares_for_replaced_jobs_are_stale() {
+        let (stats, results) = crossbeam_channel::unbounded();
+        let mut queue = ShareQueue::new().with_stats(stats);
+        queue.job_received("a", Some(100));
+        queue.job_received("b", Some(100));
+
+        // Same height: both jobs stay live
+        queue.push(share("a", 1));
+        queue.push(share("b", 2));
+        assert_eq!(nonces(&queue.take_ready()), [1, 2]);
+
+        // New height: earlier jobs are gone
+        queue.job_received("c", Some(101));
+        queue.push(share("b", 3));
+        queue.push(share("c", 4));
+        assert_eq!(nonces(&queue.take_ready()), [4]);
+
+        // Jobs without height replace everything
+        queue.job_received("d", None);
+        queue.push(share("c", 5));
+        assert!(queue.take_ready().is_empty());
+
+        assert_eq!(queue.stale(), 2);
+        assert!(matches!(results.try_recv(), Ok(ShareResult::Stale)));
+        assert!(matches!(results.try_recv(), Ok(ShareResult::Stale)));
+        assert!(results.try_recv().is_err());
+    }
+
+    #[test]
+    fn shares_are_held_while_reconnecting() {
+        let mut queue = ShareQueue::new();
+        queue.job_received("a", Some(100));
+
+        queue.session_started();
+        queue.push(share("a", 1));
+        assert!(queue.take_ready().is_empty());
+
+        // The new session resends the job: the held share is still good
+        queue.job_received("a", Some(100));
+        assert_eq!(nonces(&queue.take_ready()), [1]);
+        assert_eq!(queue.stale(), 0);
+    }
+
+    #[test]
+    fn previous_session_jobs_are_not_live() {
+        let mut queue = ShareQueue::new();
+        queue.job_received("a", Some(100));
+
+        queue.session_started();
+        queue.push(share("a", 1));
+        // Same block, but the pool no longer knows the old session's job
+        queue.job_received("b", Some(100));
+        queue.push(share("a", 2));
+        assert!(queue.take_ready().is_empty());
+        assert_eq!(queue.stale(), 2);
+    }
+
+    #[test]
+    fn held_shares_expire_by_found_time() {
+        let mut queue = ShareQueue::new();
+        queue.job_received("a", Some(100));
+        let mut old = share("a", 1);
+        old.found = Instant::now() - SHARE_BUFFER_TTL;
+        queue.push(old);
+        let ready = queue.take_ready();
+
+        // A failed submission puts it back without refreshing its age
+        queue.session_started();
+        queue.requeue(ready[0].clone());
+        queue.push(share("a", 2));
+        assert!(queue.take_ready().is_empty());
+        assert_eq!(queue.stale(), 1);
+
+        queue.job_received("a", Some(100));
+        assert_eq!(nonces(&queue.take_ready()), [2]);
+    }
+
+    #[test]
+    fn held_shares_for_an_old_block_are_stale() {
+        let mut queue = ShareQueue::new();
+        queue.job_received("a", Some(100));
+
+        queue.session_started();
+        queue.push(share("a", 1));
+        assert!(queue.take_ready().is_empty());
+
+        queue.job_received("b", Some(101));
+        assert!(queue.take_ready().is_empty());
+        assert_eq!(queue.stale(), 1);
+    }
+}
@@ -0,0 +1,510 @@
+// src/network/simulator.rs
+//! Built-in pool simulator for offline testing
+//!
+//! Runs a local Stratum server that issues synthetic jobs for a chosen
+//! algorithm and validates submitted shares by recomputing their hash. Faults
+//! can be injected to soak-test [`PoolClient`] resilience:
+//! - Connections are closed after a fixed time
+//! - Every Nth valid share is answered with a pool error
+//!
+//! [`PoolClient`]: crate::network::PoolClient
+
+use crate::miner::algorithm::KEY_SIZE;
+use crate::miner::features::RandomXFlags;
+use crate::network::stratum::{
+    self, ShareHasher, StratumConnection, error_response, job_notification, result_response,
+};
+use crate::types::AlgorithmType;
+use crate::utils::difficulty::{meets_target, target_from_difficulty};
+use crate::utils::error::MinerError;
+use serde_json::{Value, json};
+use sha3::{Digest, Keccak256};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::time;
+
+/// Length of the synthetic hashing blobs
+const BLOB_LEN: usize = 76;
+
+/// Seed hash of the synthetic jobs (the RandomX key)
+const SEED_HASH: [u8; KEY_SIZE] = [0u8; KEY_SIZE];
+
+/// Interval between statistics reports
+const REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Errors cycled through when injecting share rejections
+const INJECTED_ERRORS: [&str; 5] = [
+    "Low difficulty share",
+    "Stale share",
+    "Duplicate share",
+    "Invalid job id",
+    "Unauthenticated",
+];
+
+/// Pool simulator settings
+#[derive(Debug, Clone)]
+pub struct SimulatorConfig {
+    /// Address to listen on (e.g., "127.0.0.1:3333")
+    pub bind: String,
+    /// Algorithm of the synthetic jobs
+    pub algorithm: AlgorithmType,
+    /// Share difficulty handed to miners
+    pub difficulty: u64,
+    /// Time between new jobs
+    pub job_interval: Duration,
+    /// Close every connection after this long (default: never)
+    pub disconnect_after: Option<Duration>,
+    /// Answer every Nth valid share with an injected error (default: never)
+    pub error_every: Option<u64>,
+}
+
+/// A synthetic job
+#[derive(Debug, Clone)]
+struct SimJob {
+    /// Job identifier
+    job_id: String,
+    /// Hashing blob
+    blob: Vec<u8>,
+    /// Block height the job pretends to be for
+    height: u64,
+}
+
+/// Share counters across all connections
+#[derive(Debug, Default)]
+struct SimStats {
+    /// Shares that verified
+    accepted: AtomicU64,
+    /// Shares that failed validation
+    rejected: AtomicU64,
+    /// Valid shares answered with an injected error
+    injected: AtomicU64,
+    /// Connections closed on purpose
+    disconnects: AtomicU64,
+}
+
+/// Local Stratum server issuing synthetic jobs
+pub struct PoolSimulator {
+    /// Simulator settings
+    config: SimulatorConfig,
+    /// Share counters
+    stats: Arc<SimStats>,
+    /// Flags RandomX verifier instances are created with
+    randomx_flags: RandomXFlags,
+}
+
+impl PoolSimulator {
+    /// Creates a new PoolSimulator
+    ///
+    /// # Arguments
+    /// * `config` - Simulator settings
+    pub fn new(config: SimulatorConfig) -> Self {
+        PoolSimulator {
+            config,
+            stats: Arc::new(SimStats::default()),
+            randomx_flags: RandomXFlags::detect(),
+        }
+    }
+
+    /// Validates shares with RandomX instances using the given flags instead
+    /// of the fastest ones the CPU supports
+    ///
+    /// # Arguments
+    /// * `flags` - Flags selected with [`RandomXFlags::select`]
+    pub fn with_randomx_flags(mut self, flags: RandomXFlags) -> Self {
+        self.randomx_flags = flags;
+        self
+    }
+
+    /// Runs the simulator until the listener fails
+    ///
+    /// # Errors
+    /// Returns `MinerError` if binding or accepting fails
+    pub async fn run(self) -> Result<(), MinerError> {
+        let listener = TcpListener::bind(&self.config.bind).await?;
+        self.serve(listener).await
+    }
+
+    /// Issues jobs to miners connecting to `listener` until accepting fails
+    async fn serve(self, listener: TcpListener) -> Result<(), MinerError> {
+        log::info!(
+            "Pool simulator listening on {} ({}, difficulty {}, new job every {}s)",
+            self.config.bind,
+            self.config.algorithm,
+            self.config.difficulty,
+            self.config.job_interval.as_secs()
+        );
+
+        let (job_watch, jobs) = watch::channel(new_job(1));
+        tokio::spawn(issue_jobs(job_watch, self.config.job_interval));
+        tokio::spawn(report_stats(self.stats.clone()));
+
+        // Validation instances are created on the first share and shared
+        let hasher = ShareHasher::new(self.randomx_flags);
+
+        let mut next_session = 0u64;
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            next_session += 1;
+            let session = SimSession {
+                id: format!("sim{:04}", next_session),
+                peer,
+                config: self.config.clone(),
+                hasher: hasher.clone(),
+                stats: self.stats.clone(),
+                jobs: jobs.clone(),
+                job: None,
+                submitted: HashSet::new(),
+            };
+            tokio::spawn(async move {
+                if let Err(e) = session.run(stream).await {
+                    log::warn!("Miner {} disconnected: {}", peer, e);
+                }
+            });
+        }
+    }
+}
+
+/// A single miner connection to the simulator
+struct SimSession {
+    /// Session identifier returned on login
+    id: String,
+    /// Remote address of the miner
+    peer: SocketAddr,
+    /// Simulator settings
+    config: SimulatorConfig,
+    /// Recomputes submitted shares
+    hasher: ShareHasher,
+    /// Share counters
+    stats: Arc<SimStats>,
+    /// Latest synthetic job
+    jobs: watch::Receiver<SimJob>,
+    /// Job last sent to this miner
+    job: Option<SimJob>,
+    /// (job, nonce) pairs already submitted for the current job
+    submitted: HashSet<(String, u32)>,
+}
+
+impl SimSession {
+    /// Serves the connection until the miner disconnects or the disconnect
+    /// timer fires
+    async fn run(mut self, stream: TcpStream) -> Result<(), MinerError> {
+        let mut conn = StratumConnection::accept(stream).await?;
+        let mut logged_in = false;
+        let disconnect_after = self.config.disconnect_after;
+        let disconnect = time::sleep(disconnect_after.unwrap_or_default());
+        tokio::pin!(disconnect);
+
+        loop {
+            tokio::select! {
+                message = conn.recv() => {
+                    let Some(message) = message? else {
+                        log::info!("Miner {} disconnected", self.peer);
+                        return Ok(());
+                    };
+                    let id = message["id"].clone();
+
+                    match message["method"].as_str() {
+                        Some("login") => {
+                            logged_in = true;
+                            log::info!("Miner {} logged in as {}", self.peer, message["params"]["login"]);
+                            let job = self.next_job();
+                            let result = json!({ "id": self.id, "job": job, "status": "OK" });
+                            conn.send(&result_response(&id, result)).await?;
+                        }
+                        Some("submit") => {
+                            let response = match self.handle_submit(&message["params"]).await {
+                                Ok(()) => result_response(&id, json!({ "status": "OK" })),
+                                Err(message) => error_response(&id, &message),
+                            };
+                            conn.send(&response).await?;
+                        }
+                        Some("keepalived") => {
+                            conn.send(&result_response(&id, json!({ "status": "KEEPALIVED" }))).await?;
+                        }
+                        Some("subscribe") => {
+                            conn.send(&result_response(&id, json!({ "status": "OK" }))).await?;
+                        }
+                        _ => conn.send(&error_response(&id, "Unsupported method")).await?,
+                    }
+                }
+                changed = self.jobs.changed() => {
+                    changed.map_err(|_| MinerError::ChannelError("Job generator stopped".into()))?;
+                    if logged_in {
+                        let job = self.next_job();
+                        conn.send(&job_notification(job)).await?;
+                    }
+                }
+                _ = &mut disconnect, if disconnect_after.is_some() => {
+                    log::info!("Disconnecting miner {} (injected)", self.peer);
+                    self.stats.disconnects.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Share target sent with every job (8 bytes, little-endian)
+    fn target(&self) -> [u8; 8] {
+        target_from_difficulty(self.config.difficulty).to_le_bytes()
+    }
+
+    /// Builds job parameters from the latest synthetic job
+    fn next_job(&mut self) -> Value {
+        let job = self.jobs.borrow_and_update().clone();
+        let params = json!({
+            "job_id": job.job_id,
+            "blob": hex::encode(&job.blob),
+            "target": hex::encode(self.target()),
+            "algo": self.config.algorithm.stratum_name(),
+            "height": job.height,
+            "seed_hash": hex::encode(SEED_HASH),
+        });
+
+        self.submitted.clear();
+        self.job = Some(job);
+        params
+    }
+
+    /// Validates a submitted share
+    ///
+    /// # Returns
+    /// * `Ok(())` - The share is valid
+    /// * `Err(String)` - The error message sent to the miner (validation
+    ///   failure or injected error)
+    async fn handle_submit(&mut self, params: &Value) -> Result<(), String> {
+        let result = self.validate(params).await;
+        match result {
+            Ok(()) => {
+                let accepted = self.stats.accepted.fetch_add(1, Ordering::Relaxed) + 1;
+                if let Some(every) = self.config.error_every.filter(|every| *every > 0)
+                    && accepted.is_multiple_of(every)
+                {
+                    let injected = self.stats.injected.fetch_add(1, Ordering::Relaxed);
+                    let error = INJECTED_ERRORS[injected as usize % INJECTED_ERRORS.len()];
+                    log::info!("Injecting \"{}\" for miner {}", error, self.peer);
+                    return Err(error.to_string());
+                }
+                Ok(())
+            }
+            Err(error) => {
+                self.stats.rejected.fetch_add(1, Ordering::Relaxed);
+                log::warn!("Rejected share from miner {}: {}", self.peer, error);
+                Err(error.to_string())
+            }
+        }
+    }
+
+    /// Recomputes a submitted share and checks it against the share difficulty
+    ///
+    /// # Errors
+    /// Returns `MinerError` describing why the share was rejected
+    async fn validate(&mut self, params: &Value) -> Result<(), MinerError> {
+        let job = self
+            .job
+            .as_ref()
+            .filter(|job| params["job_id"].as_str() == Some(job.job_id.as_str()))
+            .ok_or_else(|| MinerError::ProtocolError("Block expired".to_string()))?;
+        let (nonce, result) = stratum::parse_submit(params)?;
+        if !self.submitted.insert((job.job_id.clone(), nonce)) {
+            return Err(MinerError::ProtocolError("Duplicate share".to_string()));
+        }
+
+        let hash = self
+            .hasher
+            .hash(
+                self.config.algorithm,
+                Some(hex::encode(SEED_HASH)),
+                job.blob.clone(),
+                nonce,
+            )
+            .await?;
+        if hash != result {
+            return Err(MinerError::ProtocolError("Invalid result".to_string()));
+        }
+        // The same check the miner applies to the target it was sent
+        if !meets_target(&result, &self.target()) {
+            return Err(MinerError::ProtocolError(
+                "Low difficulty share".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Builds a synthetic job for `height`
+///
+/// The blob mimics a block hashing blob: version bytes, a timestamp and a
+/// pseudo previous-block hash derived from the height, with the nonce bytes
+/// left zero.
+fn new_job(height: u64) -> SimJob {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    let mut blob = vec![0u8; BLOB_LEN];
+    blob[0] = 16; // major version
+    blob[1] = 16; // minor version
+    blob[2..7].copy_from_slice(&now.to_le_bytes()[..5]);
+    let prev_hash = Keccak256::digest([height.to_le_bytes(), now.to_le_bytes()].concat());
+    blob[7..39].copy_from_slice(&prev_hash);
+    blob[43..75].copy_from_slice(&Keccak256::digest(prev_hash));
+    blob[75] = 1; // transaction count
+
+    SimJob {
+        job_id: format!(
+            "{:016x}",
+            u64::from_le_bytes(prev_hash[..8].try_into().expect("8 bytes"))
+        ),
+        blob,
+        height,
+    }
+}
+
+/// Publishes a new job every `interval`
+async fn issue_jobs(job_watch: watch::Sender<SimJob>, interval: Duration) {
+    let mut ticker = time::interval(interval);
+    ticker.tick().await;
+
+    let mut height = 1;
+    loop {
+        ticker.tick().await;
+        height += 1;
+        let job = new_job(height);
+        log::info!("New job {} at height {}", job.job_id, height);
+        job_watch.send_replace(job);
+    }
+}
+
+/// Periodically logs share counters
+async fn report_stats(stats: Arc<SimStats>) {
+    let mut interval = time::interval(REPORT_INTERVAL);
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+        log::info!(
+            "Simulator: {} valid, {} invalid, {} injected errors, {} injected disconnects",
+            stats.accepted.load(Ordering::Relaxed),
+            stats.rejected.load(Ordering::Relaxed),
+            stats.injected.load(Ordering::Relaxed),
+            stats.disconnects.load(Ordering::Relaxed)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::miner::algorithm::{self, Algorithm};
+    use crate::miner::scheduler::{MiningJob, Share};
+    use crate::network::pool::{PoolClient, PoolConfig};
+    use crate::stats::ShareResult;
+    use crate::types::RejectReason;
+    use std::time::Instant;
+
+    /// Receives from a blocking channel without stalling the runtime
+    async fn recv<T: Send + 'static>(receiver: &crossbeam_channel::Receiver<T>) -> T {
+        let receiver = receiver.clone();
+        tokio::task::spawn_blocking(move || receiver.recv_timeout(Duration::from_secs(10)))
+            .await
+            .unwrap()
+            .expect("nothing received within 10s")
+    }
+
+    fn share(job: &MiningJob, algorithm: &Arc<dyn Algorithm>, nonce: u64) -> Share {
+        Share {
+            job_id: job.job_id.clone(),
+            nonce,
+            result: algorithm.hash(&job.blob, nonce).unwrap(),
+            extra_nonce: 0,
+            found: Instant::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn pool_client_survives_injected_faults() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let simulator = PoolSimulator::new(SimulatorConfig {
+            bind: addr.to_string(),
+            algorithm: AlgorithmType::CryptoNightV7,
+            difficulty: 1,
+            job_interval: Duration::from_secs(3600),
+            disconnect_after: Some(Duration::from_secs(2)),
+            error_every: Some(2),
+        });
+        let stats = simulator.stats.clone();
+        tokio::spawn(simulator.serve(listener));
+
+        let config = PoolConfig {
+            url: format!("ws://{}", addr),
+            user: "wallet".to_string(),
+            password: "x".to_string(),
+            worker_id: "rig".to_string(),
+            self_select: None,
+            benchmark_file: std::env::temp_dir().join(format!(
+                "xmr_miner-no-benchmark-{}.json",
+                std::process::id()
+            )),
+            target_shares_per_minute: None,
+            block_notify_url: None,
+            record_file: None,
+        };
+        let (job_sender, jobs) = crossbeam_channel::unbounded();
+        let (share_sender, share_receiver) = crossbeam_channel::unbounded();
+        let (outcome_sender, outcomes) = crossbeam_channel::unbounded();
+        let client = Arc::new(
+            PoolClient::new(config, job_sender, share_receiver).with_outcomes(outcome_sender),
+        );
+        let algorithm = algorithm::create_verifier(
+            AlgorithmType::CryptoNightV7,
+            &SEED_HASH,
+            RandomXFlags::detect(),
+        );
+
+        for session in 1..=2u64 {
+            let session_client = client.clone();
+            let session_run = tokio::spawn(async move {
+                session_client.connect().await?;
+                session_client.run().await
+            });
+
+            let job = recv(&jobs).await.expect("login job");
+            assert_eq!(job.algorithm, AlgorithmType::CryptoNightV7);
+
+            // Every second valid share is answered with an injected error
+            share_sender.send(share(&job, &algorithm, 1)).unwrap();
+            let (first, result) = recv(&outcomes).await;
+            assert_eq!(first.nonce, 1);
+            assert!(matches!(result, ShareResult::Accepted), "{:?}", result);
+            share_sender.send(share(&job, &algorithm, 2)).unwrap();
+            let (second, result) = recv(&outcomes).await;
+            assert_eq!(second.nonce, 2);
+            let expected = if session == 1 {
+                RejectReason::LowDifficulty
+            } else {
+                RejectReason::Stale
+            };
+            assert!(
+                matches!(result, ShareResult::Rejected(reason) if reason == expected),
+                "{:?}",
+                result
+            );
+
+            // The injected disconnect ends the session; the client connects again
+            let _ = time::timeout(Duration::from_secs(10), session_run)
+                .await
+                .expect("session ends after the injected disconnect");
+            assert_eq!(stats.disconnects.load(Ordering::Relaxed), session);
+        }
+
+        assert_eq!(stats.accepted.load(Ordering::Relaxed), 4);
+        assert_eq!(stats.injected.load(Ordering::Relaxed), 2);
+        assert_eq!(stats.rejected.load(Ordering::Relaxed), 0);
+    }
+}
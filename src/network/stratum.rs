@@ -0,0 +1,253 @@
+// src/network/stratum.rs
+//! Stratum server building blocks
+//!
+//! Shared pieces for the Stratum servers run by the miner:
+//! - Accepting downstream connections over plain TCP (newline-delimited JSON,
+//!   as used by most third-party miners) or WebSocket (as used by [`PoolClient`])
+//! - Building job notifications and JSON-RPC responses
+//! - Parsing submitted shares and recomputing their hashes
+//!
+//! [`PoolClient`]: crate::network::PoolClient
+
+use crate::miner::features::RandomXFlags;
+use crate::miner::verifier::VerifierCache;
+use crate::types::AlgorithmType;
+use crate::utils::error::MinerError;
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::TcpStream;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio_tungstenite::WebSocketStream;
+use tungstenite::protocol::Message;
+
+/// Configuration for a Stratum server listening for downstream miners
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerConfig {
+    /// Address to listen on (e.g., "0.0.0.0:3333")
+    pub bind: String,
+    /// Share difficulty handed to downstream miners (default: 10000)
+    #[serde(default = "default_share_difficulty")]
+    pub share_difficulty: u64,
+}
+
+fn default_share_difficulty() -> u64 {
+    10_000
+}
+
+/// Recomputes submitted share hashes for all connections of a server
+///
+/// Algorithm instances (a light-mode VM for RandomX) are created on first use
+/// per seed hash and shared, so a connection costs no extra memory.
+#[derive(Clone, Default)]
+pub struct ShareHasher {
+    /// Verifier instances per algorithm and seed
+    verifiers: VerifierCache,
+}
+
+impl ShareHasher {
+    /// Creates a hasher whose RandomX instances use the given flags
+    ///
+    /// # Arguments
+    /// * `randomx_flags` - Flags selected with
+    ///   [`RandomXFlags::select`](crate::miner::features::RandomXFlags::select)
+    pub fn new(randomx_flags: RandomXFlags) -> Self {
+        ShareHasher {
+            verifiers: VerifierCache::new(randomx_flags),
+        }
+    }
+
+    /// Computes the hash of `blob` with `nonce` off the async runtime
+    ///
+    /// # Arguments
+    /// * `algorithm` - Algorithm of the job the share was found for
+    /// * `seed_hash` - Seed hash (hex) of the job
+    /// * `blob` - Hashing blob sent to the miner
+    /// * `nonce` - Nonce submitted by the miner
+    ///
+    /// # Errors
+    /// Returns `MinerError` if the seed hash is malformed or hashing fails
+    pub async fn hash(
+        &self,
+        algorithm: AlgorithmType,
+        seed_hash: Option<String>,
+        blob: Vec<u8>,
+        nonce: u32,
+    ) -> Result<[u8; 32], MinerError> {
+        let verifiers = self.verifiers.clone();
+        tokio::task::spawn_blocking(move || {
+            verifiers
+                .get(algorithm, seed_hash.as_deref())?
+                .hash(&blob, nonce as u64)
+        })
+        .await?
+    }
+}
+
+/// A downstream miner connection
+///
+/// The transport is detected from the first bytes sent by the client: an HTTP
+/// `GET` starts a WebSocket handshake, anything else is treated as
+/// newline-delimited JSON over TCP.
+pub enum StratumConnection {
+    /// Newline-delimited JSON over plain TCP
+    Tcp {
+        /// Line reader over the read half
+        reader: Lines<BufReader<OwnedReadHalf>>,
+        /// Write half of the socket
+        writer: OwnedWriteHalf,
+    },
+    /// JSON messages over WebSocket text frames
+    WebSocket(Box<WebSocketStream<TcpStream>>),
+}
+
+impl StratumConnection {
+    /// Accepts a downstream connection, detecting its transport
+    ///
+    /// # Errors
+    /// Returns `MinerError` if the socket fails or the WebSocket handshake fails
+    pub async fn accept(stream: TcpStream) -> Result<Self, MinerError> {
+        let mut head = [0u8; 4];
+        let read = stream.peek(&mut head).await?;
+
+        if &head[..read] == b"GET " {
+            let ws = tokio_tungstenite::accept_async(stream).await?;
+            Ok(StratumConnection::WebSocket(Box::new(ws)))
+        } else {
+            let (read_half, writer) = stream.into_split();
+            Ok(StratumConnection::Tcp {
+                reader: BufReader::new(read_half).lines(),
+                writer,
+            })
+        }
+    }
+
+    /// Receives the next JSON message
+    ///
+    /// # Returns
+    /// * `Ok(Some(Value))` - The next message
+    /// * `Ok(None)` - If the peer closed the connection
+    /// * `Err(MinerError)` - If the socket fails or the message is not valid JSON
+    pub async fn recv(&mut self) -> Result<Option<Value>, MinerError> {
+        match self {
+            StratumConnection::Tcp { reader, .. } => loop {
+                match reader.next_line().await? {
+                    Some(line) if line.trim().is_empty() => continue,
+                    Some(line) => return Ok(Some(serde_json::from_str(&line)?)),
+                    None => return Ok(None),
+                }
+            },
+            StratumConnection::WebSocket(ws) => loop {
+                match ws.next().await {
+                    Some(Ok(Message::Text(text))) => return Ok(Some(serde_json::from_str(&text)?)),
+                    Some(Ok(Message::Close(_))) | None => return Ok(None),
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                }
+            },
+        }
+    }
+
+    /// Sends a JSON message
+    ///
+    /// # Errors
+    /// Returns `MinerError` if the socket write fails
+    pub async fn send(&mut self, value: &Value) -> Result<(), MinerError> {
+        match self {
+            StratumConnection::Tcp { writer, .. } => {
+                let mut line = value.to_string();
+                line.push('\n');
+                writer.write_all(line.as_bytes()).await?;
+            }
+            StratumConnection::WebSocket(ws) => {
+                ws.send(Message::Text(value.to_string().into())).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds a successful JSON-RPC response
+pub fn result_response(id: &Value, result: Value) -> Value {
+    json!({
+        "id": id,
+        "jsonrpc": "2.0",
+        "error": null,
+        "result": result
+    })
+}
+
+/// Builds a JSON-RPC error response
+pub fn error_response(id: &Value, message: &str) -> Value {
+    json!({
+        "id": id,
+        "jsonrpc": "2.0",
+        "error": { "code": -1, "message": message },
+        "result": null
+    })
+}
+
+/// Builds a `job` notification from job parameters
+pub fn job_notification(job: Value) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "job",
+        "params": job
+    })
+}
+
+/// Reads the nonce and result hash of a `submit` request
+///
+/// # Arguments
+/// * `params` - Parameters of the request
+///
+/// # Returns
+/// The nonce (sent little-endian, as it sits in the blob) and the result hash
+///
+/// # Errors
+/// Returns `MinerError::InputError` if either field is missing, not hex or of
+/// the wrong length
+pub fn parse_submit(params: &Value) -> Result<(u32, [u8; 32]), MinerError> {
+    let nonce: [u8; 4] = hex_field(params, "nonce")
+        .ok_or_else(|| MinerError::InputError("Invalid nonce".to_string()))?;
+    let result = hex_field(params, "result")
+        .ok_or_else(|| MinerError::InputError("Invalid result".to_string()))?;
+    Ok((u32::from_le_bytes(nonce), result))
+}
+
+/// Decodes the hex string field `name` into exactly `N` bytes
+fn hex_field<const N: usize>(params: &Value, name: &str) -> Option<[u8; N]> {
+    hex::decode(params[name].as_str()?).ok()?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_submitted_nonce_and_result() {
+        let params = json!({
+            "job_id": "1",
+            "nonce": "01020304",
+            "result": hex::encode([0xab; 32])
+        });
+        assert_eq!(parse_submit(&params).unwrap(), (0x0403_0201, [0xab; 32]));
+    }
+
+    #[test]
+    fn malformed_submit_is_rejected() {
+        let result = hex::encode([0xab; 32]);
+        for (nonce, result, expected) in [
+            (json!("010203"), json!(result), "Invalid nonce"),
+            (json!("0102030z"), json!(result), "Invalid nonce"),
+            (Value::Null, json!(result), "Invalid nonce"),
+            (json!("01020304"), json!("abab"), "Invalid result"),
+            (json!("01020304"), Value::Null, "Invalid result"),
+        ] {
+            let params = json!({ "nonce": nonce, "result": result });
+            let err = parse_submit(&params).unwrap_err().to_string();
+            assert!(err.contains(expected), "{}: {}", params, err);
+        }
+    }
+}
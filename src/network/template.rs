@@ -0,0 +1,478 @@
+// src/network/template.rs
+//! Block template handling for solo mining
+//!
+//! Wraps the `blocktemplate_blob` returned by a node's `getblocktemplate` call
+//! and uses the reserved space in the coinbase transaction as an extra nonce.
+//! For every extra nonce value this module can:
+//! - Write the extra nonce into the reserved area of the template
+//! - Rebuild the hashing blob (header + merkle root + transaction count)
+//! - Assemble the final block blob for submission
+
+use crate::utils::difficulty::full_target;
+use crate::utils::error::MinerError;
+use sha3::{Digest, Keccak256};
+
+/// Size of the block header nonce field in bytes
+const NONCE_SIZE: usize = 4;
+
+/// Transaction input tag for coinbase (`txin_gen`) inputs
+const TXIN_GEN: u8 = 0xff;
+
+/// Output target tag for `txout_to_key`
+const TXOUT_TO_KEY: u8 = 0x02;
+
+/// Output target tag for `txout_to_tagged_key` (key followed by a view tag)
+const TXOUT_TO_TAGGED_KEY: u8 = 0x03;
+
+/// A block template received from a node
+///
+/// The reserved area requested via `reserve_size` is used as an extra nonce:
+/// the upper 32 bits carry the rig identifier and the lower 32 bits a round
+/// counter that is advanced each time the 32-bit header nonce space is exhausted.
+#[derive(Debug, Clone)]
+pub struct BlockTemplate {
+    /// Raw block template blob as returned by the node
+    pub blob: Vec<u8>,
+    /// Offset of the reserved area inside `blob`
+    pub reserved_offset: usize,
+    /// Size of the reserved area in bytes
+    pub reserve_size: usize,
+    /// Height of the block being mined
+    pub height: u64,
+    /// Network difficulty for this block
+    pub difficulty: u64,
+    /// RandomX seed hash (hex) for this block
+    pub seed_hash: String,
+    /// Identifier of this rig, written into the upper half of the extra nonce
+    pub rig_id: u32,
+}
+
+impl BlockTemplate {
+    /// Builds the extra nonce for the given round
+    ///
+    /// # Arguments
+    /// * `round` - Number of times the 32-bit header nonce space has been exhausted
+    pub fn extra_nonce(&self, round: u32) -> u64 {
+        ((self.rig_id as u64) << 32) | round as u64
+    }
+
+    /// Computes the 32-byte target for the network difficulty
+    ///
+    /// The target is `(2^256 - 1) / difficulty` in little-endian byte order,
+    /// matching how monerod reads the proof-of-work hash; compare it with
+    /// [`meets_target`](crate::utils::difficulty::meets_target).
+    pub fn target(&self) -> Vec<u8> {
+        full_target(self.difficulty).to_vec()
+    }
+
+    /// Returns a copy of the template blob with the extra nonce written into the reserved area
+    ///
+    /// # Arguments
+    /// * `extra_nonce` - Value to store (big-endian, truncated to the reserved size)
+    ///
+    /// # Errors
+    /// Returns `MinerError::ProtocolError` if the reserved area lies outside the blob
+    pub fn with_extra_nonce(&self, extra_nonce: u64) -> Result<Vec<u8>, MinerError> {
+        let len = self.reserve_size.min(8);
+        let end = self.reserved_offset + len;
+        if end > self.blob.len() {
+            return Err(MinerError::ProtocolError(format!(
+                "Reserved offset {} out of bounds for {}-byte template",
+                self.reserved_offset,
+                self.blob.len()
+            )));
+        }
+
+        let mut blob = self.blob.clone();
+        blob[self.reserved_offset..end].copy_from_slice(&extra_nonce.to_be_bytes()[8 - len..]);
+        Ok(blob)
+    }
+
+    /// Rebuilds the hashing blob for the given extra nonce
+    ///
+    /// The hashing blob is the block header followed by the merkle root of all
+    /// transaction hashes (coinbase first) and the transaction count.
+    ///
+    /// # Arguments
+    /// * `extra_nonce` - Extra nonce written into the coinbase reserved area
+    ///
+    /// # Errors
+    /// Returns `MinerError::ProtocolError` if the template cannot be parsed
+    pub fn hashing_blob(&self, extra_nonce: u64) -> Result<Vec<u8>, MinerError> {
+        let blob = self.with_extra_nonce(extra_nonce)?;
+        let layout = TemplateLayout::parse(&blob)?;
+
+        let mut hashes = Vec::with_capacity(layout.tx_hashes.len() + 1);
+        hashes.push(layout.miner_tx_hash(&blob));
+        hashes.extend(layout.tx_hashes.iter().copied());
+
+        let mut hashing_blob = blob[..layout.header_end].to_vec();
+        hashing_blob.extend_from_slice(&tree_hash(&hashes));
+        write_varint(&mut hashing_blob, hashes.len() as u64);
+        Ok(hashing_blob)
+    }
+
+    /// Assembles the final block blob for submission
+    ///
+    /// # Arguments
+    /// * `extra_nonce` - Extra nonce the share was found with
+    /// * `nonce` - Header nonce that solved the block (lower 32 bits are used,
+    ///   as written into the hashing blob)
+    ///
+    /// # Errors
+    /// Returns `MinerError::ProtocolError` if the template cannot be parsed
+    pub fn block_blob(&self, extra_nonce: u64, nonce: u64) -> Result<Vec<u8>, MinerError> {
+        let mut blob = self.with_extra_nonce(extra_nonce)?;
+        let layout = TemplateLayout::parse(&blob)?;
+        let start = layout.header_end - NONCE_SIZE;
+        blob[start..layout.header_end].copy_from_slice(&(nonce as u32).to_le_bytes());
+        Ok(blob)
+    }
+}
+
+/// Byte positions of the interesting parts of a block template blob
+struct TemplateLayout {
+    /// End of the block header (just past the nonce field)
+    header_end: usize,
+    /// End of the coinbase transaction prefix (start of its RingCT section)
+    prefix_end: usize,
+    /// End of the coinbase transaction
+    miner_tx_end: usize,
+    /// Coinbase transaction version
+    version: u64,
+    /// Hashes of the non-coinbase transactions
+    tx_hashes: Vec<[u8; 32]>,
+}
+
+impl TemplateLayout {
+    /// Walks a block blob and records where each section ends
+    fn parse(blob: &[u8]) -> Result<Self, MinerError> {
+        let mut reader = Reader { blob, pos: 0 };
+
+        // Block header: major, minor, timestamp, prev_id, nonce
+        reader.varint()?;
+        reader.varint()?;
+        reader.varint()?;
+        reader.bytes(32)?;
+        reader.bytes(NONCE_SIZE)?;
+        let header_end = reader.pos;
+
+        // Coinbase transaction prefix
+        let version = reader.varint()?;
+        reader.varint()?; // unlock_time
+        for _ in 0..reader.varint()? {
+            match reader.byte()? {
+                TXIN_GEN => {
+                    reader.varint()?; // height
+                }
+                tag => {
+                    return Err(MinerError::ProtocolError(format!(
+                        "Unexpected coinbase input tag {:#04x}",
+                        tag
+                    )));
+                }
+            }
+        }
+        for _ in 0..reader.varint()? {
+            reader.varint()?; // amount
+            match reader.byte()? {
+                TXOUT_TO_KEY => reader.bytes(32)?,
+                TXOUT_TO_TAGGED_KEY => reader.bytes(33)?,
+                tag => {
+                    return Err(MinerError::ProtocolError(format!(
+                        "Unexpected coinbase output tag {:#04x}",
+                        tag
+                    )));
+                }
+            };
+        }
+        let extra_len = reader.varint()? as usize;
+        reader.bytes(extra_len)?;
+        let prefix_end = reader.pos;
+
+        // Coinbase RingCT section is just the (null) signature type
+        if version >= 2 {
+            reader.byte()?;
+        }
+        let miner_tx_end = reader.pos;
+
+        let tx_hashes = (0..reader.varint()?)
+            .map(|_| {
+                reader
+                    .bytes(32)
+                    .map(|h| h.try_into().expect("32-byte slice"))
+            })
+            .collect::<Result<Vec<[u8; 32]>, MinerError>>()?;
+
+        Ok(TemplateLayout {
+            header_end,
+            prefix_end,
+            miner_tx_end,
+            version,
+            tx_hashes,
+        })
+    }
+
+    /// Computes the coinbase transaction hash
+    fn miner_tx_hash(&self, blob: &[u8]) -> [u8; 32] {
+        let tx = &blob[self.header_end..self.miner_tx_end];
+        if self.version < 2 {
+            return keccak(tx);
+        }
+
+        let prefix_len = self.prefix_end - self.header_end;
+        let mut parts = Vec::with_capacity(96);
+        parts.extend_from_slice(&keccak(&tx[..prefix_len]));
+        parts.extend_from_slice(&keccak(&tx[prefix_len..]));
+        parts.extend_from_slice(&[0u8; 32]); // No prunable data for coinbase
+        keccak(&parts)
+    }
+}
+
+/// Minimal cursor over a blob with CryptoNote varint support
+struct Reader<'a> {
+    blob: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, MinerError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], MinerError> {
+        let end = self.pos + len;
+        let slice = self
+            .blob
+            .get(self.pos..end)
+            .ok_or_else(|| MinerError::ProtocolError("Truncated block template".to_string()))?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn varint(&mut self) -> Result<u64, MinerError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(MinerError::ProtocolError(
+            "Varint overflow in block template".to_string(),
+        ))
+    }
+}
+
+/// Appends a CryptoNote varint to `out`
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Keccak-256 (CryptoNote `cn_fast_hash`)
+fn keccak(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+/// Keccak of two concatenated hashes
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// CryptoNote merkle tree root of the given transaction hashes
+fn tree_hash(hashes: &[[u8; 32]]) -> [u8; 32] {
+    match hashes.len() {
+        0 => [0u8; 32],
+        1 => hashes[0],
+        2 => hash_pair(&hashes[0], &hashes[1]),
+        count => {
+            // Largest power of two strictly below `count`
+            let cnt = count.next_power_of_two() >> 1;
+            let direct = 2 * cnt - count;
+            let mut level: Vec<[u8; 32]> = hashes[..direct].to_vec();
+            level.extend(hashes[direct..].chunks(2).map(|p| hash_pair(&p[0], &p[1])));
+
+            while level.len() > 2 {
+                level = level.chunks(2).map(|p| hash_pair(&p[0], &p[1])).collect();
+            }
+            hash_pair(&level[0], &level[1])
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Size of the extra nonce reserved in test templates
+    const RESERVE_SIZE: usize = 8;
+
+    fn leaf(byte: u8) -> [u8; 32] {
+        [byte; 32]
+    }
+
+    /// Builds a v2 template blob with `tx_count` non-coinbase transactions
+    ///
+    /// # Returns
+    /// The blob and the offset of its reserved area
+    fn template_blob(tx_count: usize) -> (Vec<u8>, usize) {
+        let mut blob = vec![16, 16];
+        write_varint(&mut blob, 1_700_000_000);
+        blob.extend_from_slice(&[0xaa; 32]); // prev_id
+        blob.extend_from_slice(&[0; NONCE_SIZE]);
+
+        // Coinbase: version, unlock_time, one txin_gen, one tagged output
+        blob.push(2);
+        write_varint(&mut blob, 3_000_060);
+        blob.extend_from_slice(&[1, TXIN_GEN]);
+        write_varint(&mut blob, 3_000_000);
+        blob.push(1);
+        write_varint(&mut blob, 600_000_000_000);
+        blob.push(TXOUT_TO_TAGGED_KEY);
+        blob.extend_from_slice(&[0xbb; 33]);
+        // Extra: tx public key, then the nonce field holding the reserved area
+        let mut extra = vec![0x01];
+        extra.extend_from_slice(&[0xcc; 32]);
+        extra.extend_from_slice(&[0x02, RESERVE_SIZE as u8]);
+        let reserved_offset = blob.len() + 1 + extra.len();
+        extra.extend_from_slice(&[0; RESERVE_SIZE]);
+        write_varint(&mut blob, extra.len() as u64);
+        blob.extend_from_slice(&extra);
+        blob.push(0); // RCTTypeNull
+
+        write_varint(&mut blob, tx_count as u64);
+        for tx in 0..tx_count {
+            blob.extend_from_slice(&leaf(tx as u8));
+        }
+        (blob, reserved_offset)
+    }
+
+    /// Template for height 3,000,000 with `tx_count` transactions
+    pub(crate) fn template(tx_count: usize) -> BlockTemplate {
+        let (blob, reserved_offset) = template_blob(tx_count);
+        BlockTemplate {
+            blob,
+            reserved_offset,
+            reserve_size: RESERVE_SIZE,
+            height: 3_000_000,
+            difficulty: 300_000_000_000,
+            seed_hash: String::new(),
+            rig_id: 0x0a0b_0c0d,
+        }
+    }
+
+    #[test]
+    fn tree_hash_of_small_trees() {
+        let (a, b, c, d, e) = (leaf(1), leaf(2), leaf(3), leaf(4), leaf(5));
+        assert_eq!(tree_hash(&[]), [0; 32]);
+        assert_eq!(tree_hash(&[a]), a);
+        assert_eq!(tree_hash(&[a, b]), hash_pair(&a, &b));
+        // The last leaves are paired first so the next level is a power of two
+        assert_eq!(tree_hash(&[a, b, c]), hash_pair(&a, &hash_pair(&b, &c)));
+        assert_eq!(
+            tree_hash(&[a, b, c, d]),
+            hash_pair(&hash_pair(&a, &b), &hash_pair(&c, &d))
+        );
+        assert_eq!(
+            tree_hash(&[a, b, c, d, e]),
+            hash_pair(&hash_pair(&a, &b), &hash_pair(&c, &hash_pair(&d, &e)))
+        );
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        for (value, encoded) in [
+            (0, vec![0x00]),
+            (127, vec![0x7f]),
+            (128, vec![0x80, 0x01]),
+            (300, vec![0xac, 0x02]),
+            (u64::MAX, [vec![0xff; 9], vec![0x01]].concat()),
+        ] {
+            let mut out = Vec::new();
+            write_varint(&mut out, value);
+            assert_eq!(out, encoded);
+            let mut reader = Reader { blob: &out, pos: 0 };
+            assert_eq!(reader.varint().unwrap(), value);
+            assert_eq!(reader.pos, out.len());
+        }
+
+        let mut reader = Reader {
+            blob: &[0x80, 0x80],
+            pos: 0,
+        };
+        assert!(reader.varint().is_err());
+    }
+
+    #[test]
+    fn hashing_blob_ends_with_root_and_varint_tx_count() {
+        for (tx_count, encoded) in [(0, vec![1]), (2, vec![3]), (127, vec![0x80, 0x01])] {
+            let template = template(tx_count);
+            let blob = template.with_extra_nonce(7).unwrap();
+            let layout = TemplateLayout::parse(&blob).unwrap();
+            assert_eq!(layout.tx_hashes.len(), tx_count);
+
+            let mut leaves = vec![layout.miner_tx_hash(&blob)];
+            leaves.extend((0..tx_count).map(|tx| leaf(tx as u8)));
+            let hashing_blob = template.hashing_blob(7).unwrap();
+            let (header, rest) = hashing_blob.split_at(layout.header_end);
+            assert_eq!(header, &blob[..layout.header_end]);
+            assert_eq!(&rest[..32], &tree_hash(&leaves));
+            assert_eq!(&rest[32..], &encoded);
+        }
+    }
+
+    #[test]
+    fn extra_nonce_is_big_endian_at_reserved_offset() {
+        let template = template(1);
+        let offset = template.reserved_offset;
+        assert_eq!(template.extra_nonce(5), 0x0a0b_0c0d_0000_0005);
+
+        let blob = template.with_extra_nonce(0x0102_0304_0506_0708).unwrap();
+        assert_eq!(&blob[offset..offset + 8], &[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(&blob[..offset], &template.blob[..offset]);
+        assert_eq!(&blob[offset + 8..], &template.blob[offset + 8..]);
+
+        // A smaller reserved area keeps the low-order bytes
+        let mut small = template.clone();
+        small.reserve_size = 4;
+        let blob = small.with_extra_nonce(0x0102_0304_0506_0708).unwrap();
+        assert_eq!(&blob[offset..offset + 4], &[5, 6, 7, 8]);
+        assert_eq!(&blob[offset + 4..offset + 8], &[0; 4]);
+
+        // Each extra nonce changes the merkle root, not the header
+        let first = template.hashing_blob(template.extra_nonce(0)).unwrap();
+        let second = template.hashing_blob(template.extra_nonce(1)).unwrap();
+        let header_end = first.len() - 33;
+        assert_eq!(first[..header_end], second[..header_end]);
+        assert_ne!(first[header_end..], second[header_end..]);
+
+        let mut outside = template.clone();
+        outside.reserved_offset = outside.blob.len() - 4;
+        assert!(outside.with_extra_nonce(1).is_err());
+    }
+
+    #[test]
+    fn block_blob_carries_nonce_and_extra_nonce() {
+        let template = template(2);
+        let blob = template
+            .block_blob(0x0a0b_0c0d_0000_0001, 0x1_dead_beef)
+            .unwrap();
+        let layout = TemplateLayout::parse(&blob).unwrap();
+
+        assert_eq!(
+            &blob[layout.header_end - NONCE_SIZE..layout.header_end],
+            &0xdead_beefu32.to_le_bytes()
+        );
+        let offset = template.reserved_offset;
+        assert_eq!(&blob[offset..offset + 8], &[10, 11, 12, 13, 0, 0, 0, 1]);
+        assert_eq!(blob.len(), template.blob.len());
+    }
+}
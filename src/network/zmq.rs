@@ -0,0 +1,153 @@
+// src/network/zmq.rs
+//! ZeroMQ chain notifications
+//!
+//! Subscribes to a Monero node's ZMQ publisher (`--zmq-pub`) so new blocks and
+//! transaction pool additions are seen immediately instead of on the next poll.
+//! Any publisher emitting the same `topic:json` frames (e.g. a local test
+//! stand-in bound with `zeromq::PubSocket`) can be used.
+
+use crate::utils::error::MinerError;
+use serde_json::Value;
+use zeromq::{Socket, SocketRecv, SubSocket};
+
+/// Topic published by the node when the main chain advances
+pub const CHAIN_MAIN_TOPIC: &str = "json-minimal-chain_main";
+
+/// Topic published by the node when transactions enter the pool
+pub const TXPOOL_ADD_TOPIC: &str = "json-minimal-txpool_add";
+
+/// Notification received from the node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainEvent {
+    /// The main chain advanced; carries the height of the new tip
+    NewBlock {
+        /// Height of the newest block
+        height: u64,
+    },
+    /// New transactions entered the pool
+    TxPoolAdd {
+        /// Number of transactions added
+        count: usize,
+    },
+}
+
+/// Subscriber for node ZMQ notifications
+pub struct ZmqSubscriber {
+    /// Underlying SUB socket
+    socket: SubSocket,
+}
+
+impl ZmqSubscriber {
+    /// Connects to a node's ZMQ publisher and subscribes to chain and pool topics
+    ///
+    /// # Arguments
+    /// * `url` - Publisher endpoint (e.g., "tcp://127.0.0.1:18083")
+    ///
+    /// # Errors
+    /// Returns `MinerError::ConnectionError` if the connection or subscription fails
+    pub async fn connect(url: &str) -> Result<Self, MinerError> {
+        let mut socket = SubSocket::new();
+        socket.connect(url).await?;
+        socket.subscribe(CHAIN_MAIN_TOPIC).await?;
+        socket.subscribe(TXPOOL_ADD_TOPIC).await?;
+        Ok(ZmqSubscriber { socket })
+    }
+
+    /// Waits for the next chain notification
+    ///
+    /// Messages on unknown topics are skipped.
+    ///
+    /// # Errors
+    /// Returns `MinerError` if the socket fails or a message is malformed
+    pub async fn next_event(&mut self) -> Result<ChainEvent, MinerError> {
+        loop {
+            let message = self.socket.recv().await?;
+            let frames: Vec<u8> = message
+                .into_vec()
+                .into_iter()
+                .flat_map(|frame| frame.to_vec())
+                .collect();
+
+            if let Some(event) = parse_event(&String::from_utf8_lossy(&frames))? {
+                return Ok(event);
+            }
+        }
+    }
+}
+
+/// Parses a `topic:json` notification
+///
+/// # Returns
+/// * `Ok(Some(ChainEvent))` - For chain and pool notifications
+/// * `Ok(None)` - For other topics
+/// * `Err(MinerError)` - If the payload is malformed
+fn parse_event(message: &str) -> Result<Option<ChainEvent>, MinerError> {
+    let (topic, payload) = message
+        .split_once(':')
+        .ok_or_else(|| MinerError::ProtocolError("ZMQ message without topic".to_string()))?;
+
+    match topic {
+        CHAIN_MAIN_TOPIC => {
+            let json: Value = serde_json::from_str(payload)?;
+            let first_height = json["first_height"].as_u64().ok_or_else(|| {
+                MinerError::ProtocolError("Missing first_height in chain_main".to_string())
+            })?;
+            let blocks = json["ids"].as_array().map_or(1, |ids| ids.len().max(1)) as u64;
+            Ok(Some(ChainEvent::NewBlock {
+                height: first_height + blocks - 1,
+            }))
+        }
+        TXPOOL_ADD_TOPIC => {
+            let json: Value = serde_json::from_str(payload)?;
+            Ok(Some(ChainEvent::TxPoolAdd {
+                count: json.as_array().map_or(0, |txs| txs.len()),
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chain_main_reports_new_tip() {
+        let message = r#"json-minimal-chain_main:{"first_height":3000000,"first_prev_id":"aa","ids":["bb","cc"]}"#;
+        assert_eq!(
+            parse_event(message).unwrap(),
+            Some(ChainEvent::NewBlock { height: 3_000_001 })
+        );
+    }
+
+    #[test]
+    fn chain_main_without_ids_is_one_block() {
+        let message = r#"json-minimal-chain_main:{"first_height":42}"#;
+        assert_eq!(
+            parse_event(message).unwrap(),
+            Some(ChainEvent::NewBlock { height: 42 })
+        );
+    }
+
+    #[test]
+    fn txpool_add_counts_transactions() {
+        let message = r#"json-minimal-txpool_add:[{"id":"aa","blob_size":1500,"weight":1500,"fee":30000},{"id":"bb","blob_size":900,"weight":900,"fee":20000}]"#;
+        assert_eq!(
+            parse_event(message).unwrap(),
+            Some(ChainEvent::TxPoolAdd { count: 2 })
+        );
+    }
+
+    #[test]
+    fn other_topics_are_skipped() {
+        assert_eq!(parse_event("json-full-chain_main:[]").unwrap(), None);
+    }
+
+    #[test]
+    fn malformed_messages_are_errors() {
+        assert!(parse_event("json-minimal-chain_main:{not json").is_err());
+        assert!(parse_event(r#"json-minimal-chain_main:{"ids":[]}"#).is_err());
+        assert!(parse_event("json-minimal-txpool_add:[").is_err());
+        assert!(parse_event("no topic separator").is_err());
+    }
+}
@@ -0,0 +1,76 @@
+// src/stats/benchmark.rs
+//! Stored benchmark results
+//!
+//! The `benchmark` subcommand records the measured hashrate of each algorithm
+//! in a small JSON file. Pool logins read it back to advertise per-algorithm
+//! performance (`algo-perf`) so multi-algo pools can pick the best work.
+//! The CPU features and RandomX flags of the host are stored alongside, so
+//! results from different machines can be compared.
+
+use crate::miner::features::{CpuFeatures, RandomXFlags};
+use crate::types::AlgorithmType;
+use crate::utils::error::MinerError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Benchmark hashrates keyed by Stratum algorithm name (e.g. "rx/0")
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BenchmarkResults {
+    /// Measured hashrate in H/s per algorithm
+    pub hashrates: BTreeMap<String, f64>,
+    /// CPU features of the host that produced the results
+    #[serde(default)]
+    pub cpu_features: Vec<String>,
+    /// RandomX flags in effect during the benchmark
+    #[serde(default)]
+    pub randomx_flags: Vec<String>,
+}
+
+impl BenchmarkResults {
+    /// Loads stored results
+    ///
+    /// # Arguments
+    /// * `path` - Path to the results file
+    ///
+    /// # Returns
+    /// * `Ok(BenchmarkResults)` - Stored results, or empty results if the file does not exist
+    /// * `Err(MinerError)` - If the file couldn't be read or parsed
+    pub fn load(path: &Path) -> Result<Self, MinerError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Writes the results to `path`
+    ///
+    /// # Errors
+    /// Returns `MinerError` if the file couldn't be written
+    pub fn save(&self, path: &Path) -> Result<(), MinerError> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Records the hashrate measured for an algorithm
+    pub fn record(&mut self, algorithm: AlgorithmType, hashrate: f64) {
+        self.hashrates
+            .insert(algorithm.stratum_name().to_string(), hashrate);
+    }
+
+    /// Records the host the results were measured on
+    ///
+    /// # Arguments
+    /// * `features` - Detected CPU features
+    /// * `flags` - RandomX flags in effect
+    pub fn record_host(&mut self, features: &CpuFeatures, flags: &RandomXFlags) {
+        self.cpu_features = features.names().into_iter().map(String::from).collect();
+        self.randomx_flags = flags.names().into_iter().map(String::from).collect();
+    }
+
+    /// Returns the stored hashrate for an algorithm, if benchmarked
+    pub fn hashrate(&self, algorithm: AlgorithmType) -> Option<f64> {
+        self.hashrates.get(algorithm.stratum_name()).copied()
+    }
+}
@@ -19,5 +19,11 @@
 /// - Thread-safe communication channels for receiving data
 pub mod reporter;
 
+/// Submodule storing benchmark results between runs
+///
+/// Used to advertise per-algorithm performance to pools on login.
+pub mod benchmark;
+
 // Re-export main components
-pub use reporter::{HardwareStats, MiningStats, StatsReporter};
+pub use benchmark::BenchmarkResults;
+pub use reporter::{HardwareStats, MiningStats, ShareResult, StatsReporter, cpu_temperature};
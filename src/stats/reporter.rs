@@ -1,9 +1,11 @@
 // src/stats/reporter.rs
+use crate::types::RejectReason;
 use crossbeam_channel::{Receiver, Sender};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
-use sysinfo::{Components, System};
+use sysinfo::{Components, ProcessRefreshKind, ProcessesToUpdate, System};
 //use crate::miner::scheduler::Share;
 //use crate::utils::error::MinerError;
 
@@ -16,6 +18,16 @@ pub struct MiningStats {
     pub shares_accepted: u64,
     /// Number of shares rejected by the mining pool/node
     pub shares_rejected: u64,
+    /// Rejected shares by the reason the pool gave
+    pub rejected_by_reason: BTreeMap<RejectReason, u64>,
+    /// Number of pool shares that also met the network difficulty
+    pub block_candidates: u64,
+    /// Number of shares dropped because their hash did not verify locally
+    pub hardware_errors: u64,
+    /// Number of shares dropped because their job was no longer live
+    pub stale_shares: u64,
+    /// Number of shares dropped because they were already submitted
+    pub duplicate_shares: u64,
     /// Average hashrate over 1 minute (hashes per second)
     pub avg_hashrate_1m: f64,
     /// Average hashrate over 15 minutes (hashes per second)
@@ -27,6 +39,8 @@ pub struct MiningStats {
 pub struct HardwareStats {
     /// Current CPU usage percentage (0-100)
     pub cpu_usage: f32,
+    /// CPU usage of processes other than the miner, as a share of the host (0-100)
+    pub foreign_cpu_usage: f32,
     /// Memory currently used by the process (in bytes)
     pub memory_used: u64,
     /// Current CPU temperature in Celsius
@@ -50,6 +64,11 @@ struct MiningStatsAtomic {
     hashes: AtomicU64,
     accepted: AtomicU64,
     rejected: AtomicU64,
+    rejected_by_reason: [AtomicU64; RejectReason::ALL.len()],
+    block_candidates: AtomicU64,
+    hardware_errors: AtomicU64,
+    stale_shares: AtomicU64,
+    duplicate_shares: AtomicU64,
     start_time: Instant,
 }
 
@@ -75,6 +94,11 @@ impl StatsReporter {
                 hashes: AtomicU64::new(0),
                 accepted: AtomicU64::new(0),
                 rejected: AtomicU64::new(0),
+                rejected_by_reason: std::array::from_fn(|_| AtomicU64::new(0)),
+                block_candidates: AtomicU64::new(0),
+                hardware_errors: AtomicU64::new(0),
+                stale_shares: AtomicU64::new(0),
+                duplicate_shares: AtomicU64::new(0),
                 start_time: Instant::now(),
             }),
             system: System::new_all(),
@@ -115,6 +139,16 @@ impl StatsReporter {
             hashes_total: hashes,
             shares_accepted: self.stats.accepted.load(Ordering::Relaxed),
             shares_rejected: self.stats.rejected.load(Ordering::Relaxed),
+            rejected_by_reason: RejectReason::ALL
+                .iter()
+                .zip(&self.stats.rejected_by_reason)
+                .map(|(reason, count)| (*reason, count.load(Ordering::Relaxed)))
+                .filter(|(_, count)| *count > 0)
+                .collect(),
+            block_candidates: self.stats.block_candidates.load(Ordering::Relaxed),
+            hardware_errors: self.stats.hardware_errors.load(Ordering::Relaxed),
+            stale_shares: self.stats.stale_shares.load(Ordering::Relaxed),
+            duplicate_shares: self.stats.duplicate_shares.load(Ordering::Relaxed),
             avg_hashrate_1m: hashes as f64 / total_seconds.max(60.0) * 60.0,
             avg_hashrate_15m: hashes as f64 / total_seconds.max(900.0) * 900.0,
         }
@@ -122,32 +156,42 @@ impl StatsReporter {
 
     /// Gets the current hardware statistics
     ///
-    /// This refreshes system information before returning the stats.
+    /// This refreshes system information before returning the stats. CPU
+    /// usage is measured since the previous call.
     ///
     /// # Returns
     /// A snapshot of the current hardware statistics
     pub fn get_hardware_stats(&mut self) -> HardwareStats {
+        let pid = sysinfo::get_current_pid().ok();
         self.system.refresh_cpu_all();
+        if let Some(pid) = pid {
+            self.system.refresh_processes_specifics(
+                ProcessesToUpdate::Some(&[pid]),
+                true,
+                ProcessRefreshKind::nothing().with_cpu(),
+            );
+        }
         self.system.refresh_memory();
         self.components.refresh(true);
 
+        let cpus = self.system.cpus().len() as f32;
         let cpu_usage = self
             .system
             .cpus()
             .iter()
             .map(|c| c.cpu_usage())
             .sum::<f32>()
-            / self.system.cpus().len() as f32;
+            / cpus;
+        // Process usage is reported per logical CPU (100% = one CPU)
+        let own_usage = pid
+            .and_then(|pid| self.system.process(pid))
+            .map_or(0.0, |process| process.cpu_usage() / cpus);
 
-        let temperature = self
-            .components
-            .iter()
-            .find(|c| c.label().contains("CPU"))
-            .and_then(|c| c.temperature())
-            .unwrap_or(0.0);
+        let temperature = cpu_temperature(&self.components).unwrap_or(0.0);
 
         HardwareStats {
             cpu_usage,
+            foreign_cpu_usage: (cpu_usage - own_usage).max(0.0),
             memory_used: self.system.used_memory(),
             temperature,
         }
@@ -174,13 +218,25 @@ impl StatsReporter {
                 let hw_stats = reporter.get_hardware_stats();
 
                 log::info!(
-                    "Hashrate: {:.2} H/s | Accepted/Rejected: {}/{} | CPU: {:.1}% | Temp: {:.1}°C",
+                    "Hashrate: {:.2} H/s | Accepted/Rejected: {}/{} | Stale/Duplicate: {}/{} | Block candidates: {} | HW errors: {} | CPU: {:.1}% | Temp: {:.1}°C",
                     mining_stats.avg_hashrate_1m,
                     mining_stats.shares_accepted,
                     mining_stats.shares_rejected,
+                    mining_stats.stale_shares,
+                    mining_stats.duplicate_shares,
+                    mining_stats.block_candidates,
+                    mining_stats.hardware_errors,
                     hw_stats.cpu_usage,
                     hw_stats.temperature
                 );
+                if !mining_stats.rejected_by_reason.is_empty() {
+                    let reasons: Vec<String> = mining_stats
+                        .rejected_by_reason
+                        .iter()
+                        .map(|(reason, count)| format!("{}: {}", reason, count))
+                        .collect();
+                    log::info!("Rejected shares by reason: {}", reasons.join(", "));
+                }
             }
         });
     }
@@ -193,7 +249,20 @@ impl StatsReporter {
             for result in receiver {
                 match result {
                     ShareResult::Accepted => stats.accepted.fetch_add(1, Ordering::Relaxed),
-                    ShareResult::Rejected => stats.rejected.fetch_add(1, Ordering::Relaxed),
+                    ShareResult::Rejected(reason) => {
+                        stats.rejected_by_reason[reason as usize].fetch_add(1, Ordering::Relaxed);
+                        stats.rejected.fetch_add(1, Ordering::Relaxed)
+                    }
+                    ShareResult::BlockCandidate => {
+                        stats.block_candidates.fetch_add(1, Ordering::Relaxed)
+                    }
+                    ShareResult::HardwareError => {
+                        stats.hardware_errors.fetch_add(1, Ordering::Relaxed)
+                    }
+                    ShareResult::Stale => stats.stale_shares.fetch_add(1, Ordering::Relaxed),
+                    ShareResult::Duplicate => {
+                        stats.duplicate_shares.fetch_add(1, Ordering::Relaxed)
+                    }
                 };
             }
         });
@@ -216,6 +285,37 @@ impl StatsReporter {
 pub enum ShareResult {
     /// The share was accepted as valid
     Accepted,
-    /// The share was rejected (likely invalid)
-    Rejected,
+    /// The share was rejected, for the given reason
+    Rejected(RejectReason),
+    /// The share also met the network difficulty and may solve a block
+    BlockCandidate,
+    /// The share's hash did not reproduce locally and was not submitted
+    HardwareError,
+    /// The share's job was no longer live and it was not submitted
+    Stale,
+    /// The share was already submitted and was not sent again
+    Duplicate,
+}
+
+/// Labels of temperature sensors that report CPU temperature
+const CPU_SENSOR_LABELS: [&str; 6] = ["CPU", "Package", "Tctl", "Tdie", "coretemp", "k10temp"];
+
+/// Reads the hottest CPU temperature sensor
+///
+/// # Arguments
+/// * `components` - Refreshed sensor list
+///
+/// # Returns
+/// The temperature in Celsius, or `None` if no CPU sensor is readable
+pub fn cpu_temperature(components: &Components) -> Option<f32> {
+    components
+        .iter()
+        .filter(|c| {
+            CPU_SENSOR_LABELS
+                .iter()
+                .any(|label| c.label().contains(label))
+        })
+        .filter_map(|c| c.temperature())
+        .filter(|t| t.is_finite())
+        .reduce(f32::max)
 }
@@ -9,7 +9,7 @@ use std::str::FromStr;
 /// This enum represents the different proof-of-work algorithms
 /// that can be used for Monero mining, each with different
 /// performance characteristics and hardware requirements.
-#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, ValueEnum, Serialize, Deserialize)]
 pub enum AlgorithmType {
     /// RandomX algorithm (CPU-optimized, ASIC-resistant)
     ///
@@ -43,15 +43,210 @@ impl fmt::Display for AlgorithmType {
     }
 }
 
+impl AlgorithmType {
+    /// Returns every algorithm supported by this build
+    pub fn supported() -> &'static [AlgorithmType] {
+        AlgorithmType::value_variants()
+    }
+
+    /// Returns the algorithm name used by Stratum pools (e.g. "rx/0")
+    pub fn stratum_name(&self) -> &'static str {
+        match self {
+            AlgorithmType::RandomX => "rx/0",
+            AlgorithmType::CryptoNightV7 => "cn/1",
+            AlgorithmType::CryptoNightR => "cn/r",
+        }
+    }
+
+    /// Returns the per-thread scratchpad size in bytes
+    ///
+    /// Each hashing thread works best with its scratchpad resident in the
+    /// shared L3 cache, so this bounds how many threads a cache can feed.
+    pub fn scratchpad_size(&self) -> u64 {
+        match self {
+            AlgorithmType::RandomX => 2 * 1024 * 1024,
+            AlgorithmType::CryptoNightV7 | AlgorithmType::CryptoNightR => 2 * 1024 * 1024,
+        }
+    }
+}
+
 impl FromStr for AlgorithmType {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
-            "randomx" => Ok(AlgorithmType::RandomX),
-            "cnv7" | "cryptonight-v7" => Ok(AlgorithmType::CryptoNightV7),
-            "cnr" | "cryptonight-r" => Ok(AlgorithmType::CryptoNightR),
+            "randomx" | "rx/0" => Ok(AlgorithmType::RandomX),
+            "cnv7" | "cryptonight-v7" | "cn/1" => Ok(AlgorithmType::CryptoNightV7),
+            "cnr" | "cryptonight-r" | "cn/r" => Ok(AlgorithmType::CryptoNightR),
             _ => Err(format!("Unknown algorithm: {}", s)),
         }
     }
 }
+
+/// Reason a pool gave for rejecting a share or request
+///
+/// Pools only send free-form error messages; [`RejectReason::classify`] maps
+/// the wording used by common pool software onto these classes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum RejectReason {
+    /// The share hash does not meet the job target
+    LowDifficulty,
+    /// The share's job was replaced by a newer block
+    Stale,
+    /// The same nonce was already submitted for the job
+    Duplicate,
+    /// The pool does not know the job id
+    InvalidJobId,
+    /// The nonce or result was malformed or failed the pool's checks
+    InvalidShare,
+    /// The session is not (or no longer) logged in
+    Unauthenticated,
+    /// The pool banned this miner or address
+    Banned,
+    /// Any message not recognized above
+    Other,
+}
+
+impl RejectReason {
+    /// Every reason, in counting order
+    pub const ALL: [RejectReason; 8] = [
+        RejectReason::LowDifficulty,
+        RejectReason::Stale,
+        RejectReason::Duplicate,
+        RejectReason::InvalidJobId,
+        RejectReason::InvalidShare,
+        RejectReason::Unauthenticated,
+        RejectReason::Banned,
+        RejectReason::Other,
+    ];
+
+    /// Classifies a pool error message
+    ///
+    /// # Arguments
+    /// * `message` - Error message sent by the pool (e.g. "Low difficulty share")
+    pub fn classify(message: &str) -> Self {
+        let message = message.to_lowercase();
+        let has = |words: &[&str]| words.iter().any(|word| message.contains(word));
+
+        if has(&["low difficulty", "low diff", "high-hash", "above target"]) {
+            RejectReason::LowDifficulty
+        } else if has(&["duplicate"]) {
+            RejectReason::Duplicate
+        } else if has(&["stale", "expired", "outdated"]) {
+            RejectReason::Stale
+        } else if has(&["job id", "job_id", "job not found", "unknown job"]) {
+            RejectReason::InvalidJobId
+        } else if has(&[
+            "unauthenticated",
+            "unauthorized",
+            "not logged in",
+            "not authorized",
+            "invalid login",
+        ]) {
+            RejectReason::Unauthenticated
+        } else if has(&["banned", "blocked"]) {
+            RejectReason::Banned
+        } else if has(&["invalid nonce", "invalid result", "malformed", "bad share"]) {
+            RejectReason::InvalidShare
+        } else {
+            RejectReason::Other
+        }
+    }
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectReason::LowDifficulty => write!(f, "low difficulty"),
+            RejectReason::Stale => write!(f, "stale"),
+            RejectReason::Duplicate => write!(f, "duplicate"),
+            RejectReason::InvalidJobId => write!(f, "invalid job id"),
+            RejectReason::InvalidShare => write!(f, "invalid share"),
+            RejectReason::Unauthenticated => write!(f, "unauthenticated"),
+            RejectReason::Banned => write!(f, "banned"),
+            RejectReason::Other => write!(f, "other"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_common_pool_messages() {
+        let cases = [
+            ("Low difficulty share", RejectReason::LowDifficulty),
+            ("Share above target", RejectReason::LowDifficulty),
+            ("high-hash", RejectReason::LowDifficulty),
+            ("Stale", RejectReason::Stale),
+            ("Block expired", RejectReason::Stale),
+            ("Duplicate share", RejectReason::Duplicate),
+            ("Invalid job id", RejectReason::InvalidJobId),
+            ("Job not found", RejectReason::InvalidJobId),
+            ("Unauthenticated", RejectReason::Unauthenticated),
+            ("Invalid login", RejectReason::Unauthenticated),
+            ("IP Address currently banned", RejectReason::Banned),
+            (
+                "Invalid nonce; is miner not compatible with NiceHash?",
+                RejectReason::InvalidShare,
+            ),
+            ("Invalid result", RejectReason::InvalidShare),
+            ("Malformed share", RejectReason::InvalidShare),
+            ("Internal server error", RejectReason::Other),
+            ("", RejectReason::Other),
+        ];
+        for (message, reason) in cases {
+            assert_eq!(RejectReason::classify(message), reason, "{:?}", message);
+        }
+    }
+
+    #[test]
+    fn classification_ignores_case() {
+        assert_eq!(
+            RejectReason::classify("LOW DIFFICULTY SHARE"),
+            RejectReason::LowDifficulty
+        );
+        assert_eq!(RejectReason::classify("STALE SHARE"), RejectReason::Stale);
+    }
+
+    #[test]
+    fn earlier_keywords_win() {
+        // Low difficulty is checked before the generic invalid share wording
+        assert_eq!(
+            RejectReason::classify("Invalid share: low difficulty"),
+            RejectReason::LowDifficulty
+        );
+        // Duplicate before stale: "duplicate share for expired job" is a duplicate
+        assert_eq!(
+            RejectReason::classify("Duplicate share for expired job"),
+            RejectReason::Duplicate
+        );
+        // Stale before job id: the pool still knew the job
+        assert_eq!(
+            RejectReason::classify("Stale share, job id outdated"),
+            RejectReason::Stale
+        );
+        // Job id before login: "not authorized" wording is not reached
+        assert_eq!(
+            RejectReason::classify("Unknown job id for unauthorized worker"),
+            RejectReason::InvalidJobId
+        );
+        // Login before ban or invalid share
+        assert_eq!(
+            RejectReason::classify("Unauthenticated: invalid nonce"),
+            RejectReason::Unauthenticated
+        );
+        assert_eq!(
+            RejectReason::classify("Banned: invalid result"),
+            RejectReason::Banned
+        );
+    }
+
+    #[test]
+    fn every_reason_is_counted_at_its_index() {
+        for (index, reason) in RejectReason::ALL.iter().enumerate() {
+            assert_eq!(*reason as usize, index);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+// src/utils/difficulty.rs
+//! Difficulty and share target helpers
+//!
+//! Stratum pools express share difficulty as a compact little-endian target
+//! (4 or 8 bytes). A hash meets a difficulty when its most significant 64 bits
+//! (the last 8 bytes, little-endian) do not exceed `u64::MAX / difficulty`.
+//! Solo jobs carry the full 256-bit network target, also little-endian, the
+//! way monerod reads proof-of-work hashes.
+
+use crate::utils::error::MinerError;
+use std::cmp::Ordering;
+
+/// Converts a difficulty into a 64-bit target
+pub fn target_from_difficulty(difficulty: u64) -> u64 {
+    u64::MAX / difficulty.max(1)
+}
+
+/// Encodes a difficulty as a Stratum target (8 bytes, little-endian hex)
+pub fn encode_target(difficulty: u64) -> String {
+    hex::encode(target_from_difficulty(difficulty).to_le_bytes())
+}
+
+/// Decodes a Stratum target into a difficulty
+///
+/// Accepts both the 4-byte and the 8-byte target encodings.
+///
+/// # Errors
+/// Returns `MinerError::InputError` if the target is not valid hex or has an
+/// unsupported length
+pub fn difficulty_from_target(target: &[u8]) -> Result<u64, MinerError> {
+    match target.len() {
+        4 => {
+            let value = u32::from_le_bytes(target.try_into().expect("4-byte target"));
+            Ok(u32::MAX as u64 / (value as u64).max(1))
+        }
+        8 => {
+            let value = u64::from_le_bytes(target.try_into().expect("8-byte target"));
+            Ok(u64::MAX / value.max(1))
+        }
+        len => Err(MinerError::InputError(format!(
+            "Unsupported target length: {} bytes",
+            len
+        ))),
+    }
+}
+
+/// Returns the difficulty a hash satisfies
+pub fn hash_difficulty(hash: &[u8; 32]) -> u64 {
+    let high = u64::from_le_bytes(hash[24..].try_into().expect("8-byte slice"));
+    u64::MAX / high.max(1)
+}
+
+/// Checks whether a hash meets the given difficulty
+pub fn meets_difficulty(hash: &[u8; 32], difficulty: u64) -> bool {
+    let high = u64::from_le_bytes(hash[24..].try_into().expect("8-byte slice"));
+    high <= target_from_difficulty(difficulty)
+}
+
+/// Computes the full 256-bit target `(2^256 - 1) / difficulty`
+///
+/// # Returns
+/// The target as 32 little-endian bytes, comparable with [`meets_target`]
+pub fn full_target(difficulty: u64) -> [u8; 32] {
+    let difficulty = difficulty.max(1) as u128;
+    let mut target = [0u8; 32];
+    let mut remainder = 0u128;
+    // Long division from the most significant byte down
+    for byte in target.iter_mut().rev() {
+        let current = (remainder << 8) | 0xff;
+        remainder = current % difficulty;
+        *byte = (current / difficulty) as u8;
+    }
+    target
+}
+
+/// Checks whether a hash meets a job target
+///
+/// Both are little-endian numbers. 32-byte targets are compared over their
+/// full width; 4- and 8-byte Stratum targets through their difficulty.
+///
+/// # Returns
+/// `false` for targets of any other length
+pub fn meets_target(hash: &[u8; 32], target: &[u8]) -> bool {
+    match target.len() {
+        32 => hash.iter().rev().cmp(target.iter().rev()) != Ordering::Greater,
+        _ => difficulty_from_target(target)
+            .is_ok_and(|difficulty| meets_difficulty(hash, difficulty)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hash whose most significant 64 bits are `high`, with `low` in every lower byte
+    fn hash(high: u64, low: u8) -> [u8; 32] {
+        let mut hash = [low; 32];
+        hash[24..].copy_from_slice(&high.to_le_bytes());
+        hash
+    }
+
+    #[test]
+    fn compact_targets_round_trip() {
+        for difficulty in [1, 2, 1_000, 120_000, 1_000_000] {
+            let target = hex::decode(encode_target(difficulty)).unwrap();
+            assert_eq!(target.len(), 8);
+            assert_eq!(difficulty_from_target(&target).unwrap(), difficulty);
+        }
+
+        // 4-byte targets as sent by many pools
+        let target = (u32::MAX / 5_000).to_le_bytes();
+        assert_eq!(difficulty_from_target(&target).unwrap(), 5_000);
+        assert_eq!(difficulty_from_target(&[0xff; 4]).unwrap(), 1);
+        assert_eq!(difficulty_from_target(&[0; 4]).unwrap(), u32::MAX as u64);
+
+        assert!(difficulty_from_target(&[0xff; 6]).is_err());
+        assert!(difficulty_from_target(&[0xff; 32]).is_err());
+    }
+
+    #[test]
+    fn full_target_matches_compact_target() {
+        assert_eq!(full_target(1), [0xff; 32]);
+        assert_eq!(full_target(0), [0xff; 32]);
+        assert_eq!(full_target(3), [0x55; 32]);
+
+        for difficulty in [2, 7, 1_000, 350_000_000_000] {
+            let target = full_target(difficulty);
+            assert_eq!(
+                u64::from_le_bytes(target[24..].try_into().unwrap()),
+                target_from_difficulty(difficulty)
+            );
+        }
+    }
+
+    #[test]
+    fn compact_target_boundary() {
+        let target = hex::decode(encode_target(1_000)).unwrap();
+        let limit = u64::MAX / 1_000;
+
+        assert!(meets_target(&hash(limit, 0xff), &target));
+        assert!(!meets_target(&hash(limit + 1, 0), &target));
+        assert!(meets_difficulty(&hash(limit, 0), 1_000));
+        assert!(!meets_difficulty(&hash(limit + 1, 0), 1_000));
+        assert_eq!(hash_difficulty(&hash(limit, 0)), 1_000);
+        assert_eq!(hash_difficulty(&[0; 32]), u64::MAX);
+    }
+
+    #[test]
+    fn full_target_boundary() {
+        let target = full_target(3);
+        assert!(meets_target(&target, &target));
+
+        // One more in the least significant byte
+        let mut above = target;
+        above[0] += 1;
+        assert!(!meets_target(&above, &target));
+        let mut below = target;
+        below[31] -= 1;
+        assert!(meets_target(&below, &target));
+
+        // Top 64 bits at the compact limit but larger below them
+        let target = full_target(1_000);
+        let limit = u64::MAX / 1_000;
+        assert!(meets_difficulty(&hash(limit, 0xff), 1_000));
+        assert!(!meets_target(&hash(limit, 0xff), &target));
+
+        assert!(!meets_target(&[0; 32], &[0; 16]));
+    }
+}
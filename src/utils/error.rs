@@ -78,10 +78,11 @@ impl From<crossbeam_channel::SendError<scheduler::Share>> for MinerError {
 
 /// Converts crossbeam channel send errors for MiningJobs into MinerError
 ///
-/// Used when failing to send new mining jobs through inter-thread channels.
-/// Wraps the original error in a `ChannelError` variant with context.
-impl From<crossbeam_channel::SendError<scheduler::MiningJob>> for MinerError {
-    fn from(e: crossbeam_channel::SendError<scheduler::MiningJob>) -> Self {
+/// Used when failing to send new mining jobs (or the withdrawal of a job)
+/// through inter-thread channels. Wraps the original error in a
+/// `ChannelError` variant with context.
+impl From<crossbeam_channel::SendError<Option<scheduler::MiningJob>>> for MinerError {
+    fn from(e: crossbeam_channel::SendError<Option<scheduler::MiningJob>>) -> Self {
         MinerError::ChannelError(format!("Job send failed: {}", e))
     }
 }
@@ -111,3 +112,14 @@ impl From<tokio::task::JoinError> for MinerError {
         MinerError::TaskError(format!("Async task failed: {}", e))
     }
 }
+
+/// Converts ZeroMQ errors into MinerError
+///
+/// Used when the node notification socket fails to connect,
+/// subscribe or receive. Wraps the original error in a
+/// `ConnectionError` variant.
+impl From<zeromq::ZmqError> for MinerError {
+    fn from(e: zeromq::ZmqError) -> Self {
+        MinerError::ConnectionError(format!("ZMQ error: {}", e))
+    }
+}
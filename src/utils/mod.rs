@@ -16,6 +16,12 @@ pub mod error;
 /// including formatting and output destinations.
 pub mod logging;
 
+/// Difficulty and share target helpers
+///
+/// Conversions between Stratum targets and difficulties, and checks of
+/// hashes against a difficulty.
+pub mod difficulty;
+
 // Re-export for easier access
 pub use error::MinerError;
 pub use logging::init_logging;
@@ -0,0 +1,519 @@
+// tests/node_client.rs
+//! NodeClient integration tests against a mock monerod
+
+mod support;
+
+use std::time::{Duration, Instant};
+use support::monerod::{MockConfig, MockMonerod};
+use xmr_miner_rs::miner::algorithm::cryptonight::CryptoNightAlgo;
+use xmr_miner_rs::network::node::NodeConfig;
+use xmr_miner_rs::utils::difficulty::meets_target;
+use xmr_miner_rs::{Algorithm, MiningJob, NodeClient, Share};
+use zeromq::{Socket, SocketSend, ZmqMessage};
+
+const WALLET: &str = "4AdUndXHHZ6cfufTMvppY6JwXNouMBzSkbLYfpAV5Usx3skxNgYeYTRj5UzqtReoS44qo9mtmXCqY45DJ852K5Jv2684Rge";
+
+/// Node configuration pointing at `primary` with optional backups
+fn node_config(primary: &MockMonerod, backups: &[&MockMonerod]) -> NodeConfig {
+    NodeConfig {
+        rpc_url: primary.url(),
+        rpc_user: String::new(),
+        rpc_password: String::new(),
+        wallet_address: WALLET.to_string(),
+        rig_id: 0,
+        backup_rpc_urls: backups.iter().map(|b| b.url()).collect(),
+        max_height_lag: 2,
+        zmq_url: None,
+        backup_zmq_urls: Vec::new(),
+        server: None,
+    }
+}
+
+/// Creates a client along with the miner side of its channels
+fn client(
+    config: NodeConfig,
+) -> (
+    NodeClient,
+    crossbeam_channel::Receiver<Option<MiningJob>>,
+    crossbeam_channel::Sender<Share>,
+) {
+    let (job_sender, job_receiver) = crossbeam_channel::unbounded();
+    let (share_sender, share_receiver) = crossbeam_channel::unbounded();
+    (
+        NodeClient::new(config, job_sender, share_receiver),
+        job_receiver,
+        share_sender,
+    )
+}
+
+fn share(job: &MiningJob, nonce: u64, extra_nonce: u64) -> Share {
+    Share {
+        job_id: job.job_id.clone(),
+        nonce,
+        result: [0u8; 32],
+        extra_nonce,
+        found: Instant::now(),
+    }
+}
+
+/// Waits for a job update on a blocking channel without stalling the runtime
+async fn next_update(
+    jobs: &crossbeam_channel::Receiver<Option<MiningJob>>,
+    timeout: Duration,
+) -> Option<MiningJob> {
+    let jobs = jobs.clone();
+    tokio::task::spawn_blocking(move || jobs.recv_timeout(timeout))
+        .await
+        .unwrap()
+        .expect("no job update received")
+}
+
+/// Waits for a job, failing on a withdrawal
+async fn next_job(jobs: &crossbeam_channel::Receiver<Option<MiningJob>>) -> MiningJob {
+    next_update(jobs, Duration::from_secs(10))
+        .await
+        .expect("job withdrawn")
+}
+
+#[tokio::test]
+async fn block_template_follows_chain_tip() {
+    let node = MockMonerod::start_with(MockConfig {
+        height: 250,
+        difficulty: 123_456,
+        ..Default::default()
+    })
+    .await;
+    let (mut client, _jobs, _shares) = client(node_config(&node, &[]));
+
+    let job = client.get_block_template().await.unwrap();
+    let template = job.template.as_ref().unwrap();
+
+    assert_eq!(template.height, 250);
+    assert_eq!(template.difficulty, 123_456);
+    // Little-endian 256-bit target: the top 64 bits are the last 8 bytes
+    assert_eq!(job.target.len(), 32);
+    assert_eq!(
+        u64::from_le_bytes(job.target[24..].try_into().unwrap()),
+        u64::MAX / 123_456
+    );
+    let mut hash = [0u8; 32];
+    hash[24..].copy_from_slice(&(u64::MAX / 123_456).to_le_bytes());
+    assert!(meets_target(&hash, &job.target));
+    hash[24..].copy_from_slice(&(u64::MAX / 123_456 + 1).to_le_bytes());
+    assert!(!meets_target(&hash, &job.target));
+    assert!(job.job_id.starts_with("250-"));
+    assert!(job.blob.windows(32).any(|w| w == node.tip()));
+    assert_eq!(node.calls("getblocktemplate"), 1);
+}
+
+#[tokio::test]
+async fn submitted_block_extends_chain() {
+    let node = MockMonerod::start().await;
+    let (mut client, _jobs, _shares) = client(node_config(&node, &[]));
+
+    let tip = node.tip();
+    let job = client.get_block_template().await.unwrap();
+    client
+        .submit_block(share(&job, 0xdead_beef, 0x0102_0304))
+        .await
+        .unwrap();
+
+    assert_eq!(node.height(), 101);
+    let submitted = node.submitted();
+    assert_eq!(submitted.len(), 1);
+    assert_eq!(submitted[0].prev_hash, tip);
+    assert_eq!(submitted[0].nonce, 0xdead_beef);
+
+    let reserved = job.template.as_ref().unwrap().reserved_offset;
+    assert_eq!(
+        &submitted[0].blob[reserved..reserved + 8],
+        &0x0102_0304u64.to_be_bytes()
+    );
+}
+
+/// Proof of work the mock node checks blocks with (CryptoNight V7)
+fn cryptonight_pow(blob: &[u8]) -> [u8; 32] {
+    cryptonight::cryptonight(blob, blob.len(), 1)
+        .try_into()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn found_block_meets_network_difficulty() {
+    let node = MockMonerod::start_with(MockConfig {
+        difficulty: 16,
+        pow: Some(cryptonight_pow),
+        ..Default::default()
+    })
+    .await;
+    let (mut client, _jobs, _shares) = client(node_config(&node, &[]));
+    let job = client.get_block_template().await.unwrap();
+    let extra_nonce = job.template.as_ref().unwrap().extra_nonce(0);
+
+    // Hash like the scheduler until one nonce meets the target and one misses
+    let algorithm = CryptoNightAlgo::new(1);
+    let (mut found, mut missed) = (None, None);
+    for nonce in 0..10_000u64 {
+        let hash = algorithm.hash(&job.blob, nonce).unwrap();
+        let slot = if meets_target(&hash, &job.target) {
+            &mut found
+        } else {
+            &mut missed
+        };
+        slot.get_or_insert(Share {
+            job_id: job.job_id.clone(),
+            nonce,
+            result: hash,
+            extra_nonce,
+            found: Instant::now(),
+        });
+        if found.is_some() && missed.is_some() {
+            break;
+        }
+    }
+
+    let err = client.submit_block(missed.unwrap()).await.unwrap_err();
+    assert!(err.to_string().contains("Block not accepted"), "{}", err);
+    assert_eq!(node.height(), 100);
+
+    let found = found.unwrap();
+    client.submit_block(found.clone()).await.unwrap();
+    assert_eq!(node.height(), 101);
+    assert_eq!(node.submitted()[0].nonce as u64, found.nonce);
+}
+
+#[tokio::test]
+async fn orphaned_block_is_rejected() {
+    let node = MockMonerod::start().await;
+    let (mut client, _jobs, _shares) = client(node_config(&node, &[]));
+
+    let job = client.get_block_template().await.unwrap();
+    node.advance(1);
+
+    let err = client.submit_block(share(&job, 1, 0)).await.unwrap_err();
+    assert!(err.to_string().contains("Block not accepted"), "{}", err);
+    assert!(node.submitted().is_empty());
+}
+
+#[tokio::test]
+async fn injected_errors_are_surfaced() {
+    let node = MockMonerod::start().await;
+    let (mut client, _jobs, _shares) = client(node_config(&node, &[]));
+
+    node.fail_next("getblocktemplate", -9, "Core is busy");
+    let err = client.get_block_template().await.unwrap_err();
+    assert!(err.to_string().contains("Core is busy"), "{}", err);
+
+    let job = client.get_block_template().await.unwrap();
+    node.fail_next("submit_block", -7, "Block not accepted");
+    let err = client.submit_block(share(&job, 1, 0)).await.unwrap_err();
+    assert!(err.to_string().contains("Block rejected"), "{}", err);
+    assert_eq!(node.height(), 100);
+
+    client.submit_block(share(&job, 2, 0)).await.unwrap();
+    assert_eq!(node.height(), 101);
+}
+
+#[tokio::test]
+async fn failed_submission_retries_on_backup() {
+    let primary = MockMonerod::start().await;
+    let backup = MockMonerod::start().await;
+    let lagging = MockMonerod::start_with(MockConfig {
+        height: 99,
+        ..Default::default()
+    })
+    .await;
+    let (mut client, _jobs, _shares) = client(node_config(&primary, &[&lagging, &backup]));
+
+    let job = client.get_block_template().await.unwrap();
+    primary.fail_next("submitblock", -7, "Block not accepted");
+    client.submit_block(share(&job, 7, 0)).await.unwrap();
+
+    assert!(primary.submitted().is_empty());
+    assert_eq!(backup.submitted().len(), 1);
+    assert_eq!(backup.submitted()[0].nonce, 7);
+    assert_eq!(backup.height(), 101);
+    // Only nodes synchronized at the template's height get the block
+    assert_eq!(lagging.calls("submit_block"), 0);
+}
+
+#[tokio::test]
+async fn stale_share_is_not_submitted() {
+    let node = MockMonerod::start().await;
+    let (mut client, _jobs, _shares) = client(node_config(&node, &[]));
+
+    let old = client.get_block_template().await.unwrap();
+    node.advance(1);
+    client.get_block_template().await.unwrap();
+
+    let err = client.submit_block(share(&old, 1, 0)).await.unwrap_err();
+    assert!(err.to_string().contains("Stale job"), "{}", err);
+    assert_eq!(node.calls("submit_block"), 0);
+}
+
+#[tokio::test]
+async fn refreshed_template_keeps_same_height_jobs() {
+    let node = MockMonerod::start().await;
+    let (mut client, _jobs, _shares) = client(node_config(&node, &[]));
+
+    // A refresh for new pool transactions, same height
+    let first = client.get_block_template().await.unwrap();
+    let second = client.get_block_template().await.unwrap();
+    assert_ne!(first.job_id, second.job_id);
+
+    client.submit_block(share(&first, 3, 0)).await.unwrap();
+    let submitted = node.submitted();
+    assert_eq!(submitted.len(), 1);
+    assert_eq!(submitted[0].nonce, 3);
+    assert_eq!(node.height(), 101);
+}
+
+#[tokio::test]
+async fn pool_template_carries_extra_nonce() {
+    let node = MockMonerod::start().await;
+    let (client, _jobs, _shares) = client(node_config(&node, &[]));
+
+    let template = client
+        .get_pool_template(WALLET, "00112233aabbccdd")
+        .await
+        .unwrap();
+    assert_eq!(template["height"], 100);
+    assert!(
+        template["blocktemplate_blob"]
+            .as_str()
+            .unwrap()
+            .contains("00112233aabbccdd")
+    );
+
+    let err = client.get_pool_template("", "00").await.unwrap_err();
+    assert!(err.to_string().contains("wallet address"), "{}", err);
+}
+
+#[tokio::test]
+async fn digest_auth_is_negotiated_once() {
+    let node = MockMonerod::start_with(MockConfig {
+        auth: Some(("miner".to_string(), "hunter2".to_string())),
+        ..Default::default()
+    })
+    .await;
+    let mut config = node_config(&node, &[]);
+    config.rpc_user = "miner".to_string();
+    config.rpc_password = "hunter2".to_string();
+    let (mut client, _jobs, _shares) = client(config);
+
+    for _ in 0..3 {
+        client.get_block_template().await.unwrap();
+    }
+    assert_eq!(node.calls("getblocktemplate"), 3);
+    assert_eq!(node.unauthorized(), 1);
+
+    // A new server nonce costs one more challenge round trip
+    node.rotate_nonce();
+    client.get_block_template().await.unwrap();
+    assert_eq!(node.unauthorized(), 2);
+}
+
+#[tokio::test]
+async fn digest_auth_rejects_wrong_password() {
+    let node = MockMonerod::start_with(MockConfig {
+        auth: Some(("miner".to_string(), "hunter2".to_string())),
+        ..Default::default()
+    })
+    .await;
+    let mut config = node_config(&node, &[]);
+    config.rpc_user = "miner".to_string();
+    config.rpc_password = "wrong".to_string();
+    let (mut client, _jobs, _shares) = client(config);
+
+    assert!(client.get_block_template().await.is_err());
+    assert_eq!(node.calls("getblocktemplate"), 0);
+}
+
+#[tokio::test]
+async fn monitor_chain_fails_over_to_backup() {
+    let primary = MockMonerod::start_with(MockConfig {
+        synchronized: false,
+        ..Default::default()
+    })
+    .await;
+    let backup = MockMonerod::start_with(MockConfig {
+        height: 100,
+        ..Default::default()
+    })
+    .await;
+    backup.script_heights([105]);
+    let (mut client, jobs, shares) = client(node_config(&primary, &[&backup]));
+
+    tokio::spawn(async move { client.monitor_chain().await });
+
+    let job = next_job(&jobs).await;
+    assert_eq!(job.template.as_ref().unwrap().height, 105);
+    assert_eq!(primary.calls("getblocktemplate"), 0);
+
+    // Blocks found by the miners go to the node the template came from
+    shares.send(share(&job, 7, 0)).unwrap();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while backup.submitted().is_empty() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("block not submitted");
+    assert_eq!(backup.height(), 106);
+    assert!(primary.submitted().is_empty());
+}
+
+#[tokio::test]
+async fn monitor_chain_skips_lagging_primary() {
+    let primary = MockMonerod::start_with(MockConfig {
+        height: 97,
+        ..Default::default()
+    })
+    .await;
+    let backup = MockMonerod::start_with(MockConfig {
+        height: 100,
+        ..Default::default()
+    })
+    .await;
+    let (mut client, jobs, _shares) = client(node_config(&primary, &[&backup]));
+
+    tokio::spawn(async move { client.monitor_chain().await });
+
+    let job = next_job(&jobs).await;
+    assert_eq!(job.template.as_ref().unwrap().height, 100);
+    assert_eq!(primary.calls("getblocktemplate"), 0);
+}
+
+#[tokio::test]
+async fn monitor_chain_waits_for_synchronized_node() {
+    let node = MockMonerod::start_with(MockConfig {
+        synchronized: false,
+        ..Default::default()
+    })
+    .await;
+    let (mut client, jobs, _shares) = client(node_config(&node, &[]));
+
+    let monitor = tokio::spawn(async move { client.monitor_chain().await });
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(!monitor.is_finished());
+    assert!(jobs.is_empty());
+    assert_eq!(node.calls("getblocktemplate"), 0);
+
+    node.set_synchronized(true);
+    let job = next_job(&jobs).await;
+    assert_eq!(job.template.as_ref().unwrap().height, node.height());
+}
+
+#[tokio::test]
+async fn monitor_chain_retries_failed_template() {
+    let node = MockMonerod::start().await;
+    node.fail_next("getblocktemplate", -9, "Core is busy");
+    let (mut client, jobs, _shares) = client(node_config(&node, &[]));
+
+    tokio::spawn(async move { client.monitor_chain().await });
+
+    let job = next_job(&jobs).await;
+    assert_eq!(job.template.as_ref().unwrap().height, node.height());
+    assert_eq!(node.calls("getblocktemplate"), 2);
+}
+
+#[tokio::test]
+async fn monitor_chain_withdraws_job_until_node_recovers() {
+    let node = MockMonerod::start().await;
+    let (mut client, jobs, _shares) = client(node_config(&node, &[]));
+
+    tokio::spawn(async move { client.monitor_chain().await });
+    next_job(&jobs).await;
+
+    // Noticed on the next regular poll
+    node.set_synchronized(false);
+    let update = next_update(&jobs, Duration::from_secs(40)).await;
+    assert!(update.is_none(), "job not withdrawn");
+
+    node.set_synchronized(true);
+    let job = next_job(&jobs).await;
+    assert_eq!(job.template.as_ref().unwrap().height, node.height());
+}
+
+#[tokio::test]
+async fn zmq_new_block_refreshes_template() {
+    let node = MockMonerod::start_with(MockConfig {
+        height: 100,
+        ..Default::default()
+    })
+    .await;
+    let mut publisher = zeromq::PubSocket::new();
+    let endpoint = publisher.bind("tcp://127.0.0.1:0").await.unwrap();
+    let mut config = node_config(&node, &[]);
+    config.zmq_url = Some(endpoint.to_string());
+    let (mut client, jobs, _shares) = client(config);
+
+    tokio::spawn(async move { client.monitor_chain().await });
+    let job = next_job(&jobs).await;
+    assert_eq!(job.template.as_ref().unwrap().height, 100);
+
+    // Well before the next 30-second poll; republished until the
+    // subscription has reached the publisher
+    node.advance(1);
+    let notification = r#"json-minimal-chain_main:{"first_height":101,"ids":["aa"]}"#;
+    let job = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            publisher
+                .send(ZmqMessage::from(notification.to_string()))
+                .await
+                .unwrap();
+            if let Ok(Some(job)) = jobs.try_recv() {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("template not refreshed on ZMQ notification");
+    assert_eq!(job.template.as_ref().unwrap().height, 101);
+}
+
+#[tokio::test]
+async fn zmq_follows_failover_to_backup() {
+    let primary = MockMonerod::start_with(MockConfig {
+        synchronized: false,
+        ..Default::default()
+    })
+    .await;
+    let backup = MockMonerod::start_with(MockConfig {
+        height: 100,
+        ..Default::default()
+    })
+    .await;
+    let mut primary_publisher = zeromq::PubSocket::new();
+    let primary_endpoint = primary_publisher.bind("tcp://127.0.0.1:0").await.unwrap();
+    let mut backup_publisher = zeromq::PubSocket::new();
+    let backup_endpoint = backup_publisher.bind("tcp://127.0.0.1:0").await.unwrap();
+    let mut config = node_config(&primary, &[&backup]);
+    config.zmq_url = Some(primary_endpoint.to_string());
+    config.backup_zmq_urls = vec![backup_endpoint.to_string()];
+    let (mut client, jobs, _shares) = client(config);
+
+    tokio::spawn(async move { client.monitor_chain().await });
+    let job = next_job(&jobs).await;
+    assert_eq!(job.template.as_ref().unwrap().height, 100);
+
+    // Only the backup's publisher announces its blocks
+    backup.advance(1);
+    let notification = r#"json-minimal-chain_main:{"first_height":101,"ids":["aa"]}"#;
+    let job = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            backup_publisher
+                .send(ZmqMessage::from(notification.to_string()))
+                .await
+                .unwrap();
+            if let Ok(Some(job)) = jobs.try_recv() {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("template not refreshed on the backup's ZMQ notification");
+    assert_eq!(job.template.as_ref().unwrap().height, 101);
+}
@@ -0,0 +1,255 @@
+// tests/pool_client.rs
+//! PoolClient integration tests against a scripted pool and a mock monerod
+
+mod support;
+
+use serde_json::{Value, json};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use support::monerod::MockMonerod;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tokio::time;
+use xmr_miner_rs::network::node::NodeConfig;
+use xmr_miner_rs::network::pool::PoolConfig;
+use xmr_miner_rs::network::stratum::{StratumConnection, error_response, result_response};
+use xmr_miner_rs::stats::BenchmarkResults;
+use xmr_miner_rs::{AlgorithmType, MinerError, MiningJob, PoolClient};
+
+const WALLET: &str = "4AdUndXHHZ6cfufTMvppY6JwXNouMBzSkbLYfpAV5Usx3skxNgYeYTRj5UzqtReoS44qo9mtmXCqY45DJ852K5Jv2684Rge";
+
+/// Extra nonce the pool reserves for this miner
+const EXTRA_NONCE: &str = "00112233aabbccdd";
+
+/// Self-select job as sent by the pool in the login response
+fn self_select_job() -> Value {
+    json!({
+        "job_id": "ss-1",
+        "pool_wallet": WALLET,
+        "extra_nonce": EXTRA_NONCE,
+        "target": "ffffffff",
+        "algo": "rx/0"
+    })
+}
+
+/// Accepts one connection and answers its login with `job`
+///
+/// A `block_template` report is accepted, or refused with `template_error`.
+///
+/// # Returns
+/// The address to connect to, and a handle yielding every message the pool
+/// received until a `block_template` arrives or the miner disconnects
+async fn scripted_pool(
+    job: Value,
+    template_error: Option<&'static str>,
+) -> (SocketAddr, JoinHandle<Vec<Value>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handle = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut conn = StratumConnection::accept(stream).await.unwrap();
+        let mut received = Vec::new();
+        while let Ok(Some(message)) = conn.recv().await {
+            match message["method"].as_str() {
+                Some("login") => {
+                    let result = json!({ "id": "session", "job": job, "status": "OK" });
+                    conn.send(&result_response(&message["id"], result))
+                        .await
+                        .unwrap();
+                }
+                Some("block_template") => {
+                    let reply = match template_error {
+                        Some(error) => error_response(&message["id"], error),
+                        None => result_response(&message["id"], json!({ "status": "OK" })),
+                    };
+                    conn.send(&reply).await.unwrap();
+                    received.push(message);
+                    break;
+                }
+                _ => {}
+            }
+            received.push(message);
+        }
+        received
+    });
+    (addr, handle)
+}
+
+fn pool_config(pool: SocketAddr, node: Option<&MockMonerod>) -> PoolConfig {
+    PoolConfig {
+        url: format!("ws://{}", pool),
+        user: WALLET.to_string(),
+        password: "x".to_string(),
+        worker_id: "rig".to_string(),
+        self_select: node.map(|node| NodeConfig {
+            rpc_url: node.url(),
+            rpc_user: String::new(),
+            rpc_password: String::new(),
+            wallet_address: WALLET.to_string(),
+            rig_id: 0,
+            backup_rpc_urls: Vec::new(),
+            max_height_lag: 2,
+            zmq_url: None,
+            backup_zmq_urls: Vec::new(),
+            server: None,
+        }),
+        benchmark_file: std::env::temp_dir().join(format!(
+            "xmr_miner-no-benchmark-{}.json",
+            std::process::id()
+        )),
+        target_shares_per_minute: None,
+        block_notify_url: None,
+        record_file: None,
+    }
+}
+
+/// Connects a client and runs its session in the background
+fn start_client(
+    config: PoolConfig,
+) -> (
+    JoinHandle<Result<(), MinerError>>,
+    crossbeam_channel::Receiver<Option<MiningJob>>,
+) {
+    let (job_sender, jobs) = crossbeam_channel::unbounded();
+    let (_share_sender, share_receiver) = crossbeam_channel::unbounded();
+    let client = Arc::new(PoolClient::new(config, job_sender, share_receiver));
+    let session = tokio::spawn(async move {
+        client.connect().await?;
+        client.run().await
+    });
+    (session, jobs)
+}
+
+/// Waits for the next job on a blocking channel without stalling the runtime
+async fn next_job(jobs: crossbeam_channel::Receiver<Option<MiningJob>>) -> MiningJob {
+    next_update(jobs).await.expect("job withdrawn")
+}
+
+/// Waits for the next job update, `None` meaning the job was withdrawn
+async fn next_update(jobs: crossbeam_channel::Receiver<Option<MiningJob>>) -> Option<MiningJob> {
+    tokio::task::spawn_blocking(move || jobs.recv_timeout(Duration::from_secs(10)))
+        .await
+        .unwrap()
+        .expect("no job update received")
+}
+
+/// Waits for the session to fail and returns its error message
+async fn session_error(session: JoinHandle<Result<(), MinerError>>) -> String {
+    time::timeout(Duration::from_secs(10), session)
+        .await
+        .expect("session did not end")
+        .unwrap()
+        .expect_err("session should fail")
+        .to_string()
+}
+
+#[tokio::test]
+async fn login_advertises_algorithms_and_benchmarks() {
+    let job = json!({
+        "job_id": "cn-1",
+        "blob": hex::encode([0x07u8; 76]),
+        "target": "ffffffff",
+        "algo": "cn/r"
+    });
+    let (addr, pool) = scripted_pool(job, None).await;
+    let mut config = pool_config(addr, None);
+    config.benchmark_file =
+        std::env::temp_dir().join(format!("xmr_miner-algo-perf-{}.json", std::process::id()));
+    let mut results = BenchmarkResults::default();
+    results.record(AlgorithmType::RandomX, 1200.5);
+    results.record(AlgorithmType::CryptoNightV7, 40.0);
+    results.save(&config.benchmark_file).unwrap();
+    let benchmark_file = config.benchmark_file.clone();
+
+    let (session, jobs) = start_client(config);
+    // The pool's choice of algorithm is passed on with the job
+    let job = next_job(jobs).await;
+    assert_eq!(job.algorithm, AlgorithmType::CryptoNightR);
+    session.abort();
+    std::fs::remove_file(benchmark_file).unwrap();
+
+    let received = pool.await.unwrap();
+    let login = &received[0]["params"];
+    assert_eq!(login["algo"], json!(["rx/0", "cn/1", "cn/r"]));
+    // Algorithms without a benchmark are left out
+    assert_eq!(login["algo-perf"], json!({ "rx/0": 1200.5, "cn/1": 40.0 }));
+    assert!(login.get("mode").is_none());
+}
+
+#[tokio::test]
+async fn self_select_job_reports_block_template() {
+    let node = MockMonerod::start().await;
+    let (addr, pool) = scripted_pool(self_select_job(), None).await;
+    let (_session, jobs) = start_client(pool_config(addr, Some(&node)));
+
+    let received = time::timeout(Duration::from_secs(10), pool)
+        .await
+        .expect("no block_template received")
+        .unwrap();
+    assert_eq!(received[0]["method"], "login");
+    assert_eq!(received[0]["params"]["mode"], "self-select");
+
+    let report = received.last().unwrap();
+    assert_eq!(report["method"], "block_template");
+    // Allocated from the submit counter, whose first id is 100
+    assert_eq!(report["id"], 100);
+    let params = &report["params"];
+    assert_eq!(params["id"], "session");
+    assert_eq!(params["job_id"], "ss-1");
+    assert_eq!(params["height"], node.height());
+    assert_eq!(params["difficulty"], node.difficulty());
+    assert_eq!(params["prev_hash"], hex::encode(node.tip()));
+    assert!(params["blob"].as_str().unwrap().contains(EXTRA_NONCE));
+
+    // Once accepted, the template's hashing blob is mined against the pool target
+    let job = next_job(jobs).await;
+    assert_eq!(job.job_id, "ss-1");
+    assert_eq!(job.target, [0xff; 4]);
+    assert_eq!(job.algorithm, AlgorithmType::RandomX);
+    assert_eq!(job.seed_hash, Some(hex::encode([0u8; 32])));
+    let blob = params["blob"].as_str().unwrap();
+    assert!(blob.starts_with(&hex::encode(&job.blob)));
+}
+
+#[tokio::test]
+async fn rejected_block_template_withdraws_job() {
+    let node = MockMonerod::start().await;
+    let (addr, pool) = scripted_pool(self_select_job(), Some("Invalid block template")).await;
+    let (_session, jobs) = start_client(pool_config(addr, Some(&node)));
+
+    let received = time::timeout(Duration::from_secs(10), pool)
+        .await
+        .expect("no block_template received")
+        .unwrap();
+    assert_eq!(received.last().unwrap()["method"], "block_template");
+    assert!(next_update(jobs).await.is_none(), "job not withdrawn");
+}
+
+#[tokio::test]
+async fn self_select_job_with_missing_fields_fails() {
+    let node = MockMonerod::start().await;
+    for field in ["job_id", "pool_wallet", "extra_nonce", "target"] {
+        let mut job = self_select_job();
+        job.as_object_mut().unwrap().remove(field);
+        // Without a pool wallet the job is an ordinary one, missing its blob
+        let expected = if field == "pool_wallet" {
+            "Missing blob".to_string()
+        } else {
+            format!("Missing {}", field)
+        };
+
+        let (addr, _pool) = scripted_pool(job, None).await;
+        let (session, _jobs) = start_client(pool_config(addr, Some(&node)));
+        let err = session_error(session).await;
+        assert!(err.contains(&expected), "{}: {}", field, err);
+    }
+}
+
+#[tokio::test]
+async fn self_select_job_needs_a_node() {
+    let (addr, _pool) = scripted_pool(self_select_job(), None).await;
+    let (session, _jobs) = start_client(pool_config(addr, None));
+    let err = session_error(session).await;
+    assert!(err.contains("no self_select node"), "{}", err);
+}
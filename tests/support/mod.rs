@@ -0,0 +1,6 @@
+// tests/support/mod.rs
+//! Shared helpers for integration tests
+#![allow(dead_code)]
+
+/// Mock monerod JSON-RPC server
+pub mod monerod;
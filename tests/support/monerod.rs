@@ -0,0 +1,668 @@
+// tests/support/monerod.rs
+//! Mock monerod JSON-RPC server
+//!
+//! Serves the subset of the daemon RPC used by [`NodeClient`] over plain
+//! HTTP/1.1 on a local port:
+//! - `get_info`
+//! - `getblocktemplate` / `get_block_template`
+//! - `submit_block` / `submitblock`
+//! - `get_block_header_by_height`
+//!
+//! The chain can be advanced by hand or scripted per `get_info` call, any
+//! method can be made to fail, and HTTP digest authentication can be required
+//! the way `monerod --rpc-login` does. Submitted blocks are checked for a
+//! well-formed header on top of the current tip and, when a proof-of-work
+//! function is configured, against the network difficulty the way monerod's
+//! `check_hash` does.
+//!
+//! [`NodeClient`]: xmr_miner_rs::NodeClient
+
+use md5::Md5;
+use serde_json::{Value, json};
+use sha3::{Digest as _, Keccak256};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Realm announced in digest challenges (same as monerod)
+const REALM: &str = "monero-rpc";
+
+/// Size of the block header nonce field
+const NONCE_SIZE: usize = 4;
+
+/// Proof-of-work hash function applied to a hashing blob
+pub type PowFn = fn(&[u8]) -> [u8; 32];
+
+/// Settings for a mock daemon
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    /// Chain height at start (number of blocks, as reported by `get_info`)
+    pub height: u64,
+    /// Network difficulty reported for every block
+    pub difficulty: u64,
+    /// Whether `get_info` reports the daemon as synchronized
+    pub synchronized: bool,
+    /// Credentials required via digest auth, if any
+    pub auth: Option<(String, String)>,
+    /// Proof-of-work hash of a hashing blob; submitted blocks must meet the
+    /// difficulty under it (not checked when `None`)
+    pub pow: Option<PowFn>,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        MockConfig {
+            height: 100,
+            difficulty: 1000,
+            synchronized: true,
+            auth: None,
+            pow: None,
+        }
+    }
+}
+
+/// A block of the mock chain
+#[derive(Debug, Clone)]
+pub struct MockBlock {
+    /// Block id
+    pub hash: [u8; 32],
+    /// Id of the previous block
+    pub prev_hash: [u8; 32],
+    /// Header timestamp
+    pub timestamp: u64,
+    /// Header nonce
+    pub nonce: u32,
+    /// Full block blob (empty for generated blocks)
+    pub blob: Vec<u8>,
+}
+
+/// Mutable daemon state
+#[derive(Debug)]
+struct State {
+    /// Chain, indexed by height
+    blocks: Vec<MockBlock>,
+    /// Network difficulty
+    difficulty: u64,
+    /// Reported sync state
+    synchronized: bool,
+    /// Heights to reach on successive `get_info` calls
+    script: VecDeque<u64>,
+    /// Injected JSON-RPC errors per method, used once each
+    errors: HashMap<String, VecDeque<(i64, String)>>,
+    /// Required credentials
+    auth: Option<(String, String)>,
+    /// Current server nonce for digest auth
+    server_nonce: String,
+    /// Requests per method
+    calls: HashMap<String, usize>,
+    /// Requests answered with `401`
+    unauthorized: usize,
+    /// Blocks accepted through `submit_block`
+    submitted: Vec<MockBlock>,
+    /// Proof-of-work hash checked on submission
+    pow: Option<PowFn>,
+}
+
+impl State {
+    /// Appends generated blocks until the chain has `height` blocks
+    fn grow_to(&mut self, height: u64) {
+        while (self.blocks.len() as u64) < height {
+            let prev_hash = self.blocks.last().map_or([0u8; 32], |b| b.hash);
+            let height = self.blocks.len() as u64;
+            self.blocks.push(MockBlock {
+                hash: keccak(&[&prev_hash[..], &height.to_le_bytes()].concat()),
+                prev_hash,
+                timestamp: 1_700_000_000 + height * 120,
+                nonce: 0,
+                blob: Vec::new(),
+            });
+        }
+    }
+
+    /// Height of the chain (number of blocks)
+    fn height(&self) -> u64 {
+        self.blocks.len() as u64
+    }
+
+    /// Id of the top block
+    fn tip(&self) -> [u8; 32] {
+        self.blocks.last().map_or([0u8; 32], |b| b.hash)
+    }
+}
+
+/// Handle to a running mock daemon
+///
+/// The server runs on the current Tokio runtime until the runtime shuts down.
+#[derive(Clone)]
+pub struct MockMonerod {
+    /// Listening address
+    addr: SocketAddr,
+    /// Shared daemon state
+    state: Arc<Mutex<State>>,
+}
+
+impl MockMonerod {
+    /// Starts a daemon with default settings
+    pub async fn start() -> Self {
+        Self::start_with(MockConfig::default()).await
+    }
+
+    /// Starts a daemon with the given settings on a free local port
+    pub async fn start_with(config: MockConfig) -> Self {
+        let mut state = State {
+            blocks: Vec::new(),
+            difficulty: config.difficulty,
+            synchronized: config.synchronized,
+            script: VecDeque::new(),
+            errors: HashMap::new(),
+            auth: config.auth,
+            server_nonce: hex::encode(&keccak(b"mock-monerod-nonce")[..16]),
+            calls: HashMap::new(),
+            unauthorized: 0,
+            submitted: Vec::new(),
+            pow: config.pow,
+        };
+        state.grow_to(config.height);
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock monerod");
+        let addr = listener.local_addr().expect("mock monerod address");
+        let state = Arc::new(Mutex::new(state));
+
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, server_state.clone()));
+            }
+        });
+
+        MockMonerod { addr, state }
+    }
+
+    /// JSON-RPC endpoint URL (e.g. "http://127.0.0.1:12345/json_rpc")
+    pub fn url(&self) -> String {
+        format!("http://{}/json_rpc", self.addr)
+    }
+
+    /// Current chain height (number of blocks)
+    pub fn height(&self) -> u64 {
+        self.lock().height()
+    }
+
+    /// Id of the current top block
+    pub fn tip(&self) -> [u8; 32] {
+        self.lock().tip()
+    }
+
+    /// Network difficulty
+    pub fn difficulty(&self) -> u64 {
+        self.lock().difficulty
+    }
+
+    /// Mines `count` blocks as if found by another miner
+    pub fn advance(&self, count: u64) {
+        let mut state = self.lock();
+        let height = state.height() + count;
+        state.grow_to(height);
+    }
+
+    /// Scripts the chain height reached on each following `get_info` call
+    ///
+    /// Heights below the current height leave the chain unchanged; once the
+    /// script runs out the chain stays where it is.
+    pub fn script_heights(&self, heights: impl IntoIterator<Item = u64>) {
+        self.lock().script.extend(heights);
+    }
+
+    /// Sets the sync state reported by `get_info`
+    pub fn set_synchronized(&self, synchronized: bool) {
+        self.lock().synchronized = synchronized;
+    }
+
+    /// Makes the next call of `method` fail with a JSON-RPC error
+    ///
+    /// Method aliases (`submitblock`, `get_block_template`) share the
+    /// canonical method's queue.
+    pub fn fail_next(&self, method: &str, code: i64, message: &str) {
+        self.lock()
+            .errors
+            .entry(canonical(method).to_string())
+            .or_default()
+            .push_back((code, message.to_string()));
+    }
+
+    /// Issues a new server nonce, making the clients' stored challenge stale
+    pub fn rotate_nonce(&self) {
+        let mut state = self.lock();
+        state.server_nonce = hex::encode(&keccak(state.server_nonce.as_bytes())[..16]);
+    }
+
+    /// Number of authenticated calls of `method` (aliases included)
+    pub fn calls(&self, method: &str) -> usize {
+        self.lock()
+            .calls
+            .get(canonical(method))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Number of requests answered with `401 Unauthorized`
+    pub fn unauthorized(&self) -> usize {
+        self.lock().unauthorized
+    }
+
+    /// Blocks accepted through `submit_block`, oldest first
+    pub fn submitted(&self) -> Vec<MockBlock> {
+        self.lock().submitted.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("mock monerod state poisoned")
+    }
+}
+
+/// Maps method aliases onto one name
+fn canonical(method: &str) -> &str {
+    match method {
+        "submitblock" => "submit_block",
+        "get_block_template" => "getblocktemplate",
+        other => other,
+    }
+}
+
+/// Serves HTTP requests on one connection until the client closes it
+async fn serve(stream: TcpStream, state: Arc<Mutex<State>>) {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read);
+
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+            return;
+        }
+        let method = request_line
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let uri = request_line
+            .split_whitespace()
+            .nth(1)
+            .unwrap_or("/")
+            .to_string();
+
+        let mut headers = HashMap::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+                return;
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+            }
+        }
+
+        let length = headers
+            .get("content-length")
+            .and_then(|l| l.parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0u8; length];
+        if reader.read_exact(&mut body).await.is_err() {
+            return;
+        }
+
+        let response = {
+            let mut state = state.lock().expect("mock monerod state poisoned");
+            if authorized(&state, &method, &uri, headers.get("authorization")) {
+                let reply = serde_json::from_slice(&body)
+                    .map(|request| handle_rpc(&mut state, &request))
+                    .unwrap_or_else(|_| rpc_error(&Value::Null, -32700, "Parse error"));
+                http_response("200 OK", &[], &reply.to_string())
+            } else {
+                state.unauthorized += 1;
+                let challenges = [
+                    format!(
+                        "Digest qop=\"auth\",algorithm=MD5-sess,realm=\"{}\",nonce=\"{}\",stale=false",
+                        REALM, state.server_nonce
+                    ),
+                    format!(
+                        "Digest qop=\"auth\",algorithm=MD5,realm=\"{}\",nonce=\"{}\",stale=false",
+                        REALM, state.server_nonce
+                    ),
+                ];
+                http_response("401 Unauthorized", &challenges, "")
+            }
+        };
+
+        if write.write_all(response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Builds an HTTP/1.1 response
+fn http_response(status: &str, challenges: &[String], body: &str) -> String {
+    let mut response = format!("HTTP/1.1 {}\r\n", status);
+    for challenge in challenges {
+        response.push_str(&format!("WWW-Authenticate: {}\r\n", challenge));
+    }
+    response.push_str(&format!(
+        "Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    ));
+    response
+}
+
+/// Checks the digest `Authorization` header when credentials are required
+fn authorized(state: &State, method: &str, uri: &str, header: Option<&String>) -> bool {
+    let Some((user, password)) = &state.auth else {
+        return true;
+    };
+    let Some(params) = header.and_then(|h| h.strip_prefix("Digest ")) else {
+        return false;
+    };
+
+    let fields: HashMap<String, String> = params
+        .split(", ")
+        .filter_map(|field| field.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim_matches('"').to_string()))
+        .collect();
+    let field = |name: &str| fields.get(name).map(String::as_str).unwrap_or_default();
+
+    let ha1 = md5_hex(&format!("{}:{}:{}", user, REALM, password));
+    let ha2 = md5_hex(&format!("{}:{}", method, uri));
+    let expected = md5_hex(&format!(
+        "{}:{}:{}:{}:{}:{}",
+        ha1,
+        state.server_nonce,
+        field("nc"),
+        field("cnonce"),
+        field("qop"),
+        ha2
+    ));
+
+    field("username") == user
+        && field("realm") == REALM
+        && field("nonce") == state.server_nonce
+        && field("uri") == uri
+        && field("qop") == "auth"
+        && field("response") == expected
+}
+
+/// Dispatches one JSON-RPC request
+fn handle_rpc(state: &mut State, request: &Value) -> Value {
+    let id = &request["id"];
+    let method = canonical(request["method"].as_str().unwrap_or_default()).to_string();
+    *state.calls.entry(method.clone()).or_default() += 1;
+
+    if let Some((code, message)) = state.errors.get_mut(&method).and_then(|e| e.pop_front()) {
+        return rpc_error(id, code, &message);
+    }
+
+    let params = &request["params"];
+    match method.as_str() {
+        "get_info" => {
+            if let Some(height) = state.script.pop_front() {
+                state.grow_to(height);
+            }
+            rpc_result(
+                id,
+                json!({
+                    "height": state.height(),
+                    "target_height": state.height(),
+                    "difficulty": state.difficulty,
+                    "top_block_hash": hex::encode(state.tip()),
+                    "synchronized": state.synchronized,
+                    "status": "OK"
+                }),
+            )
+        }
+        "getblocktemplate" => {
+            if params["wallet_address"]
+                .as_str()
+                .unwrap_or_default()
+                .is_empty()
+            {
+                return rpc_error(id, -2, "Failed to parse wallet address");
+            }
+            let extra_nonce = params["extra_nonce"]
+                .as_str()
+                .and_then(|n| hex::decode(n).ok());
+            let reserve_size = params["reserve_size"].as_u64().unwrap_or(0) as usize;
+            let (blob, reserved_offset) =
+                template_blob(state, reserve_size, extra_nonce.as_deref());
+
+            rpc_result(
+                id,
+                json!({
+                    "blocktemplate_blob": hex::encode(&blob),
+                    "blockhashing_blob": hex::encode(&blob[..header_len(&blob)]),
+                    "difficulty": state.difficulty,
+                    "height": state.height(),
+                    "prev_hash": hex::encode(state.tip()),
+                    "reserved_offset": reserved_offset,
+                    "seed_hash": hex::encode([0u8; 32]),
+                    "next_seed_hash": "",
+                    "expected_reward": 600_000_000_000u64,
+                    "status": "OK"
+                }),
+            )
+        }
+        "submit_block" => {
+            let Some(blob) = params[0].as_str().and_then(|b| hex::decode(b).ok()) else {
+                return rpc_error(id, -6, "Wrong block blob");
+            };
+            if let Some(pow) = state.pow
+                && (blob.len() < header_len(&blob) + 2
+                    || !check_hash(&pow(&hashing_blob(&blob)), state.difficulty))
+            {
+                return rpc_error(id, -7, "Block not accepted");
+            }
+            match parse_header(&blob) {
+                Some((timestamp, prev_hash, nonce)) if prev_hash == state.tip() => {
+                    let block = MockBlock {
+                        hash: keccak(&blob),
+                        prev_hash,
+                        timestamp,
+                        nonce,
+                        blob,
+                    };
+                    state.submitted.push(block.clone());
+                    state.blocks.push(block);
+                    rpc_result(id, json!({ "status": "OK" }))
+                }
+                _ => rpc_error(id, -7, "Block not accepted"),
+            }
+        }
+        "get_block_header_by_height" => {
+            let height = params["height"].as_u64().unwrap_or(u64::MAX);
+            let Some(block) = state.blocks.get(height as usize) else {
+                return rpc_error(
+                    id,
+                    -2,
+                    &format!(
+                        "Requested block height: {} greater than current top block height: {}",
+                        height,
+                        state.height().saturating_sub(1)
+                    ),
+                );
+            };
+            rpc_result(
+                id,
+                json!({
+                    "block_header": {
+                        "height": height,
+                        "hash": hex::encode(block.hash),
+                        "prev_hash": hex::encode(block.prev_hash),
+                        "timestamp": block.timestamp,
+                        "nonce": block.nonce,
+                        "difficulty": state.difficulty,
+                        "depth": state.height() - height - 1
+                    },
+                    "status": "OK"
+                }),
+            )
+        }
+        _ => rpc_error(id, -32601, "Method not found"),
+    }
+}
+
+/// Builds a block template on top of the current tip
+///
+/// The coinbase extra holds a transaction public key, the pool extra nonce
+/// (self-select) and a zeroed reserved area of `reserve_size` bytes.
+///
+/// # Returns
+/// The blob and the offset of the reserved area
+fn template_blob(
+    state: &State,
+    reserve_size: usize,
+    extra_nonce: Option<&[u8]>,
+) -> (Vec<u8>, usize) {
+    let height = state.height();
+    let mut blob = Vec::new();
+
+    // Header: major, minor, timestamp, prev_id, nonce
+    write_varint(&mut blob, 16);
+    write_varint(&mut blob, 16);
+    write_varint(&mut blob, 1_700_000_000 + height * 120);
+    blob.extend_from_slice(&state.tip());
+    blob.extend_from_slice(&[0u8; NONCE_SIZE]);
+
+    // Coinbase transaction prefix
+    write_varint(&mut blob, 2); // version
+    write_varint(&mut blob, height + 60); // unlock_time
+    write_varint(&mut blob, 1); // inputs
+    blob.push(0xff); // txin_gen
+    write_varint(&mut blob, height);
+    write_varint(&mut blob, 1); // outputs
+    write_varint(&mut blob, 600_000_000_000);
+    blob.push(0x03); // txout_to_tagged_key
+    blob.extend_from_slice(&keccak(&height.to_le_bytes()));
+    blob.push(0x5a); // view tag
+
+    let mut extra = vec![0x01];
+    extra.extend_from_slice(&keccak(b"tx pubkey"));
+    if let Some(nonce) = extra_nonce {
+        extra.push(0x02);
+        extra.push(nonce.len() as u8);
+        extra.extend_from_slice(nonce);
+    }
+    let mut reserved = extra.len();
+    if reserve_size > 0 {
+        extra.push(0x02);
+        extra.push(reserve_size as u8);
+        reserved = extra.len();
+        extra.extend(std::iter::repeat_n(0u8, reserve_size));
+    }
+    write_varint(&mut blob, extra.len() as u64);
+    let reserved_offset = blob.len() + reserved;
+    blob.extend_from_slice(&extra);
+
+    blob.push(0); // RingCT type: null
+    write_varint(&mut blob, 0); // no other transactions
+    (blob, reserved_offset)
+}
+
+/// Rebuilds the hashing blob of a block built by [`template_blob`]
+///
+/// The block carries only the coinbase transaction, so the merkle root is the
+/// coinbase hash: Keccak over the prefix hash, the RingCT base hash and an
+/// empty prunable hash.
+fn hashing_blob(block: &[u8]) -> Vec<u8> {
+    let header = header_len(block);
+    // The block ends with the RingCT type byte and a zero transaction count
+    let tx_end = block.len() - 1;
+    let prefix_end = tx_end - 1;
+
+    let mut parts = Vec::with_capacity(96);
+    parts.extend_from_slice(&keccak(&block[header..prefix_end]));
+    parts.extend_from_slice(&keccak(&block[prefix_end..tx_end]));
+    parts.extend_from_slice(&[0u8; 32]);
+
+    let mut blob = block[..header].to_vec();
+    blob.extend_from_slice(&keccak(&parts));
+    write_varint(&mut blob, 1);
+    blob
+}
+
+/// Checks a proof-of-work hash against a difficulty like monerod
+///
+/// The hash is a little-endian 256-bit number; it is valid when multiplying
+/// it by the difficulty does not overflow 256 bits.
+fn check_hash(hash: &[u8; 32], difficulty: u64) -> bool {
+    let mut carry = 0u128;
+    for word in hash.chunks(8) {
+        let word = u64::from_le_bytes(word.try_into().unwrap()) as u128;
+        carry = (word * difficulty as u128 + carry) >> 64;
+    }
+    carry == 0
+}
+
+/// Length of the block header at the start of `blob`
+fn header_len(blob: &[u8]) -> usize {
+    let mut pos = 0;
+    for _ in 0..3 {
+        read_varint(blob, &mut pos);
+    }
+    pos + 32 + NONCE_SIZE
+}
+
+/// Reads timestamp, previous block id and nonce from a block header
+fn parse_header(blob: &[u8]) -> Option<(u64, [u8; 32], u32)> {
+    let mut pos = 0;
+    read_varint(blob, &mut pos)?;
+    read_varint(blob, &mut pos)?;
+    let timestamp = read_varint(blob, &mut pos)?;
+    let prev_hash = blob.get(pos..pos + 32)?.try_into().ok()?;
+    pos += 32;
+    let nonce = u32::from_le_bytes(blob.get(pos..pos + NONCE_SIZE)?.try_into().ok()?);
+    Some((timestamp, prev_hash, nonce))
+}
+
+/// Reads a CryptoNote varint, advancing `pos`
+fn read_varint(blob: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *blob.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Appends a CryptoNote varint
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn rpc_result(id: &Value, result: Value) -> Value {
+    json!({ "id": id, "jsonrpc": "2.0", "result": result })
+}
+
+fn rpc_error(id: &Value, code: i64, message: &str) -> Value {
+    json!({ "id": id, "jsonrpc": "2.0", "error": { "code": code, "message": message } })
+}
+
+fn keccak(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+fn md5_hex(data: &str) -> String {
+    hex::encode(Md5::digest(data.as_bytes()))
+}